@@ -0,0 +1,221 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use rayon::prelude::*;
+use serde::Serialize;
+
+use crate::character_sheet::CharacterSheet;
+use crate::template_registry::{RegistryError, TemplateRegistry};
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SheetFailure {
+    pub sheet: String,
+    /// The error's code, e.g. "perk_not_allowed", or "unreadable_sheet" when the file couldn't be parsed
+    pub code: String,
+    pub message: String
+}
+
+/**
+ * 
+ * The outcome of validating many sheets at once. Failures are sorted by sheet
+ * and counted by error code, so a template change's impact can be seen at a glance.
+ * 
+*/
+#[derive(Serialize, Debug, Clone, Default, PartialEq)]
+pub struct BatchReport {
+    pub total: usize,
+    pub valid: usize,
+    pub failures: Vec<SheetFailure>,
+    pub failures_by_code: BTreeMap<String, usize>
+}
+
+impl BatchReport {
+
+    pub fn is_success(&self) -> bool {
+        self.failures.is_empty()
+    }
+
+    fn from_failures(total: usize, mut failures: Vec<SheetFailure>) -> Self {
+
+        failures.sort_by(|a, b| a.sheet.cmp(&b.sheet));
+
+        let mut failures_by_code = BTreeMap::new();
+        for failure in &failures {
+            *failures_by_code.entry(failure.code.clone()).or_insert(0) += 1;
+        }
+
+        Self {
+            total,
+            valid: total - failures.len(),
+            failures,
+            failures_by_code
+        }
+
+    }
+
+    /// A plain text summary with a row per error code, followed by each failure
+    pub fn render_table(&self) -> String {
+
+        let mut table = format!("{} sheets, {} valid, {} invalid\n", self.total, self.valid, self.failures.len());
+
+        if self.failures.is_empty() {
+            return table;
+        }
+
+        let width = self.failures_by_code.keys().map(|c| c.len()).max().unwrap_or(0).max("error".len());
+
+        table.push_str(&format!("\n{:<width$}  count\n", "error"));
+        for (code, count) in &self.failures_by_code {
+            table.push_str(&format!("{:<width$}  {}\n", code, count));
+        }
+
+        table.push('\n');
+        for failure in &self.failures {
+            table.push_str(&format!("{}: {}\n", failure.sheet, failure.message));
+        }
+
+        table
+
+    }
+
+}
+
+impl TemplateRegistry {
+
+    /// Validates named sheets in parallel, each against the template it was made with
+    pub fn validate_batch(&self, sheets: &[(String, CharacterSheet)]) -> BatchReport {
+
+        let failures = sheets
+            .par_iter()
+            .filter_map(|(name, sheet)| {
+
+                self.validate(sheet).err().map(|e| SheetFailure {
+                    sheet: name.clone(),
+                    code: e.code().to_string(),
+                    message: e.to_string()
+                })
+
+            })
+            .collect();
+
+        BatchReport::from_failures(sheets.len(), failures)
+
+    }
+
+    /// Reads and validates every .json and .json5 sheet in a directory in parallel
+    pub fn validate_dir(&self, dir: impl AsRef<Path>) -> Result<BatchReport, RegistryError> {
+
+        let dir = dir.as_ref();
+
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .map_err(|e| RegistryError::Io { path: dir.display().to_string(), message: e.to_string() })?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json" || ext == "json5"))
+            .collect();
+
+        paths.sort();
+
+        let failures = paths
+            .par_iter()
+            .filter_map(|path| {
+
+                let failure = |code: &str, message: String| SheetFailure {
+                    sheet: path.display().to_string(),
+                    code: code.to_string(),
+                    message
+                };
+
+                let sheet: CharacterSheet = match fs::read_to_string(path).map_err(|e| e.to_string()).and_then(|c| json5::from_str(&c).map_err(|e| e.to_string())) {
+                    Ok(sheet) => sheet,
+                    Err(e)    => return Some(failure("unreadable_sheet", e))
+                };
+
+                self.validate(&sheet).err().map(|e| failure(e.code(), e.to_string()))
+
+            })
+            .collect();
+
+        Ok(BatchReport::from_failures(paths.len(), failures))
+
+    }
+
+}
+
+#[cfg(test)]
+mod batch_tests {
+
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+
+    fn registry() -> TemplateRegistry {
+
+        let mut registry = TemplateRegistry::new();
+        registry.add_str("standard.json5", STANDARD_TEMPLATE_STR).unwrap();
+        registry
+
+    }
+
+    fn sheets() -> Vec<(String, CharacterSheet)> {
+
+        let sample: CharacterSheet = serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap();
+
+        let mut unknown_perk = sample.clone();
+        unknown_perk.perks = Some(vec!["Flying".to_string()]);
+
+        let mut other_perk = sample.clone();
+        other_perk.perks = Some(vec!["Swimming".to_string()]);
+
+        let mut old_version = sample.clone();
+        old_version.template.version = [0, 1, 0];
+
+        vec![
+            ("sample".to_string(), sample),
+            ("unknown perk".to_string(), unknown_perk),
+            ("other perk".to_string(), other_perk),
+            ("old version".to_string(), old_version)
+        ]
+
+    }
+
+    #[test]
+    fn batch_report() {
+
+        let report = registry().validate_batch(&sheets());
+
+        assert_eq!(report.total, 4);
+        assert_eq!(report.valid, 1);
+        assert_eq!(report.failures_by_code.get("perk_not_allowed"), Some(&2));
+        assert_eq!(report.failures_by_code.get("outdated_template"), Some(&1));
+        assert_eq!(report.failures[0].sheet, "old version");
+
+        let table = report.render_table();
+        assert!(table.starts_with("4 sheets, 1 valid, 3 invalid"));
+        assert!(table.contains("perk_not_allowed   2"));
+
+    }
+
+    #[test]
+    fn batch_directory() {
+
+        let dir = std::env::temp_dir().join(format!("character-sheet-batch-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+
+        for (name, sheet) in sheets() {
+            fs::write(dir.join(format!("{}.json", name)), sheet.as_json_str()).unwrap();
+        }
+        fs::write(dir.join("broken.json"), "{").unwrap();
+
+        let report = registry().validate_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        let report = report.unwrap();
+        assert_eq!(report.total, 5);
+        assert_eq!(report.failures_by_code.get("unreadable_sheet"), Some(&1));
+        assert!(!report.is_success());
+
+    }
+
+}
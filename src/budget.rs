@@ -0,0 +1,147 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::character_sheet::CharacterSheet;
+use crate::character_template::CharacterTemplate;
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct PointBudget {
+    pub spent: i64,
+    pub given: i64
+}
+
+impl PointBudget {
+
+    /// Negative when the sheet has spent more than it was given
+    pub fn remaining(&self) -> i64 {
+        self.given - self.spent
+    }
+
+}
+
+/**
+ * 
+ * How many points a sheet has spent in each of the template's allotments.
+ * Categories the template doesn't allot points for are left out.
+ * 
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct SheetBudget {
+    pub attributes: PointBudget,
+    pub skills: Option<PointBudget>,
+    pub perks: Option<PointBudget>,
+    pub weapon_proficiencies: Option<PointBudget>
+}
+
+impl SheetBudget {
+
+    pub fn new(template: &CharacterTemplate, sheet: &CharacterSheet) -> Self {
+
+        let sheet = &sheet.resolve_ids(template);
+        let allotments = &template.allotments;
+
+        let attributes = PointBudget {
+            spent: sheet.attributes.iter().map(|a| a.value).sum(),
+            given: allotments.attributes.given_points
+        };
+
+        let skills = allotments.skills.map(|points| PointBudget {
+            spent: sheet.attributes
+                .iter()
+                .flat_map(|a| a.skills.iter().flatten())
+                .map(|s| s.value)
+                .sum(),
+            given: points.given_points
+        });
+
+        let perks = allotments.perks.map(|points| PointBudget {
+            spent: template.sheet_perks(sheet).map(|p| p.point_cost).sum(),
+            given: points.given_points
+        });
+
+        let weapon_proficiencies = allotments.weapon_proficiencies
+            .zip(template.weapon_proficiencies.as_ref())
+            .map(|(points, proficiencies)| {
+
+                let weapons: i64 = proficiencies
+                    .charged_weapons(sheet)
+                    .map(|w| w.point_cost.unwrap_or(0))
+                    .sum();
+
+                let categories: i64 = sheet.weapon_categories
+                    .iter()
+                    .flatten()
+                    .filter_map(|c| proficiencies.find_category(c))
+                    .map(|c| c.point_cost.unwrap_or(0))
+                    .sum();
+
+                PointBudget {
+                    spent: weapons + categories,
+                    given: points.given_points
+                }
+
+            });
+
+        Self {
+            attributes,
+            skills,
+            perks,
+            weapon_proficiencies
+        }
+
+    }
+
+    /// Each category's name with its budget, in the order they appear on a sheet
+    pub fn categories(&self) -> Vec<(&'static str, &PointBudget)> {
+
+        [
+            ("attributes", Some(&self.attributes)),
+            ("skills", self.skills.as_ref()),
+            ("perks", self.perks.as_ref()),
+            ("weapon_proficiencies", self.weapon_proficiencies.as_ref())
+        ]
+        .into_iter()
+        .filter_map(|(name, budget)| budget.map(|b| (name, b)))
+        .collect()
+
+    }
+
+}
+
+#[cfg(test)]
+mod budget_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    #[test]
+    fn sample_budget() {
+
+        let budget = SheetBudget::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE);
+
+        assert_eq!(budget.attributes, PointBudget { spent: 15, given: 15 });
+        assert_eq!(budget.skills.as_ref().unwrap().spent, 20);
+
+        // Charismatic 2 + Small Frame 1
+        assert_eq!(budget.perks.as_ref().unwrap().spent, 3);
+        assert_eq!(budget.perks.as_ref().unwrap().remaining(), 0);
+
+        assert_eq!(budget.categories().len(), 4);
+
+    }
+
+}
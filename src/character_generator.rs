@@ -0,0 +1,478 @@
+use std::collections::{HashMap, HashSet};
+
+use rand::distributions::WeightedIndex;
+use rand::prelude::*;
+use thiserror::Error;
+
+use crate::character_sheet::config::CHARACTER_SHEET_CONFIG;
+use crate::character_sheet::CharacterSheet;
+use crate::character_sheet_validator::{CharacterSheetError, CharacterSheetValidator};
+use crate::character_template::CharacterTemplate;
+use crate::effective_stats::{derived_armor_class, derived_health};
+
+/// The weight a focused attribute and its skills get, everything else has a weight of 1
+pub const FOCUS_WEIGHT: u32 = 4;
+
+const NAME_SYLLABLES: &[&str] = &[
+    "ka", "ren", "sol", "vi", "dar", "ta", "lo", "mer", "zan", "ri", "eth", "os", "bel", "nu", "cor", "ya"
+];
+
+#[derive(Error, Debug, PartialEq)]
+pub enum GeneratorError {
+    #[error("Character template has no attribute or skill named {0}")]
+    UnknownName(String),
+    #[error("Could not generate a valid sheet for the template: {0}")]
+    NoValidSheet(CharacterSheetError)
+}
+
+/**
+ *
+ * Builds random sheets for a template, for quick NPCs. Attribute and skill points are handed
+ * out one at a time, weighted towards the names given to `with_weight` or `with_focus`, then
+ * perks and weapon proficiencies are picked to meet their allotments' limits, counts and
+ * must-spend rules, from the ones whose requirements are met. Every sheet is checked with
+ * `CharacterSheetValidator` before it is returned.
+ *
+*/
+pub struct CharacterGenerator<'a> {
+    template: &'a CharacterTemplate,
+    weights: HashMap<String, u32>
+}
+
+impl<'a> CharacterGenerator<'a> {
+
+    pub fn new(template: &'a CharacterTemplate) -> Self {
+
+        Self {
+            template,
+            weights: HashMap::new()
+        }
+
+    }
+
+    /// Weights an attribute or skill relative to the default of 1, a weight of 0 leaves it at its minimum
+    pub fn with_weight(mut self, name: &str, weight: u32) -> Self {
+
+        self.weights.insert(name.to_string(), weight);
+        self
+
+    }
+
+    /// Favors an attribute and its skills, e.g. an "Agility" focus for a nimble character
+    pub fn with_focus(mut self, attribute: &str) -> Self {
+
+        let skills = self.template
+            .find_attribute(attribute)
+            .into_iter()
+            .flat_map(|a| a.skills.iter().flatten())
+            .map(|s| s.name.clone());
+
+        for name in skills.chain([attribute.to_string()]) {
+            self.weights.insert(name, FOCUS_WEIGHT);
+        }
+
+        self
+
+    }
+
+    pub fn generate<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<CharacterSheet, GeneratorError> {
+
+        if let Some(name) = self.weights.keys().find(|name| !self.has_name(name)) {
+            return Err(GeneratorError::UnknownName(name.clone()));
+        }
+
+        let sheet = self.build(rng);
+
+        // Allotting and picking already follow the template's rules, so this only fails for
+        // templates whose rules can't all be met
+        CharacterSheetValidator::new(self.template, &sheet)
+            .check()
+            .map(|_| sheet)
+            .map_err(GeneratorError::NoValidSheet)
+
+    }
+
+    fn build<R: Rng + ?Sized>(&self, rng: &mut R) -> CharacterSheet {
+
+        let mut sheet = self.template.get_base_character_sheet();
+        sheet.name = random_name(rng);
+
+        self.allot_attributes(&mut sheet, rng);
+        self.allot_skills(&mut sheet, rng);
+        self.pick_perks(&mut sheet, rng);
+        self.pick_weapons(&mut sheet, rng);
+
+        sheet.health = derived_health(self.template, &sheet);
+        sheet.armor_class = derived_armor_class(self.template, &sheet);
+
+        sheet
+
+    }
+
+    fn allot_attributes<R: Rng + ?Sized>(&self, sheet: &mut CharacterSheet, rng: &mut R) {
+
+        let points = &self.template.allotments.attributes;
+        let allotment_min = points.min_points_per_allotment.unwrap_or(0).max(0);
+        let max = points.max_points_per_allotment.unwrap_or(i64::MAX);
+
+        let mut slots: Vec<Slot> = sheet.attributes
+            .iter()
+            .map(|a| {
+
+                let min = self.template
+                    .find_attribute(&a.name)
+                    .and_then(|ta| ta.min_points)
+                    .map_or(allotment_min, |m| m.max(allotment_min));
+
+                Slot { value: min, max, weight: self.weight(&a.name) }
+
+            })
+            .collect();
+
+        allot(&mut slots, points.given_points, rng);
+
+        for (attribute, slot) in sheet.attributes.iter_mut().zip(slots) {
+            attribute.value = slot.value;
+        }
+
+    }
+
+    fn allot_skills<R: Rng + ?Sized>(&self, sheet: &mut CharacterSheet, rng: &mut R) {
+
+        let Some(points) = &self.template.allotments.skills else {
+            return;
+        };
+
+        let min = points.min_points_per_allotment.unwrap_or(0).max(0);
+        let max = points.max_points_per_allotment.unwrap_or(i64::MAX);
+
+        let skills = sheet.attributes.iter_mut().flat_map(|a| a.skills.iter_mut().flatten());
+        let mut skills: Vec<_> = skills.collect();
+
+        let mut slots: Vec<Slot> = skills
+            .iter()
+            .map(|s| Slot { value: min, max, weight: self.weight(&s.name) })
+            .collect();
+
+        allot(&mut slots, points.given_points, rng);
+
+        for (skill, slot) in skills.iter_mut().zip(slots) {
+            skill.value = slot.value;
+        }
+
+    }
+
+    /// Perks that raise what the generator favors are more likely to be picked
+    fn pick_perks<R: Rng + ?Sized>(&self, sheet: &mut CharacterSheet, rng: &mut R) {
+
+        let (Some(template_perks), Some(points)) = (&self.template.perks, &self.template.allotments.perks) else {
+            return;
+        };
+
+        let candidates = template_perks.iter().map(|perk| {
+
+            let attributes = perk.attributes.iter().flatten().filter(|m| m.value(sheet) > 0).map(|m| &m.name);
+            let skills = perk.skills.iter().flatten().filter(|m| m.value(sheet) > 0).map(|m| &m.name);
+            let weight = 1 + attributes.chain(skills).map(|name| self.weight(name).saturating_sub(1)).sum::<u32>();
+
+            (perk, weight)
+
+        });
+
+        let candidates: Vec<_> = weighted_order(candidates.collect(), rng)
+            .into_iter()
+            .map(|perk| (perk.name.clone(), perk.point_cost))
+            .collect();
+
+        let limits = Limits {
+            given: points.given_points,
+            min: points.min_perks.unwrap_or(0),
+            max: points.max_perks.unwrap_or(i64::MAX),
+            must_spend: points.must_spend_all == Some(true)
+        };
+
+        // When no pick meets the limits the validator reports why
+        sheet.perks = Some(pick(candidates, limits).unwrap_or_default());
+
+    }
+
+    fn pick_weapons<R: Rng + ?Sized>(&self, sheet: &mut CharacterSheet, rng: &mut R) {
+
+        let (Some(proficiencies), Some(points)) = (&self.template.weapon_proficiencies, &self.template.allotments.weapon_proficiencies) else {
+            return;
+        };
+
+        let candidates = proficiencies.categories
+            .iter()
+            .flat_map(|c| c.weapons.iter())
+            .filter(|w| w.required.as_ref().is_none_or(|r| r.is_met_by(sheet)))
+            .map(|w| {
+
+                let attack_with = w.skill.as_ref().or(w.attribute.as_ref());
+                (w, attack_with.map_or(1, |name| self.weight(name)).max(1))
+
+            })
+            .collect();
+
+        let candidates: Vec<_> = weighted_order(candidates, rng)
+            .into_iter()
+            .map(|weapon| (weapon.weapon.clone(), weapon.point_cost.unwrap_or(0)))
+            .collect();
+
+        let limits = Limits {
+            given: points.given_points,
+            min: points.min_proficiencies.unwrap_or(0),
+            max: points.max_proficiencies.unwrap_or(i64::MAX),
+            must_spend: points.must_spend_all == Some(true)
+        };
+
+        sheet.weapon_proficiencies = pick(candidates, limits).unwrap_or_default();
+
+    }
+
+    fn weight(&self, name: &str) -> u32 {
+        self.weights.get(name).copied().unwrap_or(1)
+    }
+
+    fn has_name(&self, name: &str) -> bool {
+
+        self.template.attributes.iter().any(|a| {
+            a.name == name || a.skills.iter().flatten().any(|s| s.name == name)
+        })
+
+    }
+
+}
+
+struct Slot {
+    value: i64,
+    max: i64,
+    weight: u32
+}
+
+/// Hands out the points left after the slots' starting values one at a time, by weight
+fn allot<R: Rng + ?Sized>(slots: &mut [Slot], given: i64, rng: &mut R) {
+
+    let mut remaining = given - slots.iter().map(|s| s.value).sum::<i64>();
+
+    while remaining > 0 {
+
+        let weights: Vec<u32> = slots
+            .iter()
+            .map(|s| if s.value < s.max { s.weight } else { 0 })
+            .collect();
+
+        // Every slot is full or has a weight of 0
+        let Ok(index) = WeightedIndex::new(&weights) else {
+            return;
+        };
+
+        slots[index.sample(rng)].value += 1;
+        remaining -= 1;
+
+    }
+
+}
+
+/// What a pick of perks or weapon proficiencies has to meet
+struct Limits {
+    given: i64,
+    min: i64,
+    max: i64,
+    must_spend: bool
+}
+
+/**
+ *
+ * Picks entries in order, taking each one as long as the rest can still make a pick that
+ * meets the limits, so without a minimum or must-spend it takes whatever is affordable. Dead
+ * ends are remembered by position, count and points spent, which bounds the search by the
+ * number of entries times the points given. Returns None when no pick meets the limits.
+ *
+*/
+fn pick(entries: Vec<(String, i64)>, limits: Limits) -> Option<Vec<String>> {
+
+    // The most points the entries from each position on could give back
+    let mut refunds = vec![0; entries.len() + 1];
+    for (i, (_, cost)) in entries.iter().enumerate().rev() {
+        refunds[i] = refunds[i + 1] + (*cost).min(0);
+    }
+
+    let mut picked = vec![];
+    let mut dead = HashSet::new();
+
+    search(&entries, &refunds, &limits, (0, 0, 0), &mut picked, &mut dead).then(|| {
+        picked.into_iter().map(|i| entries[i].0.clone()).collect()
+    })
+
+}
+
+fn search(entries: &[(String, i64)], refunds: &[i64], limits: &Limits, state: (usize, i64, i64), picked: &mut Vec<usize>, dead: &mut HashSet<(usize, i64, i64)>) -> bool {
+
+    let (i, count, spent) = state;
+
+    if count > limits.max || spent + refunds[i] > limits.given || dead.contains(&state) {
+        return false;
+    }
+
+    if i == entries.len() {
+        return count >= limits.min && (!limits.must_spend || spent == limits.given);
+    }
+
+    picked.push(i);
+    if search(entries, refunds, limits, (i + 1, count + 1, spent + entries[i].1), picked, dead) {
+        return true;
+    }
+
+    picked.pop();
+    if search(entries, refunds, limits, (i + 1, count, spent), picked, dead) {
+        return true;
+    }
+
+    dead.insert(state);
+    false
+
+}
+
+/// A random order where heavier entries tend to come first, entries with a weight of 0 come last
+fn weighted_order<T, R: Rng + ?Sized>(entries: Vec<(T, u32)>, rng: &mut R) -> Vec<T> {
+
+    let mut keyed: Vec<(f64, T)> = entries
+        .into_iter()
+        .map(|(entry, weight)| (rng.gen::<f64>().powf(1.0 / weight.max(1) as f64) * (weight.min(1) as f64), entry))
+        .collect();
+
+    keyed.sort_by(|(a, _), (b, _)| b.total_cmp(a));
+    keyed.into_iter().map(|(_, entry)| entry).collect()
+
+}
+
+fn random_name<R: Rng + ?Sized>(rng: &mut R) -> String {
+
+    let config = CHARACTER_SHEET_CONFIG.name;
+    let mut name = String::new();
+
+    while name.len() < config.min_length.max(4) as usize {
+        name.push_str(NAME_SYLLABLES.choose(rng).unwrap());
+    }
+
+    name.truncate(config.max_length.max(1) as usize);
+
+    let mut chars = name.chars();
+    chars.next().map_or(name.clone(), |first| first.to_uppercase().chain(chars).collect())
+
+}
+
+#[cfg(test)]
+mod character_generator_tests {
+
+    use std::sync::LazyLock;
+    use rand::rngs::StdRng;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    fn attribute_value(sheet: &CharacterSheet, name: &str) -> i64 {
+        sheet.attributes.iter().find(|a| a.name == name).unwrap().value
+    }
+
+    #[test]
+    fn generated_sheets_are_valid() {
+
+        let generator = CharacterGenerator::new(&STANDARD_TEMPLATE);
+
+        for seed in 0..50 {
+
+            let sheet = generator.generate(&mut StdRng::seed_from_u64(seed)).unwrap();
+            assert!(CharacterSheetValidator::new(&STANDARD_TEMPLATE, &sheet).check().is_ok());
+
+            let spent: i64 = sheet.attributes.iter().map(|a| a.value).sum();
+            assert_eq!(spent, STANDARD_TEMPLATE.allotments.attributes.given_points);
+
+        }
+
+    }
+
+    #[test]
+    fn same_seed_same_sheet() {
+
+        let generator = CharacterGenerator::new(&STANDARD_TEMPLATE);
+
+        let first = generator.generate(&mut StdRng::seed_from_u64(3)).unwrap();
+        let second = generator.generate(&mut StdRng::seed_from_u64(3)).unwrap();
+
+        assert_eq!(first.as_json_str(), second.as_json_str());
+
+    }
+
+    #[test]
+    fn focus_favors_the_attribute() {
+
+        let focused = CharacterGenerator::new(&STANDARD_TEMPLATE).with_focus("Agility");
+        let max = STANDARD_TEMPLATE.allotments.attributes.max_points_per_allotment.unwrap();
+
+        let total: i64 = (0..20)
+            .map(|seed| attribute_value(&focused.generate(&mut StdRng::seed_from_u64(seed)).unwrap(), "Agility"))
+            .sum();
+
+        // Spread evenly Agility would average well under half its maximum
+        assert!(total > 20 * max / 2, "{}", total);
+
+        let ignored = CharacterGenerator::new(&STANDARD_TEMPLATE).with_weight("Strength", 0);
+        let sheet = ignored.generate(&mut StdRng::seed_from_u64(1)).unwrap();
+        assert_eq!(attribute_value(&sheet, "Strength"), 0);
+
+    }
+
+    #[test]
+    fn respects_minimums_and_must_spend() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.allotments.attributes.min_points_per_allotment = Some(1);
+        template.allotments.skills.as_mut().unwrap().must_spend_all = Some(true);
+        template.allotments.weapon_proficiencies.as_mut().unwrap().min_proficiencies = Some(1);
+
+        let generator = CharacterGenerator::new(&template);
+        for seed in 0..10 {
+            assert!(generator.generate(&mut StdRng::seed_from_u64(seed)).is_ok());
+        }
+
+    }
+
+    #[test]
+    fn perks_and_weapons_meet_their_allotments() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        let perks = template.allotments.perks.as_mut().unwrap();
+        perks.min_perks = Some(4);
+        perks.must_spend_all = Some(true);
+        template.allotments.weapon_proficiencies.as_mut().unwrap().must_spend_all = Some(true);
+
+        let generator = CharacterGenerator::new(&template);
+        for seed in 0..20 {
+
+            let sheet = generator.generate(&mut StdRng::seed_from_u64(seed)).unwrap();
+            assert_eq!(sheet.perks.as_ref().unwrap().len(), 4);
+
+        }
+
+    }
+
+    #[test]
+    fn impossible_rules() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.allotments.attributes.min_points_per_allotment = Some(5);
+
+        let result = CharacterGenerator::new(&template).generate(&mut StdRng::seed_from_u64(1));
+        assert!(matches!(result, Err(GeneratorError::NoValidSheet(CharacterSheetError::AttributePointsExceeded(_)))));
+
+        let result = CharacterGenerator::new(&STANDARD_TEMPLATE).with_weight("Luck", 2).generate(&mut StdRng::seed_from_u64(1));
+        assert_eq!(result.err(), Some(GeneratorError::UnknownName("Luck".to_string())));
+
+    }
+
+}
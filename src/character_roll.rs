@@ -1,11 +1,14 @@
+use std::fmt;
 
 use rand::prelude::*;
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
 use crate::character_sheet::CharacterSheet;
+use crate::character_state::CharacterState;
 use crate::character_template::common::{AttributeModifier, SkillModifier};
 use crate::character_template::CharacterTemplate;
+use crate::dice::{DiceExpression, DiceRollResult};
 
 
 #[derive(Error, Debug, PartialEq)]
@@ -13,33 +16,43 @@ pub enum RollError {
     #[error("Invalid attribute {0}")]
     InvalidAttribute(String),
     #[error("Invalid skill {0}")]
-    InvalidSkill(String)
+    InvalidSkill(String),
+    #[error("Invalid weapon {0}")]
+    InvalidWeapon(String),
+    #[error("Weapon {0} has no attribute or skill to attack with")]
+    WeaponNotRollable(String),
+    #[error("Invalid damage expression {damage} for weapon {weapon}")]
+    InvalidDamage {
+        weapon: String,
+        damage: String
+    }
 }
 
 #[derive(Serialize, Deserialize, Clone)]
 pub enum RollTarget {
     Attribute(String),
-    Skill(String)
+    Skill(String),
+    Attack(String)
 }
 
 impl RollTarget {
 
-    pub fn to_string(&self) -> String {
+    pub fn as_str(&self) -> &str {
 
         match self {
-            RollTarget::Attribute(attr) => attr.clone(),
-            RollTarget::Skill(skill)     => skill.clone()
+            RollTarget::Attribute(attr) => attr.as_str(),
+            RollTarget::Skill(skill)     => skill.as_str(),
+            RollTarget::Attack(weapon)   => weapon.as_str()
         }
 
     }
 
-    pub fn as_str(&self) -> &str {
+}
 
-        match self {
-            RollTarget::Attribute(attr) => attr.as_str(),
-            RollTarget::Skill(skill)     => skill.as_str()
-        }
+impl fmt::Display for RollTarget {
 
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
     }
 
 }
@@ -47,7 +60,52 @@ impl RollTarget {
 pub struct CharacterRoll<'a> {
     pub template: &'a CharacterTemplate,
     pub sheet: &'a CharacterSheet,
-    pub roll_type: RollTarget
+    pub roll_type: RollTarget,
+    /// Conditions on the character's current state modify the roll
+    pub state: Option<&'a CharacterState>,
+    /// Situations in play, e.g. "Darkness", that enable situational perk modifiers
+    pub situations: &'a [String]
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct AppliedSituationalModifier {
+    pub perk: String,
+    /// The active situations that enabled the modifier
+    pub situations: Vec<String>,
+    pub modifier: i64
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ModifierSource {
+    Attribute,
+    Skill,
+    Perk,
+    Equipment,
+    Condition,
+    Situational,
+    Proficiency
+}
+
+/// A single contribution to a roll's modifier, e.g. "Small Frame +2"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ModifierEntry {
+    pub source: ModifierSource,
+    pub name: String,
+    pub modifier: i64
+}
+
+impl ModifierEntry {
+
+    fn new(source: ModifierSource, name: &str, modifier: i64) -> Self {
+
+        Self {
+            source,
+            name: name.to_string(),
+            modifier
+        }
+
+    }
+
 }
 
 #[derive(Serialize, Deserialize, Debug)]
@@ -55,120 +113,376 @@ pub struct CharacterRollResult {
     pub target: String,
     pub value: i64,
     pub roll: i64,
-    pub modifier: i64
+    pub modifier: i64,
+    /// Only present for attack rolls with a weapon that deals damage
+    pub damage: Option<DiceRollResult>,
+    /// The situational modifiers included in `modifier`
+    pub situational: Vec<AppliedSituationalModifier>,
+    /// Every source that makes up `modifier`, in the order they were applied
+    pub breakdown: Vec<ModifierEntry>
+}
+
+impl CharacterRollResult {
+
+    /**
+     * 
+     * Renders the roll for chat, e.g.
+     * "Acrobatics: 17 = 11 (d20) + 4 (Agility) + 0 (Acrobatics) + 2 (Small Frame)"
+     * 
+    */
+    pub fn render(&self) -> String {
+
+        let mut rendered = format!("{}: {} = {} (d20)", self.target, self.value, self.roll);
+
+        for entry in &self.breakdown {
+
+            let sign = if entry.modifier < 0 { '-' } else { '+' };
+            rendered.push_str(&format!(" {} {} ({})", sign, entry.modifier.abs(), entry.name));
+
+        }
+
+        if let Some(damage) = &self.damage {
+            rendered.push_str(&format!(", {} damage ({})", damage.total, damage.expression));
+        }
+
+        rendered
+
+    }
+
 }
 
 impl<'a> CharacterRoll<'a> {
 
+    pub fn new(template: &'a CharacterTemplate, sheet: &'a CharacterSheet, roll_type: RollTarget) -> Self {
+
+        Self {
+            template,
+            sheet,
+            roll_type,
+            state: None,
+            situations: &[]
+        }
+
+    }
+
+    pub fn with_state(mut self, state: &'a CharacterState) -> Self {
+
+        self.state = Some(state);
+        self
+
+    }
+
+    pub fn with_situations(mut self, situations: &'a [String]) -> Self {
+
+        self.situations = situations;
+        self
+
+    }
+
     pub fn roll(&self) -> Result<CharacterRollResult, RollError> {
+        self.roll_with_rng(&mut thread_rng())
+    }
+
+    pub fn roll_with_rng<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<CharacterRollResult, RollError> {
+        self.resolved(|roll| roll.roll_resolved(rng))
+    }
+
+    /// The total modifier the roll would add to the d20, without rolling
+    pub fn modifier(&self) -> Result<i64, RollError> {
+
+        self.resolved(|roll| {
+            let (breakdown, _) = roll.breakdown()?;
+            Ok(breakdown.iter().map(|e| e.modifier).sum())
+        })
+
+    }
+
+    /// Runs `f` with the sheet's ids replaced by names, which is what the rest of the roll looks up
+    fn resolved<T>(&self, f: impl FnOnce(&CharacterRoll) -> T) -> T {
+
+        let sheet = self.sheet.resolve_ids(self.template);
+        f(&CharacterRoll { sheet: &sheet, roll_type: self.roll_type.clone(), ..*self })
+
+    }
+
+    fn roll_resolved<R: Rng + ?Sized>(&self, rng: &mut R) -> Result<CharacterRollResult, RollError> {
 
-        let mut rng   = thread_rng();
         let base_roll = rng.gen_range(1..=20);
         
-        let modifier = match &self.roll_type {
-            RollTarget::Attribute(_) => self.get_attribute_modifier(),
-            RollTarget::Skill(_)     => self.get_skill_modifier()
-        }?;
+        let damage = match &self.roll_type {
+            RollTarget::Attack(weapon) => self.get_weapon_damage(weapon)?.map(|d| d.roll(rng)),
+            _                          => None
+        };
+
+        let (breakdown, situational) = self.breakdown()?;
+        let modifier = breakdown.iter().map(|e| e.modifier).sum();
 
         Ok(CharacterRollResult {
             target: self.roll_type.to_string(),
             value: base_roll + modifier,
             roll: base_roll,
-            modifier: modifier
+            modifier,
+            damage,
+            situational,
+            breakdown
         })
 
     }
 
-    fn get_attribute_modifier(&self) -> Result<i64, RollError> {
+    fn breakdown(&self) -> Result<(Vec<ModifierEntry>, Vec<AppliedSituationalModifier>), RollError> {
+
+        let mut breakdown = match &self.roll_type {
+            RollTarget::Attribute(attr) => self.get_attribute_modifier(attr)?,
+            RollTarget::Skill(skill)    => self.get_skill_modifier(skill)?,
+            RollTarget::Attack(weapon)  => self.get_attack_modifier(weapon)?
+        };
+
+        let situational = match &self.roll_type {
+            RollTarget::Attack(weapon) => self.situational_modifiers(&self.attack_target(weapon)?),
+            target                     => self.situational_modifiers(target)
+        };
+
+        breakdown.extend(situational.iter().map(|m| ModifierEntry::new(ModifierSource::Situational, &m.perk, m.modifier)));
+
+        if let Some(state) = self.state {
+
+            breakdown.extend(state.conditions
+                .iter()
+                .filter_map(|c| c.roll_modifier.filter(|m| *m != 0).map(|m| ModifierEntry::new(ModifierSource::Condition, &c.name, m))));
 
-        if let Some(attr) = self.sheet.attributes.iter().find(|a| a.name == self.roll_type.as_str()) {
-            return Ok(attr.value + self.perk_modifier());
         }
 
-        Err(RollError::InvalidAttribute(self.roll_type.to_string()))
+        Ok((breakdown, situational))
 
     }
 
-    fn get_skill_modifier(&self) -> Result<i64, RollError> {
+    fn get_attribute_modifier(&self, name: &str) -> Result<Vec<ModifierEntry>, RollError> {
+
+        let Some(attr) = self.sheet.attributes.iter().find(|a| a.name == name) else {
+            return Err(RollError::InvalidAttribute(name.to_string()));
+        };
+
+        let target = RollTarget::Attribute(name.to_string());
+
+        let mut breakdown = vec![ModifierEntry::new(ModifierSource::Attribute, name, attr.value)];
+        breakdown.extend(self.perk_modifier(&target));
+        breakdown.extend(self.equipment_modifier(&target));
+        breakdown.extend(self.condition_modifier(&target));
+
+        Ok(breakdown)
 
-        let perk_modifier = self.perk_modifier();
+    }
+
+    fn get_skill_modifier(&self, skill_name: &str) -> Result<Vec<ModifierEntry>, RollError> {
 
         let attr = self.sheet.attributes
             .iter()
             .find(|a| {
 
-                if a.skills.is_none() {
-                    return false;
-                }
-
-                let skill_name = self.roll_type.as_str();
-
-                a.skills.as_ref().unwrap()
-                    .iter()
-                    .find(|s| s.name == skill_name)
-                    .is_some()
+                a.skills
+                    .as_ref()
+                    .is_some_and(|skills| skills.iter().any(|s| s.name == skill_name))
 
             });
 
-        if attr.is_none() {
-            return Err(RollError::InvalidSkill(self.roll_type.to_string()));
-        }
+        let Some(attr) = attr else {
+            return Err(RollError::InvalidSkill(skill_name.to_string()));
+        };
 
-        let attr = attr.unwrap();
         let skill = attr.skills
             .as_ref()
             .unwrap()
             .iter()
-            .find(|s| s.name == self.roll_type.as_str())
+            .find(|s| s.name == skill_name)
             .unwrap();
 
-        Ok(skill.value + attr.value + perk_modifier)
+        let target = RollTarget::Skill(skill_name.to_string());
+
+        let mut breakdown = vec![
+            ModifierEntry::new(ModifierSource::Attribute, &attr.name, attr.value),
+            ModifierEntry::new(ModifierSource::Skill, skill_name, skill.value)
+        ];
+
+        if self.template.skills_inherit_attribute_modifiers() {
+
+            let attr_target = RollTarget::Attribute(attr.name.clone());
+            breakdown.extend(self.perk_modifier(&attr_target));
+            breakdown.extend(self.equipment_modifier(&attr_target));
+            breakdown.extend(self.condition_modifier(&attr_target));
+
+        }
+
+        breakdown.extend(self.perk_modifier(&target));
+        breakdown.extend(self.equipment_modifier(&target));
+        breakdown.extend(self.condition_modifier(&target));
+
+        Ok(breakdown)
 
     }
 
-    fn perk_modifier(&self) -> i64 {
+    fn get_attack_modifier(&self, weapon_name: &str) -> Result<Vec<ModifierEntry>, RollError> {
+
+        let proficiencies = self.template.weapon_proficiencies
+            .as_ref()
+            .ok_or_else(|| RollError::InvalidWeapon(weapon_name.to_string()))?;
+
+        let mut breakdown = match self.attack_target(weapon_name)? {
+            RollTarget::Skill(skill)    => self.get_skill_modifier(&skill)?,
+            RollTarget::Attribute(attr) => self.get_attribute_modifier(&attr)?,
+            RollTarget::Attack(_)       => unreachable!()
+        };
 
-        if self.template.perks.is_none() || self.sheet.perks.is_none() {
-            return 0;
+        let (name, proficiency_modifier) = if proficiencies.is_proficient(self.sheet, weapon_name) {
+            ("Proficient", proficiencies.proficiency_bonus.unwrap_or(0))
+        } else {
+            ("Not Proficient", -proficiencies.non_proficiency_penalty.unwrap_or(0))
+        };
+
+        if proficiency_modifier != 0 {
+            breakdown.push(ModifierEntry::new(ModifierSource::Proficiency, name, proficiency_modifier));
         }
 
-        let t_perks = self.template.perks.as_ref().unwrap();
-        let s_perks = self.sheet.perks.as_ref().unwrap();
+        Ok(breakdown)
 
-        let modifier: i64 = match &self.roll_type {
+    }
 
-            RollTarget::Attribute(attr) => {
+    /// The skill or attribute a weapon attacks with
+    fn attack_target(&self, weapon_name: &str) -> Result<RollTarget, RollError> {
 
-                let attr_modifiers: Vec<&AttributeModifier> = t_perks
-                    .iter()
-                    .filter(|p| s_perks.contains(&p.name)) // Filter out perks that the character doesn't have
-                    .filter(|f| f.attributes.is_some())    // Filter out perks that don't have attribute modifiers
-                    .map(|p| p.attributes.as_ref().unwrap()) // Get the attribute modifiers
-                    .flatten()
-                    .filter(|t_attr| t_attr.name == *attr) // Filter out attribute modifiers that don't match the target attribute
-                    .collect();
+        let weapon = self.template.weapon_proficiencies
+            .as_ref()
+            .and_then(|wp| wp.find_weapon(weapon_name))
+            .ok_or_else(|| RollError::InvalidWeapon(weapon_name.to_string()))?;
+
+        match (&weapon.skill, &weapon.attribute) {
+            (Some(skill), _)    => Ok(RollTarget::Skill(skill.clone())),
+            (None, Some(attr))  => Ok(RollTarget::Attribute(attr.clone())),
+            (None, None)        => Err(RollError::WeaponNotRollable(weapon_name.to_string()))
+        }
+
+    }
+
+    fn get_weapon_damage(&self, weapon_name: &str) -> Result<Option<DiceExpression>, RollError> {
+
+        let weapon = self.template.weapon_proficiencies
+            .as_ref()
+            .and_then(|wp| wp.find_weapon(weapon_name))
+            .ok_or_else(|| RollError::InvalidWeapon(weapon_name.to_string()))?;
+
+        let Some(damage) = &weapon.damage else {
+            return Ok(None);
+        };
+
+        damage
+            .parse()
+            .map(Some)
+            .map_err(|_| RollError::InvalidDamage {
+                weapon: weapon_name.to_string(),
+                damage: damage.clone()
+            })
+
+    }
+
+    /// Sums the attribute or skill modifiers that match the target
+    fn target_modifier(&self, target: &RollTarget, attributes: Option<&Vec<AttributeModifier>>, skills: Option<&Vec<SkillModifier>>) -> i64 {
+
+        match target {
+            RollTarget::Attribute(attr) => attributes
+                .into_iter()
+                .flatten()
+                .filter(|m| m.name == *attr)
+                .map(|m| m.value(self.sheet))
+                .sum(),
+            RollTarget::Skill(skill) => skills
+                .into_iter()
+                .flatten()
+                .filter(|m| m.name == *skill)
+                .map(|m| m.value(self.sheet))
+                .sum(),
+            RollTarget::Attack(_) => 0
+        }
+
+    }
+
+    /// One entry per perk the character has that modifies the target
+    fn perk_modifier(&self, target: &RollTarget) -> Vec<ModifierEntry> {
 
-                attr_modifiers.iter().map(|m| m.modifier).sum()
+        self.template
+            .sheet_perks(self.sheet)
+            .map(|p| ModifierEntry::new(ModifierSource::Perk, &p.name, self.target_modifier(target, p.attributes.as_ref(), p.skills.as_ref())))
+            .filter(|e| e.modifier != 0)
+            .collect()
 
-            },
-            RollTarget::Skill(skill) => {
+    }
+
+    fn equipment_modifier(&self, target: &RollTarget) -> Vec<ModifierEntry> {
+
+        self.template
+            .sheet_equipped(self.sheet)
+            .map(|e| ModifierEntry::new(ModifierSource::Equipment, &e.name, self.target_modifier(target, e.attributes.as_ref(), e.skills.as_ref())))
+            .filter(|e| e.modifier != 0)
+            .collect()
+
+    }
+
+    fn condition_modifier(&self, target: &RollTarget) -> Vec<ModifierEntry> {
+
+        let Some(state) = self.state else {
+            return vec![];
+        };
+
+        state.conditions
+            .iter()
+            .map(|c| ModifierEntry::new(ModifierSource::Condition, &c.name, self.target_modifier(target, c.attributes.as_ref(), c.skills.as_ref())))
+            .filter(|e| e.modifier != 0)
+            .collect()
+
+    }
+
+    fn situational_modifiers(&self, target: &RollTarget) -> Vec<AppliedSituationalModifier> {
+
+        // Skills pick up their attribute's situational modifiers the same way they do every other modifier
+        let attr_target = match target {
+            RollTarget::Skill(skill) if self.template.skills_inherit_attribute_modifiers() => self.sheet.attributes
+                .iter()
+                .find(|a| a.skills.as_ref().is_some_and(|skills| skills.iter().any(|s| s.name == *skill)))
+                .map(|a| RollTarget::Attribute(a.name.clone())),
+            _ => None
+        };
+        let attr_target = attr_target.as_ref();
 
-                let skill_modifiers: Vec<&SkillModifier> = t_perks
+        self.template
+            .sheet_perks(self.sheet)
+            .flat_map(|perk| {
+
+                perk.situational
                     .iter()
-                    .filter(|p| s_perks.contains(&p.name)) // Filter out perks that the character doesn't have
-                    .filter(|f| f.skills.is_some())    // Filter out perks that don't have skill modifiers
-                    .map(|p| p.skills.as_ref().unwrap()) // Get the skill modifiers
                     .flatten()
-                    .filter(|t_skill| t_skill.name == *skill) // Filter out skill modifiers that don't match the target skill
-                    .collect();
+                    .filter_map(move |situational| {
 
-                skill_modifiers.iter().map(|m| m.modifier).sum()
+                        let situations: Vec<String> = situational.situations
+                            .iter()
+                            .filter(|s| self.situations.contains(s))
+                            .cloned()
+                            .collect();
 
-            }
+                        if situations.is_empty() {
+                            return None;
+                        }
 
-        };
+                        let modifier = self.target_modifier(target, situational.attributes.as_ref(), situational.skills.as_ref())
+                            + attr_target.map_or(0, |t| self.target_modifier(t, situational.attributes.as_ref(), situational.skills.as_ref()));
+
+                        (modifier != 0).then(|| AppliedSituationalModifier {
+                            perk: perk.name.clone(),
+                            situations,
+                            modifier
+                        })
+
+                    })
 
-        modifier
+            })
+            .collect()
 
     }
 
@@ -180,9 +494,13 @@ mod character_roll_tests {
     use std::sync::LazyLock;
     use super::*;
 
-    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json");
+    use rand::rngs::StdRng;
+    use crate::character_state::Condition;
+    use crate::character_template::common::SituationalModifier;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
     static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
-        CharacterTemplate::from_json_str(STANDARD_TEMPLATE_STR.to_string()).unwrap()
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
     });
 
     const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
@@ -193,11 +511,7 @@ mod character_roll_tests {
     #[test]
     fn test_roll_attribute() {
 
-        let character = CharacterRoll {
-            template: &STANDARD_TEMPLATE,
-            sheet: &CHARACTER_SHEET_SAMPLE,
-            roll_type: RollTarget::Attribute("Strength".to_string())
-        };
+        let character = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Attribute("Strength".to_string()));
 
         let roll = character.roll();
         assert!(roll.is_ok());
@@ -207,11 +521,7 @@ mod character_roll_tests {
     #[test]
     fn test_roll_fake_attribute() {
 
-        let character = CharacterRoll {
-            template: &STANDARD_TEMPLATE,
-            sheet: &CHARACTER_SHEET_SAMPLE,
-            roll_type: RollTarget::Attribute("Fake".to_string())
-        };
+        let character = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Attribute("Fake".to_string()));
 
         let roll = character.roll();
         assert!(roll.is_err());
@@ -222,15 +532,260 @@ mod character_roll_tests {
     #[test]
     fn test_roll_skill() {
 
-        let character = CharacterRoll {
-            template: &STANDARD_TEMPLATE,
-            sheet: &CHARACTER_SHEET_SAMPLE,
-            roll_type: RollTarget::Skill("Acrobatics".to_string())
-        };
+        let character = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Acrobatics".to_string()));
 
         let roll = dbg!(character.roll());
         assert!(roll.is_ok());
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_roll_attack() {
+
+        let character = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Attack("Pistol".to_string()));
+
+        let roll = character.roll_with_rng(&mut StdRng::seed_from_u64(1)).unwrap();
+
+        // Agility 4, Small Frame +2 and proficient with the pistol
+        let proficiency_bonus = STANDARD_TEMPLATE.weapon_proficiencies.as_ref().unwrap().proficiency_bonus.unwrap_or(0);
+        assert_eq!(roll.modifier, 4 + 2 + proficiency_bonus);
+        assert_eq!(roll.value, roll.roll + roll.modifier);
+
+        let damage = roll.damage.unwrap();
+        assert_eq!(damage.total, damage.rolls.iter().sum::<i64>() + damage.modifier);
+
+    }
+
+    #[test]
+    fn test_roll_attack_not_proficient() {
+
+        let character = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Attack("Rifle".to_string()));
+
+        let roll = character.roll_with_rng(&mut StdRng::seed_from_u64(1)).unwrap();
+
+        let penalty = STANDARD_TEMPLATE.weapon_proficiencies.as_ref().unwrap().non_proficiency_penalty.unwrap_or(0);
+        assert_eq!(roll.modifier, 4 + 2 - penalty);
+
+    }
+
+    #[test]
+    fn test_roll_fake_weapon() {
+
+        let character = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Attack("Fake".to_string()));
+
+        let roll = character.roll();
+        assert_eq!(roll.unwrap_err(), RollError::InvalidWeapon("Fake".to_string()));
+
+    }
+
+    #[test]
+    fn test_roll_with_conditions() {
+
+        let mut state = CharacterState::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE);
+        state.add_condition(Condition {
+            name: "Blinded".to_string(),
+            duration: Some(2),
+            roll_modifier: Some(-1),
+            attributes: Some(vec![AttributeModifier { name: "Agility".to_string(), modifier: -3, formula: None }]),
+            skills: None
+        });
+
+        let without_state = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Attribute("Agility".to_string()))
+            .roll()
+            .unwrap();
+
+        let with_state = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Attribute("Agility".to_string()))
+            .with_state(&state)
+            .roll()
+            .unwrap();
+
+        assert_eq!(with_state.modifier, without_state.modifier - 4);
+
+        // Only the general roll modifier applies to other attributes
+        let strength = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Attribute("Strength".to_string()))
+            .with_state(&state)
+            .roll()
+            .unwrap();
+
+        assert_eq!(strength.modifier, -3 - 1);
+
+    }
+
+    #[test]
+    fn test_roll_with_equipment() {
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        sheet.inventory = Some(vec!["Medkit".to_string()]);
+
+        let roll = CharacterRoll::new(&STANDARD_TEMPLATE, &sheet, RollTarget::Skill("Medicine".to_string())).roll().unwrap();
+        assert_eq!(roll.modifier, 10 + 5);
+
+        sheet.equipped = Some(vec!["Medkit".to_string()]);
+
+        let roll = CharacterRoll::new(&STANDARD_TEMPLATE, &sheet, RollTarget::Skill("Medicine".to_string())).roll().unwrap();
+        assert_eq!(roll.modifier, 10 + 5 + 2);
+
+    }
+
+    #[test]
+    fn test_roll_situational() {
+
+        let roll = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Stealth".to_string()))
+            .roll()
+            .unwrap();
+
+        assert!(roll.situational.is_empty());
+
+        let situations = vec!["Darkness".to_string(), "Raining".to_string()];
+        let dark_roll = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Stealth".to_string()))
+            .with_situations(&situations)
+            .roll()
+            .unwrap();
+
+        assert_eq!(dark_roll.modifier, roll.modifier + 2);
+        assert_eq!(dark_roll.situational, vec![AppliedSituationalModifier {
+            perk: "Small Frame".to_string(),
+            situations: vec!["Darkness".to_string()],
+            modifier: 2
+        }]);
+
+        // Situational modifiers only apply to their own skill
+        let other = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Acrobatics".to_string()))
+            .with_situations(&situations)
+            .roll()
+            .unwrap();
+
+        assert!(other.situational.is_empty());
+
+    }
+
+    #[test]
+    fn test_roll_breakdown() {
+
+        let mut state = CharacterState::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE);
+        state.add_condition(Condition {
+            name: "Stunned".to_string(),
+            duration: None,
+            roll_modifier: Some(-2),
+            attributes: None,
+            skills: None
+        });
+
+        let situations = vec!["Darkness".to_string()];
+        let roll = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Stealth".to_string()))
+            .with_state(&state)
+            .with_situations(&situations)
+            .roll_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+
+        let sources: Vec<(ModifierSource, &str)> = roll.breakdown.iter().map(|e| (e.source, e.name.as_str())).collect();
+        assert_eq!(sources, vec![
+            (ModifierSource::Attribute, "Agility"),
+            (ModifierSource::Skill, "Stealth"),
+            (ModifierSource::Perk, "Small Frame"),
+            (ModifierSource::Situational, "Small Frame"),
+            (ModifierSource::Condition, "Stunned")
+        ]);
+        assert_eq!(roll.modifier, roll.breakdown.iter().map(|e| e.modifier).sum::<i64>());
+
+        let rendered = roll.render();
+        assert!(rendered.starts_with(&format!("Stealth: {} = {} (d20) + 4 (Agility)", roll.value, roll.roll)));
+        assert!(rendered.ends_with("+ 2 (Small Frame) - 2 (Stunned)"));
+
+    }
+
+    #[test]
+    fn test_roll_attack_breakdown() {
+
+        let roll = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Attack("Rifle".to_string()))
+            .roll_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+
+        assert!(roll.breakdown.contains(&ModifierEntry::new(ModifierSource::Perk, "Small Frame", 2)));
+        assert_eq!(roll.breakdown.last().unwrap().source, ModifierSource::Proficiency);
+        assert!(roll.render().contains("damage"));
+
+    }
+
+    #[test]
+    fn test_skill_inherits_attribute_modifiers() {
+
+        let mut state = CharacterState::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE);
+        state.add_condition(Condition {
+            name: "Slowed".to_string(),
+            duration: None,
+            roll_modifier: None,
+            attributes: Some(vec![AttributeModifier { name: "Agility".to_string(), modifier: -1, formula: None }]),
+            skills: None
+        });
+
+        // Agility 4 + Acrobatics 0 + Small Frame's Agility +2 + Slowed's Agility -1
+        let roll = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Acrobatics".to_string()))
+            .with_state(&state)
+            .roll_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+
+        assert_eq!(roll.modifier, 4 + 2 - 1);
+
+        // A skill roll never includes modifiers to a different attribute, Charismatic only names Persuasion
+        let roll = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Deception".to_string()))
+            .roll_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+
+        assert_eq!(roll.modifier, 5 + 4);
+
+    }
+
+    #[test]
+    fn test_skill_without_inherited_modifiers() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.skills_inherit_attribute_modifiers = Some(false);
+
+        let roll = CharacterRoll::new(&template, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Acrobatics".to_string()))
+            .roll_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+
+        // Only the attribute's points
+        assert_eq!(roll.modifier, 4);
+
+        // Attribute rolls are unaffected by the setting
+        let roll = CharacterRoll::new(&template, &CHARACTER_SHEET_SAMPLE, RollTarget::Attribute("Agility".to_string()))
+            .roll_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+
+        assert_eq!(roll.modifier, 4 + 2);
+
+    }
+
+    #[test]
+    fn test_skill_inherits_situational_modifiers() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        let small_frame = template.perks.as_mut().unwrap().iter_mut().find(|p| p.name == "Small Frame").unwrap();
+        small_frame.situational = Some(vec![SituationalModifier {
+            situations: vec!["Cramped".to_string()],
+            attributes: Some(vec![AttributeModifier { name: "Agility".to_string(), modifier: 1, formula: None }]),
+            skills: None
+        }]);
+
+        let situations = vec!["Cramped".to_string()];
+        let roll = CharacterRoll::new(&template, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Acrobatics".to_string()))
+            .with_situations(&situations)
+            .roll_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+
+        assert_eq!(roll.modifier, 4 + 2 + 1);
+
+        template.skills_inherit_attribute_modifiers = Some(false);
+        let roll = CharacterRoll::new(&template, &CHARACTER_SHEET_SAMPLE, RollTarget::Skill("Acrobatics".to_string()))
+            .with_situations(&situations)
+            .roll_with_rng(&mut StdRng::seed_from_u64(1))
+            .unwrap();
+
+        assert_eq!(roll.modifier, 4);
+        assert!(roll.situational.is_empty());
+
+    }
+
+}
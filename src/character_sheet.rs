@@ -2,26 +2,31 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 use crate::InternalVersion;
 
 pub mod config;
+pub mod resources;
+pub mod ids;
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct SheetTemplate {
     pub name: String,
-    pub version: InternalVersion
+    pub version: InternalVersion,
+    /// The template's modules the sheet uses
+    pub modules: Option<Vec<String>>
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct SheetSkill {
     pub name: String,
     pub value: i64
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct SheetAttribute {
     pub name: String,
@@ -29,16 +34,31 @@ pub struct SheetAttribute {
     pub skills: Option<Vec<SheetSkill>>
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct SheetResource {
+    pub name: String,
+    pub value: i64
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct CharacterSheet {
     pub name: String,
     pub template: SheetTemplate,
     pub description: Option<String>,
+    /// Treated as level 1 when not set
+    pub level: Option<i64>,
     pub health: i64,
     pub armor_class: i64,
     pub weapon_proficiencies: Vec<String>,
+    pub weapon_categories: Option<Vec<String>>,
     pub perks: Option<Vec<String>>,    
+    pub inventory: Option<Vec<String>>,
+    /// Items from the inventory that are currently in use
+    pub equipped: Option<Vec<String>>,
+    /// Current resource values, resources that aren't listed are full
+    pub resources: Option<Vec<SheetResource>>,
     pub attributes: Vec<SheetAttribute>
 }
 
@@ -0,0 +1,195 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use super::CharacterSheet;
+use crate::character_template::CharacterTemplate;
+
+/**
+ * 
+ * Sheets can refer to attributes, skills, perks and weapons by either their id or their
+ * name. Everything else in the crate works with names, so sheets that use ids are resolved
+ * to names first. References the template doesn't know are left as they are for the
+ * validator to report.
+ * 
+*/
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl CharacterSheet {
+
+    /// A copy of the sheet with every id replaced by the template's current name
+    pub fn resolve_ids(&self, template: &CharacterTemplate) -> CharacterSheet {
+
+        self.map_references(template, |_, name| name.to_string())
+
+    }
+
+    /// A copy of the sheet that refers to everything with an id by its id, for storage
+    pub fn to_ids(&self, template: &CharacterTemplate) -> CharacterSheet {
+
+        self.map_references(template, |id, name| id.unwrap_or(name).to_string())
+
+    }
+
+}
+
+impl CharacterSheet {
+
+    fn map_references<F>(&self, template: &CharacterTemplate, reference: F) -> CharacterSheet
+    where
+        F: Fn(Option<&str>, &str) -> String
+    {
+
+        let mut sheet = self.clone();
+
+        for attr in sheet.attributes.iter_mut() {
+
+            let Some(t_attr) = template.find_attribute(&attr.name) else {
+                continue;
+            };
+
+            for skill in attr.skills.iter_mut().flatten() {
+
+                if let Some(t_skill) = template.find_skill(&attr.name, &skill.name) {
+                    skill.name = reference(t_skill.id.as_deref(), &t_skill.name);
+                }
+
+            }
+
+            attr.name = reference(t_attr.id.as_deref(), &t_attr.name);
+
+        }
+
+        for perk in sheet.perks.iter_mut().flatten() {
+
+            if let Some(t_perk) = template.find_perk(perk) {
+                *perk = reference(t_perk.id.as_deref(), &t_perk.name);
+            }
+
+        }
+
+        if let Some(proficiencies) = &template.weapon_proficiencies {
+
+            for weapon in sheet.weapon_proficiencies.iter_mut() {
+
+                if let Some(t_weapon) = proficiencies.find_weapon(weapon) {
+                    *weapon = reference(t_weapon.id.as_deref(), &t_weapon.weapon);
+                }
+
+            }
+
+        }
+
+        sheet
+
+    }
+
+}
+
+#[cfg(test)]
+mod ids_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use crate::character_roll::{CharacterRoll, RollTarget};
+    use crate::character_sheet_validator::CharacterSheetValidator;
+    use crate::character_template::ids::IdError;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    #[test]
+    fn round_trip_through_ids() {
+
+        let with_ids = CHARACTER_SHEET_SAMPLE.to_ids(&STANDARD_TEMPLATE);
+
+        let agility = with_ids.attributes.iter().find(|a| a.name == "agility").unwrap();
+        assert!(agility.skills.iter().flatten().any(|s| s.name == "sleight_of_hand"));
+        assert_eq!(with_ids.perks, Some(vec!["charismatic".to_string(), "small_frame".to_string()]));
+
+        let resolved = with_ids.resolve_ids(&STANDARD_TEMPLATE);
+        assert_eq!(resolved.as_json_str(), CHARACTER_SHEET_SAMPLE.as_json_str());
+
+    }
+
+    #[test]
+    fn name_based_sheets_are_unchanged() {
+
+        let resolved = CHARACTER_SHEET_SAMPLE.resolve_ids(&STANDARD_TEMPLATE);
+        assert_eq!(resolved.as_json_str(), CHARACTER_SHEET_SAMPLE.as_json_str());
+
+    }
+
+    #[test]
+    fn sheets_with_ids_validate_and_roll_like_names() {
+
+        let with_ids = CHARACTER_SHEET_SAMPLE.to_ids(&STANDARD_TEMPLATE);
+
+        assert_eq!(CharacterSheetValidator::new(&STANDARD_TEMPLATE, &with_ids).check(), Ok(()));
+
+        let targets = [
+            RollTarget::Attribute("Agility".to_string()),
+            RollTarget::Skill("Persuasion".to_string()),
+            RollTarget::Attack("Pistol".to_string())
+        ];
+
+        for target in targets {
+
+            let by_name = CharacterRoll::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, target.clone())
+                .roll_with_rng(&mut StdRng::seed_from_u64(3))
+                .unwrap();
+            let by_id = CharacterRoll::new(&STANDARD_TEMPLATE, &with_ids, target)
+                .roll_with_rng(&mut StdRng::seed_from_u64(3))
+                .unwrap();
+
+            assert_eq!(by_id.value, by_name.value);
+            assert_eq!(by_id.breakdown, by_name.breakdown);
+
+        }
+
+        let stats = |sheet: &CharacterSheet| serde_json::to_string(&STANDARD_TEMPLATE.get_effective_stats(sheet)).unwrap();
+        assert_eq!(stats(&with_ids), stats(&CHARACTER_SHEET_SAMPLE));
+        assert_eq!(STANDARD_TEMPLATE.get_budget(&with_ids), STANDARD_TEMPLATE.get_budget(&CHARACTER_SHEET_SAMPLE));
+
+    }
+
+    #[test]
+    fn renamed_elements_resolve_by_id() {
+
+        let with_ids = CHARACTER_SHEET_SAMPLE.to_ids(&STANDARD_TEMPLATE);
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        let charisma = template.attributes.iter_mut().find(|a| a.name == "Charisma").unwrap();
+        charisma.name = "Charme".to_string();
+
+        let resolved = with_ids.resolve_ids(&template);
+        assert!(resolved.attributes.iter().any(|a| a.name == "Charme"));
+        assert!(!resolved.attributes.iter().any(|a| a.name == "Charisma"));
+
+    }
+
+    #[test]
+    fn duplicate_ids_are_rejected() {
+
+        assert_eq!(STANDARD_TEMPLATE.check_ids(), Ok(()));
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.attributes[1].id = Some("strength".to_string());
+        assert_eq!(template.check_ids(), Err(IdError::DuplicateId { kind: "attribute".to_string(), id: "strength".to_string() }));
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.attributes[1].id = Some("Strength".to_string());
+        assert_eq!(template.check_ids(), Err(IdError::IdShadowsName { kind: "attribute".to_string(), id: "Strength".to_string() }));
+
+    }
+
+}
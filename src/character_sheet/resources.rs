@@ -0,0 +1,224 @@
+use thiserror::Error;
+
+use crate::character_template::common::UnmetRequirement;
+use crate::character_template::resource::Resource;
+use crate::character_template::CharacterTemplate;
+use crate::effective_stats::resource_maximum;
+
+use super::{CharacterSheet, SheetResource};
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ResourceError {
+    #[error("Character template does not have a {0} resource")]
+    UnknownResource(String),
+    #[error("Resource {resource} {requirement}")]
+    RequirementNotMet {
+        resource: String,
+        requirement: UnmetRequirement
+    },
+    #[error("Not enough {resource}, {available} available but {requested} requested")]
+    NotEnough {
+        resource: String,
+        available: i64,
+        requested: i64
+    },
+    #[error("Amount can not be negative, got {0}")]
+    NegativeAmount(i64)
+}
+
+impl CharacterSheet {
+
+    /// The current value of a resource, a resource missing from the sheet is at its maximum
+    pub fn resource_value(&self, template: &CharacterTemplate, name: &str) -> Result<i64, ResourceError> {
+
+        let resource = self.available_resource(template, name)?;
+
+        let value = self.resources
+            .iter()
+            .flatten()
+            .find(|r| r.name == name)
+            .map_or_else(|| resource_maximum(template, self, resource), |r| r.value);
+
+        Ok(value)
+
+    }
+
+    /// Spends from a resource, returning what's left
+    pub fn spend_resource(&mut self, template: &CharacterTemplate, name: &str, amount: i64) -> Result<i64, ResourceError> {
+
+        if amount < 0 {
+            return Err(ResourceError::NegativeAmount(amount));
+        }
+
+        let available = self.resource_value(template, name)?;
+        if amount > available {
+            return Err(ResourceError::NotEnough {
+                resource: name.to_string(),
+                available,
+                requested: amount
+            });
+        }
+
+        self.set_resource(name, available - amount);
+        Ok(available - amount)
+
+    }
+
+    /// Restores a resource up to its maximum, restoring it fully when no amount is given
+    pub fn restore_resource(&mut self, template: &CharacterTemplate, name: &str, amount: Option<i64>) -> Result<i64, ResourceError> {
+
+        if let Some(amount) = amount.filter(|a| *a < 0) {
+            return Err(ResourceError::NegativeAmount(amount));
+        }
+
+        let resource = self.available_resource(template, name)?;
+        let maximum  = resource_maximum(template, self, resource);
+        let current  = self.resource_value(template, name)?;
+
+        let value = amount.map_or(maximum, |a| (current + a).min(maximum)).max(current);
+
+        self.set_resource(name, value);
+        Ok(value)
+
+    }
+
+    /// Applies every recovery rule with the given trigger, returning the resources that changed and their new values
+    pub fn recover_resources(&mut self, template: &CharacterTemplate, trigger: &str) -> Vec<SheetResource> {
+
+        let recoveries: Vec<(String, Option<i64>)> = template.resources
+            .iter()
+            .flatten()
+            .flat_map(|r| {
+
+                r.recovery
+                    .iter()
+                    .flatten()
+                    .filter(|rec| rec.trigger == trigger)
+                    .map(|rec| (r.name.clone(), rec.amount))
+
+            })
+            .collect();
+
+        recoveries
+            .into_iter()
+            .filter_map(|(name, amount)| {
+
+                let before = self.resource_value(template, &name).ok()?;
+                let after  = self.restore_resource(template, &name, amount).ok()?;
+
+                (after != before).then_some(SheetResource { name, value: after })
+
+            })
+            .collect()
+
+    }
+
+    fn available_resource<'a>(&self, template: &'a CharacterTemplate, name: &str) -> Result<&'a Resource, ResourceError> {
+
+        let resource = template
+            .find_resource(name)
+            .ok_or_else(|| ResourceError::UnknownResource(name.to_string()))?;
+
+        if let Some(required) = &resource.required {
+
+            required.check(self).map_err(|requirement| ResourceError::RequirementNotMet {
+                resource: name.to_string(),
+                requirement
+            })?;
+
+        }
+
+        Ok(resource)
+
+    }
+
+    fn set_resource(&mut self, name: &str, value: i64) {
+
+        let resources = self.resources.get_or_insert_with(Vec::new);
+
+        match resources.iter_mut().find(|r| r.name == name) {
+            Some(resource) => resource.value = value,
+            None => resources.push(SheetResource { name: name.to_string(), value })
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod resource_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    #[test]
+    fn missing_resources_are_full() {
+
+        let stamina = STANDARD_TEMPLATE.find_resource("Stamina").unwrap();
+        let maximum = resource_maximum(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, stamina);
+
+        // Base 3 plus Prowess 1
+        assert_eq!(maximum, 4);
+        assert_eq!(CHARACTER_SHEET_SAMPLE.resource_value(&STANDARD_TEMPLATE, "Stamina"), Ok(maximum));
+
+    }
+
+    #[test]
+    fn spend_and_restore() {
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+
+        assert_eq!(sheet.spend_resource(&STANDARD_TEMPLATE, "Stamina", 3), Ok(1));
+        assert_eq!(sheet.spend_resource(&STANDARD_TEMPLATE, "Stamina", 2), Err(ResourceError::NotEnough {
+            resource: "Stamina".to_string(),
+            available: 1,
+            requested: 2
+        }));
+
+        assert_eq!(sheet.restore_resource(&STANDARD_TEMPLATE, "Stamina", Some(10)), Ok(4));
+        assert_eq!(sheet.spend_resource(&STANDARD_TEMPLATE, "Stamina", -1), Err(ResourceError::NegativeAmount(-1)));
+
+    }
+
+    #[test]
+    fn resource_requirements() {
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+
+        assert_eq!(sheet.spend_resource(&STANDARD_TEMPLATE, "Force Points", 1), Err(ResourceError::RequirementNotMet {
+            resource: "Force Points".to_string(),
+            requirement: UnmetRequirement::Perk("Force Sensitive".to_string())
+        }));
+
+        assert_eq!(sheet.spend_resource(&STANDARD_TEMPLATE, "Fake", 1), Err(ResourceError::UnknownResource("Fake".to_string())));
+
+    }
+
+    #[test]
+    fn recover_resources() {
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        sheet.spend_resource(&STANDARD_TEMPLATE, "Stamina", 4).unwrap();
+        sheet.spend_resource(&STANDARD_TEMPLATE, "Credits", 100).unwrap();
+
+        let recovered = sheet.recover_resources(&STANDARD_TEMPLATE, "Short Rest");
+        assert_eq!(recovered.len(), 1);
+        assert_eq!(recovered[0].name, "Stamina");
+        assert_eq!(recovered[0].value, 4);
+
+        assert!(sheet.recover_resources(&STANDARD_TEMPLATE, "Short Rest").is_empty());
+        assert_eq!(sheet.resource_value(&STANDARD_TEMPLATE, "Credits"), Ok(400));
+
+    }
+
+}
@@ -2,8 +2,13 @@
 use thiserror::Error;
 
 use crate::character_sheet::config::CHARACTER_SHEET_CONFIG;
+use crate::character_template::common::UnmetRequirement;
+use crate::character_template::composition::CompositionError;
+use crate::character_template::localization::fill_placeholders;
 use crate::character_template::CharacterTemplate;
 use crate::character_sheet::CharacterSheet;
+use crate::budget::SheetBudget;
+use crate::effective_stats::resource_maximum;
 
 #[derive(Error, Debug, PartialEq)]
 pub enum CharacterSheetError {
@@ -19,11 +24,17 @@ pub enum CharacterSheetError {
     NameMismatch,
     #[error("Character template version mismatch")]
     VersionMismatch,
+    #[error("Character template extends {0} and must be resolved before validating")]
+    UnresolvedTemplate(String),
+    #[error("Character template does not have a {0} module")]
+    ModuleNotAllowed(String),
 
     #[error("Character template does not allow perks")]
     PerksNotAllowed,
     #[error("Character template does not allow {0} as a perk")]
     PerkNotAllowed(String),
+    #[error("Character sheet lists the {0} perk more than once")]
+    DuplicatePerk(String),
     #[error("Character template does not allow {0} perk points")]
     NotEnoughPerkPoints(i64),
     #[error("Character template does not allow more than {max_perks} perks, but {selected_perks} were selected")]
@@ -31,9 +42,20 @@ pub enum CharacterSheetError {
         selected_perks: i64,
         max_perks: i64
     },
+    #[error("Character template requires at least {min_perks} perks, but {selected_perks} were selected")]
+    TooFewPerks {
+        selected_perks: i64,
+        min_perks: i64
+    },
+    #[error("Character template requires every perk point to be spent, {0} are left")]
+    PerkPointsUnspent(i64),
 
     #[error("Character template does not allow {0} as an attribute")]
     AttributeNotAllowed(String),
+    #[error("Character sheet is missing the {0} attribute")]
+    AttributeMissing(String),
+    #[error("Character sheet lists the {0} attribute more than once")]
+    DuplicateAttribute(String),
     #[error("Character template does not allow this many points for a single attribute")]
     TooManyAttributePoints {
         attribute: String,
@@ -47,6 +69,14 @@ pub enum CharacterSheetError {
     },
     #[error("Character template does not allow {0} attribute points")]
     AttributePointsExceeded(i64),
+    #[error("Character template requires at least {min_points} points in the {attribute} attribute")]
+    TooFewAttributePoints {
+        attribute: String,
+        allotted_points: i64,
+        min_points: i64
+    },
+    #[error("Character template requires every attribute point to be spent, {0} are left")]
+    AttributePointsUnspent(i64),
 
     #[error("Character template does not allow {0} as a skill")]
     SkillNotAllowed(String),
@@ -57,6 +87,13 @@ pub enum CharacterSheetError {
     },
     #[error("Character template requires skills array for {0} attribute")]
     SkillsMissingInAttribute(String),
+    #[error("Character sheet is missing the {skill} skill of the {attribute} attribute")]
+    SkillMissing {
+        attribute: String,
+        skill: String
+    },
+    #[error("Character sheet lists the {0} skill more than once")]
+    DuplicateSkill(String),
     #[error("Character template does not allow this many points for a single skill")]
     TooManySkillPoints {
         skill: String,
@@ -70,16 +107,261 @@ pub enum CharacterSheetError {
     },
     #[error("Character template does not allow {0} skill points")]
     SkillPointsExceeded(i64),
+    #[error("Character template requires at least {min_points} points in the {skill} skill")]
+    TooFewSkillPoints {
+        skill: String,
+        allotted_points: i64,
+        min_points: i64
+    },
+    #[error("Character template requires every skill point to be spent, {0} are left")]
+    SkillPointsUnspent(i64),
+
+    #[error("Character template does not allow weapon proficiencies")]
+    WeaponProficienciesNotAllowed,
+    #[error("Character template does not allow {0} as a weapon")]
+    WeaponNotAllowed(String),
+    #[error("Character template does not allow {0} as a weapon category")]
+    WeaponCategoryNotAllowed(String),
+    #[error("Weapon {weapon} {requirement}")]
+    WeaponRequirementNotMet {
+        weapon: String,
+        requirement: UnmetRequirement
+    },
+    #[error("Character template does not allow {0} weapon proficiency points")]
+    WeaponPointsExceeded(i64),
+    #[error("Character template does not allow more than {max_proficiencies} weapon proficiencies, but {selected_proficiencies} were selected")]
+    TooManyWeaponProficiencies {
+        selected_proficiencies: i64,
+        max_proficiencies: i64
+    },
+    #[error("Character template requires at least {min_proficiencies} weapon proficiencies, but {selected_proficiencies} were selected")]
+    TooFewWeaponProficiencies {
+        selected_proficiencies: i64,
+        min_proficiencies: i64
+    },
+    #[error("Character template requires every weapon proficiency point to be spent, {0} are left")]
+    WeaponPointsUnspent(i64),
+
+    #[error("Character template does not allow {0} as equipment")]
+    EquipmentNotAllowed(String),
+    #[error("{0} is equipped but not in the inventory")]
+    EquipmentNotInInventory(String),
+    #[error("Equipment {item} {requirement}")]
+    EquipmentRequirementNotMet {
+        item: String,
+        requirement: UnmetRequirement
+    },
+    #[error("Only one item can be equipped in the {slot} slot")]
+    EquipmentSlotConflict {
+        slot: String,
+        items: Vec<String>
+    },
+
+    #[error("Character template does not allow {0} as a resource")]
+    ResourceNotAllowed(String),
+    #[error("Resource {resource} {requirement}")]
+    ResourceRequirementNotMet {
+        resource: String,
+        requirement: UnmetRequirement
+    },
+    #[error("Character template does not allow negative values for {resource} resource")]
+    NegativeResource {
+        resource: String,
+        value: i64
+    },
+    #[error("Resource {resource} is above its maximum of {maximum}")]
+    ResourceAboveMaximum {
+        resource: String,
+        value: i64,
+        maximum: i64
+    },
 
 }
 
+impl CharacterSheetError {
+
+    /// A stable code for the error, used to look up translated messages
+    pub fn code(&self) -> &'static str {
+
+        use CharacterSheetError::*;
+
+        match self {
+            NameTooShort                                => "name_too_short",
+            NameTooLong                                 => "name_too_long",
+            DescriptionTooLong                          => "description_too_long",
+            NameMismatch                                => "name_mismatch",
+            VersionMismatch                             => "version_mismatch",
+            UnresolvedTemplate(_)                       => "unresolved_template",
+            ModuleNotAllowed(_)                         => "module_not_allowed",
+            PerksNotAllowed                             => "perks_not_allowed",
+            PerkNotAllowed(_)                           => "perk_not_allowed",
+            DuplicatePerk(_)                            => "duplicate_perk",
+            NotEnoughPerkPoints(_)                      => "not_enough_perk_points",
+            TooManyPerks { .. }                         => "too_many_perks",
+            TooFewPerks { .. }                          => "too_few_perks",
+            PerkPointsUnspent(_)                        => "perk_points_unspent",
+            AttributeNotAllowed(_)                      => "attribute_not_allowed",
+            AttributeMissing(_)                         => "attribute_missing",
+            DuplicateAttribute(_)                       => "duplicate_attribute",
+            TooManyAttributePoints { .. }               => "too_many_attribute_points",
+            NegativeAttributePoints { .. }              => "negative_attribute_points",
+            AttributePointsExceeded(_)                  => "attribute_points_exceeded",
+            TooFewAttributePoints { .. }                => "too_few_attribute_points",
+            AttributePointsUnspent(_)                   => "attribute_points_unspent",
+            SkillNotAllowed(_)                          => "skill_not_allowed",
+            SheetSkillsNotPresentInTemplateAttribute { .. } => "skills_not_in_template",
+            SkillsMissingInAttribute(_)                 => "skills_missing_in_attribute",
+            SkillMissing { .. }                         => "skill_missing",
+            DuplicateSkill(_)                           => "duplicate_skill",
+            TooManySkillPoints { .. }                   => "too_many_skill_points",
+            NegativeSkillPoints { .. }                  => "negative_skill_points",
+            SkillPointsExceeded(_)                      => "skill_points_exceeded",
+            TooFewSkillPoints { .. }                    => "too_few_skill_points",
+            SkillPointsUnspent(_)                       => "skill_points_unspent",
+            WeaponProficienciesNotAllowed               => "weapon_proficiencies_not_allowed",
+            WeaponNotAllowed(_)                         => "weapon_not_allowed",
+            WeaponCategoryNotAllowed(_)                 => "weapon_category_not_allowed",
+            WeaponRequirementNotMet { .. }              => "weapon_requirement_not_met",
+            WeaponPointsExceeded(_)                     => "weapon_points_exceeded",
+            TooManyWeaponProficiencies { .. }           => "too_many_weapon_proficiencies",
+            TooFewWeaponProficiencies { .. }            => "too_few_weapon_proficiencies",
+            WeaponPointsUnspent(_)                      => "weapon_points_unspent",
+            EquipmentNotAllowed(_)                      => "equipment_not_allowed",
+            EquipmentNotInInventory(_)                  => "equipment_not_in_inventory",
+            EquipmentRequirementNotMet { .. }           => "equipment_requirement_not_met",
+            EquipmentSlotConflict { .. }                => "equipment_slot_conflict",
+            ResourceNotAllowed(_)                       => "resource_not_allowed",
+            ResourceRequirementNotMet { .. }            => "resource_requirement_not_met",
+            NegativeResource { .. }                     => "negative_resource",
+            ResourceAboveMaximum { .. }                 => "resource_above_maximum"
+        }
+
+    }
 
+    /// The values a translated message can refer to, named like the error's fields
+    pub fn params(&self) -> Vec<(&'static str, String)> {
+
+        use CharacterSheetError::*;
+
+        match self {
+            UnresolvedTemplate(v) | ModuleNotAllowed(v) | PerkNotAllowed(v) | DuplicatePerk(v) | AttributeNotAllowed(v) | SkillNotAllowed(v) | SkillsMissingInAttribute(v)
+            | AttributeMissing(v) | DuplicateAttribute(v) | DuplicateSkill(v)
+            | WeaponNotAllowed(v) | WeaponCategoryNotAllowed(v) | EquipmentNotAllowed(v)
+            | EquipmentNotInInventory(v) | ResourceNotAllowed(v) => vec![("0", v.clone())],
+            NotEnoughPerkPoints(v) | AttributePointsExceeded(v) | SkillPointsExceeded(v)
+            | WeaponPointsExceeded(v) | PerkPointsUnspent(v) | AttributePointsUnspent(v) | SkillPointsUnspent(v)
+            | WeaponPointsUnspent(v) => vec![("0", v.to_string())],
+            TooManyPerks { selected_perks, max_perks } => vec![
+                ("selected_perks", selected_perks.to_string()),
+                ("max_perks", max_perks.to_string())
+            ],
+            TooFewPerks { selected_perks, min_perks } => vec![
+                ("selected_perks", selected_perks.to_string()),
+                ("min_perks", min_perks.to_string())
+            ],
+            TooFewAttributePoints { attribute, allotted_points, min_points } => vec![
+                ("attribute", attribute.clone()),
+                ("allotted_points", allotted_points.to_string()),
+                ("min_points", min_points.to_string())
+            ],
+            TooFewSkillPoints { skill, allotted_points, min_points } => vec![
+                ("skill", skill.clone()),
+                ("allotted_points", allotted_points.to_string()),
+                ("min_points", min_points.to_string())
+            ],
+            TooFewWeaponProficiencies { selected_proficiencies, min_proficiencies } => vec![
+                ("selected_proficiencies", selected_proficiencies.to_string()),
+                ("min_proficiencies", min_proficiencies.to_string())
+            ],
+            TooManyAttributePoints { attribute, allotted_points, max_points } => vec![
+                ("attribute", attribute.clone()),
+                ("allotted_points", allotted_points.to_string()),
+                ("max_points", max_points.to_string())
+            ],
+            NegativeAttributePoints { offending_attribute, points } => vec![
+                ("offending_attribute", offending_attribute.clone()),
+                ("points", points.to_string())
+            ],
+            SheetSkillsNotPresentInTemplateAttribute { attribute, skills } => vec![
+                ("attribute", attribute.clone()),
+                ("skills", skills.join(", "))
+            ],
+            SkillMissing { attribute, skill } => vec![
+                ("attribute", attribute.clone()),
+                ("skill", skill.clone())
+            ],
+            TooManySkillPoints { skill, allotted_points, max_points } => vec![
+                ("skill", skill.clone()),
+                ("allotted_points", allotted_points.to_string()),
+                ("max_points", max_points.to_string())
+            ],
+            NegativeSkillPoints { offending_skill, points } => vec![
+                ("offending_skill", offending_skill.clone()),
+                ("points", points.to_string())
+            ],
+            WeaponRequirementNotMet { weapon, requirement } => vec![
+                ("weapon", weapon.clone()),
+                ("requirement", requirement.to_string())
+            ],
+            TooManyWeaponProficiencies { selected_proficiencies, max_proficiencies } => vec![
+                ("selected_proficiencies", selected_proficiencies.to_string()),
+                ("max_proficiencies", max_proficiencies.to_string())
+            ],
+            EquipmentRequirementNotMet { item, requirement } => vec![
+                ("item", item.clone()),
+                ("requirement", requirement.to_string())
+            ],
+            EquipmentSlotConflict { slot, items } => vec![
+                ("slot", slot.clone()),
+                ("items", items.join(", "))
+            ],
+            ResourceRequirementNotMet { resource, requirement } => vec![
+                ("resource", resource.clone()),
+                ("requirement", requirement.to_string())
+            ],
+            NegativeResource { resource, value } => vec![
+                ("resource", resource.clone()),
+                ("value", value.to_string())
+            ],
+            ResourceAboveMaximum { resource, value, maximum } => vec![
+                ("resource", resource.clone()),
+                ("value", value.to_string()),
+                ("maximum", maximum.to_string())
+            ],
+            NameTooShort | NameTooLong | DescriptionTooLong | NameMismatch | VersionMismatch
+            | PerksNotAllowed | WeaponProficienciesNotAllowed => vec![]
+        }
 
+    }
 
+    /// The error message in the given locale, falling back to the English message
+    pub fn localized_message(&self, template: &CharacterTemplate, locale: &str) -> String {
+
+        let Some(message) = template.error_message(locale, self.code()) else {
+            return self.to_string();
+        };
+
+        fill_placeholders(message, &self.params())
+
+    }
+
+}
+
+/// How the validator treats attributes and skills a sheet leaves out
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ValidationMode {
+    /// Every attribute and skill in the template must be on the sheet
+    #[default]
+    Strict,
+    /// Missing attributes and skills are treated as having 0 points, for older sheets that omit them
+    Lenient
+}
 
 pub struct CharacterSheetValidator<'a> {
     template: &'a CharacterTemplate,
-    sheet: &'a CharacterSheet
+    sheet: &'a CharacterSheet,
+    mode: ValidationMode,
+    preset: bool
 }
 
 impl<'a> CharacterSheetValidator<'a> {
@@ -88,15 +370,69 @@ impl<'a> CharacterSheetValidator<'a> {
 
         Self {
             template,
-            sheet
+            sheet,
+            mode: ValidationMode::default(),
+            preset: false
         }
 
     }
 
-    /// Checks the character sheet against the template and config.
+    pub fn with_mode(mut self, mode: ValidationMode) -> Self {
+
+        self.mode = mode;
+        self
+
+    }
+
+    /// Checks a starting point for a sheet, such as an archetype's, that has no name yet and doesn't have to spend every point
+    pub fn as_preset(mut self) -> Self {
+
+        self.preset = true;
+        self
+
+    }
+
+    /**
+     * 
+     * Checks the character sheet against the template and config. Sheets that use
+     * modules are checked against the template with those modules applied.
+     * 
+    */
     pub fn check(&self) -> Result<(), CharacterSheetError> {
 
-        self.validate_character_name()?;
+        if let Some(parent) = &self.template.extends {
+            return Err(CharacterSheetError::UnresolvedTemplate(parent.clone()));
+        }
+
+        if self.sheet.template.modules.as_ref().is_none_or(|m| m.is_empty()) {
+            return self.check_resolved(self.template);
+        }
+
+        let template = self.template
+            .for_sheet(self.sheet)
+            .map_err(|e| match e {
+                CompositionError::UnknownModule(module) => CharacterSheetError::ModuleNotAllowed(module),
+                e => CharacterSheetError::UnresolvedTemplate(e.to_string())
+            })?;
+
+        self.check_resolved(&template)
+
+    }
+
+    /// Checks the sheet against a template with its modules applied, once its ids are replaced by names
+    fn check_resolved(&self, template: &CharacterTemplate) -> Result<(), CharacterSheetError> {
+
+        let sheet = self.sheet.resolve_ids(template);
+        CharacterSheetValidator { template, sheet: &sheet, ..*self }.check_flattened()
+
+    }
+
+    fn check_flattened(&self) -> Result<(), CharacterSheetError> {
+
+        if !self.preset {
+            self.validate_character_name()?;
+        }
+
         self.validate_character_desc()?;
         self.check_template_name()?;
         self.check_version()?;
@@ -105,7 +441,13 @@ impl<'a> CharacterSheetValidator<'a> {
         self.check_attributes()?;
         self.check_attribute_allotment()?;
         self.check_skills()?;
+        self.check_completeness()?;
         self.check_skill_allotment()?;
+        self.check_weapon_proficiencies()?;
+        self.check_weapon_allotment()?;
+        self.check_minimums()?;
+        self.check_equipment()?;
+        self.check_resources()?;
         Ok(())
 
     }
@@ -182,17 +524,18 @@ impl<'a> CharacterSheetValidator<'a> {
                 return Err(CharacterSheetError::PerksNotAllowed);
             }
 
-        } else if self.template.perks.is_some() && self.sheet.perks.is_some() {
+        } else if let (Some(template_perks), Some(sheet_perks)) = (&self.template.perks, &self.sheet.perks) {
 
-            let template_perks = self.template.perks.as_ref().unwrap();
-            let sheet_perks    = self.sheet.perks.as_ref().unwrap();
-
-            for perk in sheet_perks {
+            for (i, perk) in sheet_perks.iter().enumerate() {
 
                 if !template_perks.iter().any(|tp| tp.name == *perk) {
                     return Err(CharacterSheetError::PerkNotAllowed(perk.clone()));
                 }
 
+                if sheet_perks[..i].contains(perk) {
+                    return Err(CharacterSheetError::DuplicatePerk(perk.clone()));
+                }
+
             }
 
 
@@ -303,7 +646,7 @@ impl<'a> CharacterSheetValidator<'a> {
             let sheet_skills = attribute.skills.as_ref();
             if sheet_skills.is_none() {
 
-                if template_attribute.skills.is_none() {
+                if template_attribute.skills.is_none() || self.mode == ValidationMode::Lenient {
                     continue;   
                 }
 
@@ -337,6 +680,152 @@ impl<'a> CharacterSheetValidator<'a> {
 
     }
 
+    /// Rejects duplicate attributes and skills, and in strict mode any the sheet leaves out
+    fn check_completeness(&self) -> Result<(), CharacterSheetError> {
+
+        let mut seen_attributes: Vec<&str> = vec![];
+        let mut seen_skills: Vec<&str> = vec![];
+
+        for attribute in self.sheet.attributes.iter() {
+
+            if seen_attributes.contains(&attribute.name.as_str()) {
+                return Err(CharacterSheetError::DuplicateAttribute(attribute.name.clone()));
+            }
+            seen_attributes.push(&attribute.name);
+
+            for skill in attribute.skills.iter().flatten() {
+
+                if seen_skills.contains(&skill.name.as_str()) {
+                    return Err(CharacterSheetError::DuplicateSkill(skill.name.clone()));
+                }
+                seen_skills.push(&skill.name);
+
+            }
+
+        }
+
+        if self.mode == ValidationMode::Lenient {
+            return Ok(());
+        }
+
+        for template_attribute in self.template.attributes.iter() {
+
+            if !seen_attributes.contains(&template_attribute.name.as_str()) {
+                return Err(CharacterSheetError::AttributeMissing(template_attribute.name.clone()));
+            }
+
+            for skill in template_attribute.skills.iter().flatten() {
+
+                if !seen_skills.contains(&skill.name.as_str()) {
+                    return Err(CharacterSheetError::SkillMissing {
+                        attribute: template_attribute.name.clone(),
+                        skill: skill.name.clone()
+                    });
+                }
+
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+    /**
+     * 
+     * Checks the allotments' minimums and must-spend rules. Attributes and skills the sheet
+     * leaves out count as 0 points, so lenient mode doesn't let them skip a minimum.
+     * 
+    */
+    fn check_minimums(&self) -> Result<(), CharacterSheetError> {
+
+        let allotments = &self.template.allotments;
+        let budget = SheetBudget::new(self.template, self.sheet);
+
+        let attribute_min = allotments.attributes.min_points_per_allotment.unwrap_or(0);
+        let skill_min = allotments.skills.and_then(|s| s.min_points_per_allotment).unwrap_or(0);
+
+        for template_attribute in self.template.attributes.iter() {
+
+            let sheet_attribute = self.sheet.attributes.iter().find(|a| a.name == template_attribute.name);
+
+            let min_points = attribute_min.max(template_attribute.min_points.unwrap_or(0));
+            let allotted_points = sheet_attribute.map_or(0, |a| a.value);
+
+            if allotted_points < min_points {
+                return Err(CharacterSheetError::TooFewAttributePoints {
+                    attribute: template_attribute.name.clone(),
+                    allotted_points,
+                    min_points
+                });
+            }
+
+            for skill in template_attribute.skills.iter().flatten() {
+
+                let allotted_points = sheet_attribute
+                    .and_then(|a| a.skills.as_ref())
+                    .and_then(|skills| skills.iter().find(|s| s.name == skill.name))
+                    .map_or(0, |s| s.value);
+
+                if allotted_points < skill_min {
+                    return Err(CharacterSheetError::TooFewSkillPoints {
+                        skill: skill.name.clone(),
+                        allotted_points,
+                        min_points: skill_min
+                    });
+                }
+
+            }
+
+        }
+
+        // Presets leave points for the player to spend
+        let must_spend = |must_spend_all: Option<bool>| must_spend_all == Some(true) && !self.preset;
+
+        if must_spend(allotments.attributes.must_spend_all) && budget.attributes.remaining() > 0 {
+            return Err(CharacterSheetError::AttributePointsUnspent(budget.attributes.remaining()));
+        }
+
+        let must_spend_skills = allotments.skills.is_some_and(|s| must_spend(s.must_spend_all));
+        if let Some(skills) = budget.skills.as_ref().filter(|s| must_spend_skills && s.remaining() > 0) {
+            return Err(CharacterSheetError::SkillPointsUnspent(skills.remaining()));
+        }
+
+        if let Some(perk_points) = &allotments.perks {
+
+            let selected_perks = self.sheet.perks.as_ref().map_or(0, |p| p.len()) as i64;
+            if let Some(min_perks) = perk_points.min_perks.filter(|min| selected_perks < *min) {
+                return Err(CharacterSheetError::TooFewPerks { selected_perks, min_perks });
+            }
+
+            let remaining = budget.perks.as_ref().map_or(0, |p| p.remaining());
+            if must_spend(perk_points.must_spend_all) && remaining > 0 {
+                return Err(CharacterSheetError::PerkPointsUnspent(remaining));
+            }
+
+        }
+
+        if let Some(weapon_points) = &allotments.weapon_proficiencies {
+
+            let selected_proficiencies = self.template.weapon_proficiencies
+                .as_ref()
+                .map_or(0, |p| p.selected_count(self.sheet)) as i64;
+
+            if let Some(min_proficiencies) = weapon_points.min_proficiencies.filter(|min| selected_proficiencies < *min) {
+                return Err(CharacterSheetError::TooFewWeaponProficiencies { selected_proficiencies, min_proficiencies });
+            }
+
+            let remaining = budget.weapon_proficiencies.as_ref().map_or(0, |w| w.remaining());
+            if must_spend(weapon_points.must_spend_all) && remaining > 0 {
+                return Err(CharacterSheetError::WeaponPointsUnspent(remaining));
+            }
+
+        }
+
+        Ok(())
+
+    }
+
     fn check_skill_allotment(&self) -> Result<(), CharacterSheetError> {
 
         let mut s_total_points: i64 = 0;
@@ -388,6 +877,177 @@ impl<'a> CharacterSheetValidator<'a> {
 
     }
 
+    fn check_weapon_proficiencies(&self) -> Result<(), CharacterSheetError> {
+
+        let sheet_categories = self.sheet.weapon_categories.as_deref().unwrap_or_default();
+
+        let Some(proficiencies) = &self.template.weapon_proficiencies else {
+
+            if self.sheet.weapon_proficiencies.is_empty() && sheet_categories.is_empty() {
+                return Ok(());
+            }
+
+            return Err(CharacterSheetError::WeaponProficienciesNotAllowed);
+
+        };
+
+        // A listed category only makes the sheet proficient in the weapons whose requirements it meets
+        if let Some(category) = sheet_categories.iter().find(|c| proficiencies.find_category(c).is_none()) {
+            return Err(CharacterSheetError::WeaponCategoryNotAllowed(category.clone()));
+        }
+
+        for weapon_name in self.sheet.weapon_proficiencies.iter() {
+
+            let Some(weapon) = proficiencies.find_weapon(weapon_name) else {
+                return Err(CharacterSheetError::WeaponNotAllowed(weapon_name.clone()));
+            };
+
+            if let Some(required) = &weapon.required {
+
+                required.check(self.sheet).map_err(|requirement| CharacterSheetError::WeaponRequirementNotMet {
+                    weapon: weapon.weapon.clone(),
+                    requirement
+                })?;
+
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+    fn check_weapon_allotment(&self) -> Result<(), CharacterSheetError> {
+
+        let (Some(weapon_points), Some(proficiencies)) = (&self.template.allotments.weapon_proficiencies, &self.template.weapon_proficiencies) else {
+            return Ok(());
+        };
+
+        let sheet_categories = self.sheet.weapon_categories.as_deref().unwrap_or_default();
+
+        let selected = proficiencies.selected_count(self.sheet) as i64;
+        if let Some(max_proficiencies) = weapon_points.max_proficiencies {
+
+            if selected > max_proficiencies {
+                return Err(CharacterSheetError::TooManyWeaponProficiencies {
+                    selected_proficiencies: selected,
+                    max_proficiencies
+                });
+            }
+
+        }
+
+        let weapon_cost: i64 = proficiencies
+            .charged_weapons(self.sheet)
+            .map(|w| w.point_cost.unwrap_or(0))
+            .sum();
+
+        let category_cost: i64 = sheet_categories
+            .iter()
+            .filter_map(|c| proficiencies.find_category(c))
+            .map(|c| c.point_cost.unwrap_or(0))
+            .sum();
+
+        let total_points = weapon_cost + category_cost;
+        if total_points > weapon_points.given_points {
+            return Err(CharacterSheetError::WeaponPointsExceeded(total_points));
+        }
+
+        Ok(())
+
+    }
+
+    fn check_equipment(&self) -> Result<(), CharacterSheetError> {
+
+        let inventory = self.sheet.inventory.as_deref().unwrap_or_default();
+        let equipped  = self.sheet.equipped.as_deref().unwrap_or_default();
+
+        for item in inventory.iter().chain(equipped) {
+
+            if self.template.find_equipment(item).is_none() {
+                return Err(CharacterSheetError::EquipmentNotAllowed(item.clone()));
+            }
+
+        }
+
+        for item in equipped {
+
+            if !inventory.contains(item) {
+                return Err(CharacterSheetError::EquipmentNotInInventory(item.clone()));
+            }
+
+            let equipment = self.template.find_equipment(item).unwrap();
+            if let Some(required) = &equipment.required {
+
+                required.check(self.sheet).map_err(|requirement| CharacterSheetError::EquipmentRequirementNotMet {
+                    item: item.clone(),
+                    requirement
+                })?;
+
+            }
+
+            if let Some(slot) = &equipment.slot {
+
+                let items: Vec<String> = self.template
+                    .sheet_equipped(self.sheet)
+                    .filter(|e| e.slot.as_ref() == Some(slot))
+                    .map(|e| e.name.clone())
+                    .collect();
+
+                if items.len() > 1 || equipped.iter().filter(|e| *e == item).count() > 1 {
+                    return Err(CharacterSheetError::EquipmentSlotConflict {
+                        slot: slot.clone(),
+                        items
+                    });
+                }
+
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+    fn check_resources(&self) -> Result<(), CharacterSheetError> {
+
+        for sheet_resource in self.sheet.resources.iter().flatten() {
+
+            let Some(resource) = self.template.find_resource(&sheet_resource.name) else {
+                return Err(CharacterSheetError::ResourceNotAllowed(sheet_resource.name.clone()));
+            };
+
+            if let Some(required) = &resource.required {
+
+                required.check(self.sheet).map_err(|requirement| CharacterSheetError::ResourceRequirementNotMet {
+                    resource: sheet_resource.name.clone(),
+                    requirement
+                })?;
+
+            }
+
+            if sheet_resource.value < 0 {
+                return Err(CharacterSheetError::NegativeResource {
+                    resource: sheet_resource.name.clone(),
+                    value: sheet_resource.value
+                });
+            }
+
+            let maximum = resource_maximum(self.template, self.sheet, resource);
+            if sheet_resource.value > maximum {
+                return Err(CharacterSheetError::ResourceAboveMaximum {
+                    resource: sheet_resource.name.clone(),
+                    value: sheet_resource.value,
+                    maximum
+                });
+            }
+
+        }
+
+        Ok(())
+
+    }
+
 }
 
 
@@ -729,4 +1389,447 @@ mod sheet_validation_tests {
 
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn weapon_not_allowed_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.weapon_proficiencies = vec!["Not a weapon".to_string()];
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::WeaponNotAllowed("Not a weapon".to_string()));
+
+    }
+
+    #[test]
+    fn weapon_category_not_allowed_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.weapon_categories = Some(vec!["Not a category".to_string()]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::WeaponCategoryNotAllowed("Not a category".to_string()));
+
+    }
+
+    #[test]
+    fn weapon_requirement_not_met_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.weapon_proficiencies = vec!["Lightsaber".to_string()];
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::WeaponRequirementNotMet {
+            weapon: "Lightsaber".to_string(),
+            requirement: UnmetRequirement::Perk("Force Sensitive".to_string())
+        });
+
+    }
+
+    #[test]
+    fn weapon_category_expansion_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.weapon_categories = Some(vec!["Melee".to_string()]);
+
+        // A listed category leaves out the weapons whose requirements the sheet doesn't meet
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert!(validator.check().is_ok());
+
+        let proficiencies = template.weapon_proficiencies.as_ref().unwrap();
+        assert!(proficiencies.is_proficient(&sheet, "Sword"));
+        assert!(!proficiencies.is_proficient(&sheet, "Lightsaber"));
+        assert!(!proficiencies.is_proficient(&sheet, "Rifle"));
+
+        sheet.perks = Some(vec!["Force Sensitive".to_string()]);
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert!(validator.check().is_ok());
+        assert!(proficiencies.is_proficient(&sheet, "Lightsaber"));
+
+    }
+
+    #[test]
+    fn weapon_covered_by_category_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+
+        // The sample's Pistol is in Ranged, so it isn't charged or counted on top of the category
+        sheet.weapon_categories = Some(vec!["Ranged".to_string()]);
+        sheet.weapon_proficiencies = vec!["Pistol".to_string(), "Rifle".to_string()];
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert!(validator.check().is_ok());
+
+        let budget = template.get_budget(&sheet).weapon_proficiencies.unwrap();
+        assert_eq!(budget.spent, 2);
+
+    }
+
+    #[test]
+    fn weapon_points_exceeded_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.weapon_categories = Some(vec!["Explosives".to_string(), "Ranged".to_string()]);
+
+        let expected_cost: i64 = template.weapon_proficiencies
+            .as_ref()
+            .unwrap()
+            .categories
+            .iter()
+            .filter(|c| c.category == "Explosives" || c.category == "Ranged")
+            .map(|c| c.point_cost.unwrap_or(0))
+            .sum();
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::WeaponPointsExceeded(expected_cost));
+
+    }
+
+    #[test]
+    fn equipment_not_allowed_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.inventory = Some(vec!["Not an item".to_string()]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::EquipmentNotAllowed("Not an item".to_string()));
+
+    }
+
+    #[test]
+    fn equipment_not_in_inventory_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.equipped = Some(vec!["Medkit".to_string()]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::EquipmentNotInInventory("Medkit".to_string()));
+
+    }
+
+    #[test]
+    fn equipment_requirement_not_met_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.inventory = Some(vec!["Heavy Armor".to_string()]);
+        sheet.equipped  = Some(vec!["Heavy Armor".to_string()]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::EquipmentRequirementNotMet {
+            item: "Heavy Armor".to_string(),
+            requirement: UnmetRequirement::Attribute {
+                name: "Strength".to_string(),
+                required: 3,
+                actual: 0
+            }
+        });
+
+        // Carrying it without wearing it is fine
+        sheet.equipped = Some(vec![]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert!(validator.check().is_ok());
+
+    }
+
+    #[test]
+    fn equipment_slot_conflict_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.attributes.iter_mut().find(|a| a.name == "Strength").unwrap().value = 3;
+        sheet.inventory = Some(vec!["Light Armor".to_string(), "Heavy Armor".to_string()]);
+        sheet.equipped  = Some(vec!["Light Armor".to_string(), "Heavy Armor".to_string()]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::EquipmentSlotConflict {
+            slot: "Armor".to_string(),
+            items: vec!["Light Armor".to_string(), "Heavy Armor".to_string()]
+        });
+
+    }
+
+    #[test]
+    fn resource_not_allowed_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.resources = Some(vec![character_sheet::SheetResource { name: "Not a resource".to_string(), value: 0 }]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::ResourceNotAllowed("Not a resource".to_string()));
+
+    }
+
+    #[test]
+    fn resource_requirement_not_met_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.resources = Some(vec![character_sheet::SheetResource { name: "Force Points".to_string(), value: 0 }]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::ResourceRequirementNotMet {
+            resource: "Force Points".to_string(),
+            requirement: UnmetRequirement::Perk("Force Sensitive".to_string())
+        });
+
+    }
+
+    #[test]
+    fn resource_above_maximum_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        let maximum = resource_maximum(&template, &sheet, template.find_resource("Stamina").unwrap());
+        sheet.resources = Some(vec![character_sheet::SheetResource { name: "Stamina".to_string(), value: maximum + 1 }]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        let response = validator.check();
+
+        assert!(response.is_err());
+        assert_eq!(response.unwrap_err(), CharacterSheetError::ResourceAboveMaximum {
+            resource: "Stamina".to_string(),
+            value: maximum + 1,
+            maximum
+        });
+
+        sheet.resources = Some(vec![character_sheet::SheetResource { name: "Stamina".to_string(), value: -1 }]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check().unwrap_err(), CharacterSheetError::NegativeResource {
+            resource: "Stamina".to_string(),
+            value: -1
+        });
+
+    }
+
+    #[test]
+    fn localized_error_messages() {
+
+        let (template, _) = get_template_and_sheet();
+
+        let error = CharacterSheetError::PerkNotAllowed("Flying".to_string());
+        assert_eq!(error.code(), "perk_not_allowed");
+        assert_eq!(error.localized_message(&template, "fr"), "L'atout Flying n'est pas autorisé par ce modèle");
+        assert_eq!(error.localized_message(&template, "de-AT"), "Der Vorteil Flying ist in dieser Vorlage nicht erlaubt");
+
+        // No translation for the locale or the code
+        assert_eq!(error.localized_message(&template, "ja"), error.to_string());
+        assert_eq!(CharacterSheetError::NameMismatch.localized_message(&template, "fr"), CharacterSheetError::NameMismatch.to_string());
+
+    }
+
+    #[test]
+    fn module_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+
+        sheet.perks = Some(vec!["Lightsaber Training".to_string()]);
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::PerkNotAllowed("Lightsaber Training".to_string())));
+
+        sheet.template.modules = Some(vec!["Jedi Academy".to_string()]);
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Ok(()));
+
+        sheet.template.modules = Some(vec!["Sith Academy".to_string()]);
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::ModuleNotAllowed("Sith Academy".to_string())));
+
+    }
+
+    #[test]
+    fn missing_attribute_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.attributes.retain(|a| a.name != "Agility");
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::AttributeMissing("Agility".to_string())));
+
+        let validator = CharacterSheetValidator::new(&template, &sheet).with_mode(ValidationMode::Lenient);
+        assert_eq!(validator.check(), Ok(()));
+
+    }
+
+    #[test]
+    fn missing_skill_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        let agility = sheet.attributes.iter_mut().find(|a| a.name == "Agility").unwrap();
+        agility.skills.as_mut().unwrap().retain(|s| s.name != "Stealth");
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::SkillMissing {
+            attribute: "Agility".to_string(),
+            skill: "Stealth".to_string()
+        }));
+
+        let validator = CharacterSheetValidator::new(&template, &sheet).with_mode(ValidationMode::Lenient);
+        assert_eq!(validator.check(), Ok(()));
+
+        // Lenient mode also accepts leaving out the whole skills array
+        sheet.attributes.iter_mut().find(|a| a.name == "Agility").unwrap().skills = None;
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::SkillsMissingInAttribute("Agility".to_string())));
+
+        let validator = CharacterSheetValidator::new(&template, &sheet).with_mode(ValidationMode::Lenient);
+        assert_eq!(validator.check(), Ok(()));
+
+    }
+
+    #[test]
+    fn duplicate_entries_test() {
+
+        let (template, mut sheet) = get_template_and_sheet();
+        let strength = sheet.attributes.iter().find(|a| a.name == "Strength").unwrap().clone();
+        sheet.attributes.push(strength);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet).with_mode(ValidationMode::Lenient);
+        assert_eq!(validator.check(), Err(CharacterSheetError::DuplicateAttribute("Strength".to_string())));
+
+        let (template, mut sheet) = get_template_and_sheet();
+        let skills = sheet.attributes.iter_mut().find(|a| a.name == "Agility").unwrap().skills.as_mut().unwrap();
+        let skill = skills[0].clone();
+        skills.push(skill.clone());
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::DuplicateSkill(skill.name)));
+
+        let (template, mut sheet) = get_template_and_sheet();
+        sheet.perks = Some(vec!["Small Frame".to_string(), "Small Frame".to_string()]);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::DuplicatePerk("Small Frame".to_string())));
+
+    }
+
+    #[test]
+    fn attribute_minimum_test() {
+
+        let (mut template, mut sheet) = get_template_and_sheet();
+        template.allotments.attributes.min_points_per_allotment = Some(1);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::TooFewAttributePoints {
+            attribute: template.attributes[0].name.clone(),
+            allotted_points: 0,
+            min_points: 1
+        }));
+
+        for attribute in sheet.attributes.iter_mut() {
+            attribute.value = 1;
+        }
+
+        template.attributes[1].min_points = Some(2);
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::TooFewAttributePoints {
+            attribute: template.attributes[1].name.clone(),
+            allotted_points: 1,
+            min_points: 2
+        }));
+
+        // Leaving the attribute out doesn't get around its minimum
+        sheet.attributes.remove(1);
+        let validator = CharacterSheetValidator::new(&template, &sheet).with_mode(ValidationMode::Lenient);
+        assert_eq!(validator.check(), Err(CharacterSheetError::TooFewAttributePoints {
+            attribute: template.attributes[1].name.clone(),
+            allotted_points: 0,
+            min_points: 2
+        }));
+
+    }
+
+    #[test]
+    fn skill_minimum_test() {
+
+        let (mut template, sheet) = get_template_and_sheet();
+        template.allotments.skills.as_mut().unwrap().min_points_per_allotment = Some(1);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert!(matches!(validator.check(), Err(CharacterSheetError::TooFewSkillPoints { allotted_points: 0, min_points: 1, .. })));
+
+    }
+
+    #[test]
+    fn must_spend_all_test() {
+
+        let (mut template, mut sheet) = get_template_and_sheet();
+        template.allotments.attributes.must_spend_all = Some(true);
+        let given = template.allotments.attributes.given_points;
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::AttributePointsUnspent(given)));
+
+        let max = template.allotments.attributes.max_points_per_allotment.unwrap();
+        let mut left = given;
+        for attribute in sheet.attributes.iter_mut() {
+            attribute.value = left.min(max);
+            left -= attribute.value;
+        }
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Ok(()));
+
+        template.allotments.perks.as_mut().unwrap().must_spend_all = Some(true);
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::PerkPointsUnspent(template.allotments.perks.unwrap().given_points)));
+
+    }
+
+    #[test]
+    fn minimum_selections_test() {
+
+        let (mut template, mut sheet) = get_template_and_sheet();
+        template.allotments.perks.as_mut().unwrap().min_perks = Some(1);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::TooFewPerks { selected_perks: 0, min_perks: 1 }));
+
+        sheet.perks = Some(vec!["Honest Face".to_string()]);
+        template.allotments.weapon_proficiencies.as_mut().unwrap().min_proficiencies = Some(1);
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::TooFewWeaponProficiencies { selected_proficiencies: 0, min_proficiencies: 1 }));
+
+    }
+
+    #[test]
+    fn unresolved_template_test() {
+
+        let (mut template, sheet) = get_template_and_sheet();
+        template.extends = Some("Standard".to_string());
+
+        let validator = CharacterSheetValidator::new(&template, &sheet);
+        assert_eq!(validator.check(), Err(CharacterSheetError::UnresolvedTemplate("Standard".to_string())));
+
+    }
+
+}
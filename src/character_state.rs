@@ -0,0 +1,293 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::character_sheet::CharacterSheet;
+use crate::character_template::common::{AttributeModifier, SkillModifier};
+use crate::character_template::CharacterTemplate;
+use crate::effective_stats::derived_health;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum StateError {
+    #[error("Amount can not be negative, got {0}")]
+    NegativeAmount(i64),
+    #[error("Condition {0} is not applied")]
+    ConditionNotApplied(String)
+}
+
+/**
+ *
+ * A named effect on a character, such as "Stunned" or "Blessed", that modifies their rolls
+ * for a number of rounds. Conditions without a duration last until they are removed.
+ *
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct Condition {
+    pub name: String,
+    pub duration: Option<i64>,
+    /// Applied to every roll the character makes
+    pub roll_modifier: Option<i64>,
+    pub attributes: Option<Vec<AttributeModifier>>,
+    pub skills: Option<Vec<SkillModifier>>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DamageResult {
+    pub absorbed: i64,
+    pub taken: i64
+}
+
+/**
+ *
+ * The runtime state of a character during play. The sheet describes who the character is,
+ * the state tracks what has happened to them since.
+ *
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct CharacterState {
+    pub name: String,
+    pub max_health: i64,
+    pub current_health: i64,
+    pub temporary_health: i64,
+    pub conditions: Vec<Condition>
+}
+
+impl CharacterState {
+
+    /// Starts at full health, derived from the template the same way the sheet's effective stats are
+    pub fn new(template: &CharacterTemplate, sheet: &CharacterSheet) -> Self {
+
+        let health = derived_health(template, &sheet.resolve_ids(template));
+
+        Self {
+            name: sheet.name.clone(),
+            max_health: health,
+            current_health: health,
+            temporary_health: 0,
+            conditions: vec![]
+        }
+
+    }
+
+    /// Deals damage, taking it from temporary health first. Health does not go below zero.
+    pub fn damage(&mut self, amount: i64) -> Result<DamageResult, StateError> {
+
+        if amount < 0 {
+            return Err(StateError::NegativeAmount(amount));
+        }
+
+        let absorbed = amount.min(self.temporary_health);
+        self.temporary_health -= absorbed;
+
+        let taken = (amount - absorbed).min(self.current_health);
+        self.current_health -= taken;
+
+        Ok(DamageResult { absorbed, taken })
+
+    }
+
+    /// Heals up to the character's maximum health, returning how much was actually healed
+    pub fn heal(&mut self, amount: i64) -> Result<i64, StateError> {
+
+        if amount < 0 {
+            return Err(StateError::NegativeAmount(amount));
+        }
+
+        let healed = amount.min(self.max_health - self.current_health).max(0);
+        self.current_health += healed;
+
+        Ok(healed)
+
+    }
+
+    /// Temporary health doesn't stack, the larger of the two values is kept
+    pub fn add_temporary_health(&mut self, amount: i64) -> Result<(), StateError> {
+
+        if amount < 0 {
+            return Err(StateError::NegativeAmount(amount));
+        }
+
+        self.temporary_health = self.temporary_health.max(amount);
+        Ok(())
+
+    }
+
+    pub fn is_down(&self) -> bool {
+        self.current_health <= 0
+    }
+
+    /// Applies a condition, replacing any existing condition with the same name
+    pub fn add_condition(&mut self, condition: Condition) {
+
+        self.conditions.retain(|c| c.name != condition.name);
+        self.conditions.push(condition);
+
+    }
+
+    pub fn remove_condition(&mut self, name: &str) -> Result<Condition, StateError> {
+
+        let idx = self.conditions
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| StateError::ConditionNotApplied(name.to_string()))?;
+
+        Ok(self.conditions.remove(idx))
+
+    }
+
+    pub fn has_condition(&self, name: &str) -> bool {
+        self.conditions.iter().any(|c| c.name == name)
+    }
+
+    /// Advances every condition by one round, returning the names of the ones that expired
+    pub fn tick_conditions(&mut self) -> Vec<String> {
+
+        let mut expired = vec![];
+
+        self.conditions.retain_mut(|c| {
+
+            let Some(duration) = c.duration.as_mut() else {
+                return true;
+            };
+
+            *duration -= 1;
+            if *duration <= 0 {
+                expired.push(c.name.clone());
+                return false;
+            }
+
+            true
+
+        });
+
+        expired
+
+    }
+
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl CharacterState {
+
+    pub fn as_json_str(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json_str(json: String) -> Result<CharacterState, String> {
+        serde_json::from_str(&json).map_err(|_| "Failed to parse JSON".to_string())
+    }
+
+}
+
+#[cfg(test)]
+mod character_state_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    fn stunned(duration: Option<i64>) -> Condition {
+
+        Condition {
+            name: "Stunned".to_string(),
+            duration,
+            roll_modifier: Some(-2),
+            attributes: None,
+            skills: None
+        }
+
+    }
+
+    #[test]
+    fn damage_and_heal() {
+
+        let mut state = CharacterState::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE);
+        assert_eq!(state.current_health, STANDARD_TEMPLATE.base_health);
+
+        assert_eq!(state.damage(4), Ok(DamageResult { absorbed: 0, taken: 4 }));
+        assert_eq!(state.current_health, STANDARD_TEMPLATE.base_health - 4);
+
+        assert_eq!(state.heal(10), Ok(4));
+        assert_eq!(state.current_health, state.max_health);
+
+        assert_eq!(state.damage(1000), Ok(DamageResult { absorbed: 0, taken: state.max_health }));
+        assert!(state.is_down());
+
+        assert_eq!(state.heal(-1), Err(StateError::NegativeAmount(-1)));
+
+    }
+
+    #[test]
+    fn health_is_derived_from_the_template() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.base_health += 5;
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        sheet.health = 1;
+
+        let state = CharacterState::new(&template, &sheet);
+        assert_eq!(state.max_health, STANDARD_TEMPLATE.base_health + 5);
+        assert_eq!(state.current_health, state.max_health);
+
+    }
+
+    #[test]
+    fn temporary_health_absorbs_first() {
+
+        let mut state = CharacterState::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE);
+        state.add_temporary_health(3).unwrap();
+        state.add_temporary_health(2).unwrap();
+        assert_eq!(state.temporary_health, 3);
+
+        assert_eq!(state.damage(5), Ok(DamageResult { absorbed: 3, taken: 2 }));
+        assert_eq!(state.temporary_health, 0);
+        assert_eq!(state.current_health, state.max_health - 2);
+
+    }
+
+    #[test]
+    fn conditions_tick_and_expire() {
+
+        let mut state = CharacterState::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE);
+        state.add_condition(stunned(Some(2)));
+        state.add_condition(Condition { name: "Cursed".to_string(), ..stunned(None) });
+
+        assert_eq!(state.conditions.len(), 2);
+        assert!(state.tick_conditions().is_empty());
+        assert_eq!(state.tick_conditions(), vec!["Stunned".to_string()]);
+
+        assert!(!state.has_condition("Stunned"));
+        assert!(state.has_condition("Cursed"));
+        assert!(state.remove_condition("Cursed").is_ok());
+        assert_eq!(state.remove_condition("Cursed").unwrap_err(), StateError::ConditionNotApplied("Cursed".to_string()));
+
+    }
+
+    #[test]
+    fn json_round_trip() {
+
+        let mut state = CharacterState::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE);
+        state.damage(3).unwrap();
+        state.add_condition(stunned(Some(1)));
+
+        let restored = CharacterState::from_json_str(state.as_json_str()).unwrap();
+        assert_eq!(restored.current_health, state.current_health);
+        assert!(restored.has_condition("Stunned"));
+
+    }
+
+}
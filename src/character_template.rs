@@ -2,18 +2,37 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 pub mod perk;
 pub mod weapon_proficiency;
 pub mod attributes;
 pub mod common;
+pub mod equipment;
+pub mod resource;
+pub mod ids;
+pub mod localization;
+pub mod composition;
+pub mod lint;
+pub mod archetype;
 
 use attributes::Attribute;
+use equipment::Equipment;
 use perk::Perk;
+use resource::Resource;
 use weapon_proficiency::WeaponProficiency;
+use localization::Translation;
+use composition::{Removals, TemplateModule};
+use archetype::Archetype;
 
 use crate::{character_sheet::{self, CharacterSheet}, InternalVersion};
+use crate::budget::SheetBudget;
+use crate::effective_stats::EffectiveStats;
+use crate::formula::{Formula, FormulaError};
+use common::{Requirements, AttributeModifier, SkillModifier, ResourceModifier};
 
 /**
  * 
@@ -21,42 +40,84 @@ use crate::{character_sheet::{self, CharacterSheet}, InternalVersion};
  * and how many points they can allocate to a single attribute/skill/perk
  * 
 */
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub struct Points {
     pub given_points: i64,
     pub max_points_per_allotment: Option<i64>,
+    pub min_points_per_allotment: Option<i64>,
+    /// Every given point has to be allotted
+    pub must_spend_all: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub struct PerkPoints {
     pub given_points: i64,
     pub max_perks: Option<i64>,
+    pub min_perks: Option<i64>,
+    pub must_spend_all: Option<bool>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct WeaponPoints {
+    pub given_points: i64,
+    pub max_proficiencies: Option<i64>,
+    pub min_proficiencies: Option<i64>,
+    pub must_spend_all: Option<bool>,
 }
 
-#[derive(Serialize, Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone, Copy)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub struct Allotment {
     pub attributes: Points,
     pub skills: Option<Points>,
     pub perks: Option<PerkPoints>,
+    pub weapon_proficiencies: Option<WeaponPoints>,
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct CharacterTemplate {
     pub name: String,
     pub version: InternalVersion,
     pub description: String,
+    /// The name of the template this one builds on, see `resolve`
+    pub extends: Option<String>,
+    /// The version of `extends` to build on, the registry pins the parent's latest version when left out
+    pub extends_version: Option<InternalVersion>,
+    /// Elements of the parent template to leave out
+    pub remove: Option<Removals>,
+    /// Add-ons that sheets can choose to use
+    pub modules: Option<Vec<TemplateModule>>,
 
     pub base_health: i64,
     pub base_armor_class: i64,
+    /// Used in place of `base_health` when set, e.g. "10 + Prowess * 2"
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+    pub health_formula: Option<Formula>,
+    /// Used in place of `base_armor_class` when set, e.g. "7 + floor(Agility / 2)"
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+    pub armor_class_formula: Option<Formula>,
+    /// The attribute or skill rolled for initiative
+    pub initiative: Option<String>,
+    /// Whether modifiers to an attribute also apply to rolls of its skills, defaults to true
+    pub skills_inherit_attribute_modifiers: Option<bool>,
 
     pub allotments: Allotment,
     pub weapon_proficiencies: Option<WeaponProficiency>,
     pub perks: Option<Vec<Perk>>,
+    pub equipment: Option<Vec<Equipment>>,
+    pub resources: Option<Vec<Resource>>,
+    /// Ready-made starting points for new sheets, see `sheet_from_archetype`
+    pub archetypes: Option<Vec<Archetype>>,
+    /// Templates that extend another only need to list the attributes they add or change
+    #[serde(default)]
     pub attributes: Vec<Attribute>,
+    /// Names and descriptions in other languages, keyed by locale, e.g. "fr" or "de-CH"
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+    pub translations: Option<HashMap<String, Translation>>,
 }
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
@@ -65,7 +126,16 @@ impl CharacterTemplate {
     /// Deserialize a JSON string into a CharacterTemplate
     pub fn from_json_str(json: String) -> Result<CharacterTemplate, String> {
 
-        Ok(serde_json::from_str(&json).map_err(|_| "Failed to parse JSON".to_string())?)
+        let template: CharacterTemplate = serde_json::from_str(&json).map_err(|_| "Failed to parse JSON".to_string())?;
+        template.check_formulas().map_err(|e| e.to_string())?;
+        template.check_ids().map_err(|e| e.to_string())?;
+
+        // Archetypes can use what a parent defines, so a child's are checked once it's resolved
+        if template.extends.is_none() {
+            template.check_archetypes().map_err(|e| e.to_string())?;
+        }
+
+        Ok(template)
 
     }
 
@@ -75,13 +145,19 @@ impl CharacterTemplate {
             name: String::new(),
             template: character_sheet::SheetTemplate {
                 name: self.name.clone(),
-                version: self.version.clone(),
+                version: self.version.to_owned(),
+                modules: self.modules.as_ref().map(|_| vec![]),
             },   
             description: None,
+            level: Some(1),
             health: self.base_health,
             armor_class: self.base_armor_class,
             weapon_proficiencies: vec![],
+            weapon_categories: self.weapon_proficiencies.as_ref().map(|_| vec![]),
             perks: self.perks.as_ref().map(|_| vec![]),
+            inventory: self.equipment.as_ref().map(|_| vec![]),
+            equipped: self.equipment.as_ref().map(|_| vec![]),
+            resources: self.resources.as_ref().map(|_| vec![]),
             attributes: self.attributes.iter().map(|a| {
 
                 character_sheet::SheetAttribute {
@@ -106,4 +182,145 @@ impl CharacterTemplate {
 
     }
 
-}
\ No newline at end of file
+    /// A new sheet from one of the template's archetypes, e.g. "Smuggler"
+    pub fn from_archetype(&self, name: String) -> Result<CharacterSheet, String> {
+        self.sheet_from_archetype(&name).map_err(|e| e.to_string())
+    }
+
+    /// The sheet's attributes, skills, health and armor class after perks and equipment
+    pub fn get_effective_stats(&self, sheet: &CharacterSheet) -> EffectiveStats {
+        EffectiveStats::new(self, sheet)
+    }
+
+    /// How many points the sheet has spent of each allotment
+    pub fn get_budget(&self, sheet: &CharacterSheet) -> SheetBudget {
+        SheetBudget::new(self, sheet)
+    }
+
+    /// Evaluates a formula against a sheet, checking the names it uses against this template
+    pub fn evaluate_formula(&self, sheet: &CharacterSheet, formula: String) -> Result<i64, String> {
+
+        let formula = Formula::parse(&formula).map_err(|e| e.to_string())?;
+        self.check_formula(&formula).map_err(|e| e.to_string())?;
+
+        Ok(formula.evaluate(&sheet.resolve_ids(self)))
+
+    }
+
+}
+
+impl CharacterTemplate {
+
+    /// The template's perks that the sheet has selected
+    pub fn sheet_perks<'a>(&'a self, sheet: &'a CharacterSheet) -> impl Iterator<Item = &'a Perk> {
+
+        let sheet_perks = sheet.perks.as_deref().unwrap_or_default();
+
+        self.perks
+            .iter()
+            .flatten()
+            .filter(move |p| sheet_perks.contains(&p.name))
+
+    }
+
+    /// The template's equipment that the sheet has equipped
+    pub fn sheet_equipped<'a>(&'a self, sheet: &'a CharacterSheet) -> impl Iterator<Item = &'a Equipment> {
+
+        let equipped = sheet.equipped.as_deref().unwrap_or_default();
+
+        self.equipment
+            .iter()
+            .flatten()
+            .filter(move |e| equipped.contains(&e.name))
+
+    }
+
+    pub fn skills_inherit_attribute_modifiers(&self) -> bool {
+        self.skills_inherit_attribute_modifiers.unwrap_or(true)
+    }
+
+    pub fn find_equipment(&self, name: &str) -> Option<&Equipment> {
+        self.equipment.as_ref()?.iter().find(|e| e.name == name)
+    }
+
+    pub fn find_resource(&self, name: &str) -> Option<&Resource> {
+        self.resources.as_ref()?.iter().find(|r| r.name == name)
+    }
+
+    /// Checks every formula in the template only refers to `level` and names the template defines
+    pub fn check_formulas(&self) -> Result<(), FormulaError> {
+
+        let requirements = self.attributes
+            .iter()
+            .filter_map(|a| a.required.as_ref())
+            .chain(self.equipment.iter().flatten().filter_map(|e| e.required.as_ref()))
+            .chain(self.resources.iter().flatten().filter_map(|r| r.required.as_ref()))
+            .chain(self.weapon_proficiencies
+                .iter()
+                .flat_map(|wp| wp.categories.iter())
+                .flat_map(|c| c.weapons.iter())
+                .filter_map(|w| w.required.as_ref()))
+            .filter_map(|r: &Requirements| r.formula.as_ref());
+
+        let situational = || self.perks.iter().flatten().flat_map(|p| p.situational.iter().flatten());
+
+        let attribute_modifiers = self.perks
+            .iter()
+            .flatten()
+            .filter_map(|p| p.attributes.as_ref())
+            .chain(situational().filter_map(|s| s.attributes.as_ref()))
+            .chain(self.equipment.iter().flatten().filter_map(|e| e.attributes.as_ref()))
+            .chain(self.resources.iter().flatten().filter_map(|r| r.attributes.as_ref()))
+            .flatten()
+            .filter_map(|m: &AttributeModifier| m.formula.as_ref());
+
+        let skill_modifiers = self.perks
+            .iter()
+            .flatten()
+            .filter_map(|p| p.skills.as_ref())
+            .chain(situational().filter_map(|s| s.skills.as_ref()))
+            .chain(self.equipment.iter().flatten().filter_map(|e| e.skills.as_ref()))
+            .flatten()
+            .filter_map(|m: &SkillModifier| m.formula.as_ref());
+
+        let resource_modifiers = self.perks
+            .iter()
+            .flatten()
+            .filter_map(|p| p.resources.as_ref())
+            .flatten()
+            .filter_map(|m: &ResourceModifier| m.formula.as_ref());
+
+        self.health_formula
+            .iter()
+            .chain(self.armor_class_formula.iter())
+            .chain(requirements)
+            .chain(attribute_modifiers)
+            .chain(skill_modifiers)
+            .chain(resource_modifiers)
+            .try_for_each(|f| self.check_formula(f))
+
+    }
+
+    fn check_formula(&self, formula: &Formula) -> Result<(), FormulaError> {
+
+        for identifier in formula.identifiers() {
+
+            let known = identifier == "level"
+                || self.attributes.iter().any(|a| a.name == identifier)
+                || self.attributes.iter().filter_map(|a| a.skills.as_ref()).flatten().any(|s| s.name == identifier)
+                || self.perks.iter().flatten().any(|p| p.name == identifier);
+
+            if !known {
+                return Err(FormulaError::UnknownIdentifier {
+                    formula: formula.to_string(),
+                    identifier: identifier.to_string()
+                });
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+}
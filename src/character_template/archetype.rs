@@ -0,0 +1,312 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::CharacterTemplate;
+use crate::character_sheet::CharacterSheet;
+use crate::character_sheet_validator::{CharacterSheetError, CharacterSheetValidator};
+use crate::effective_stats::{derived_armor_class, derived_health};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct PresetPoints {
+    /// The attribute or skill's id or name
+    pub name: String,
+    pub value: i64
+}
+
+/**
+ *
+ * A ready-made starting point for a sheet, e.g. "Smuggler", so new players don't start from
+ * a blank sheet. Attributes and skills left out start at 0, and the preset doesn't have to
+ * spend every point, the player spends the rest.
+ *
+*/
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct Archetype {
+    pub name: String,
+    pub description: String,
+    pub attributes: Option<Vec<PresetPoints>>,
+    pub skills: Option<Vec<PresetPoints>>,
+    /// Perk ids or names
+    pub perks: Option<Vec<String>>,
+    /// Weapon ids or names
+    pub weapon_proficiencies: Option<Vec<String>>,
+    pub weapon_categories: Option<Vec<String>>
+}
+
+#[derive(Error, Debug, PartialEq)]
+pub enum ArchetypeError {
+    #[error("The template has no archetype {0}")]
+    UnknownArchetype(String),
+    #[error("Archetype {archetype} refers to unknown {kind} {name}")]
+    UnknownReference {
+        archetype: String,
+        kind: &'static str,
+        name: String
+    },
+    #[error("Archetype {archetype} makes an invalid sheet: {error}")]
+    InvalidSheet {
+        archetype: String,
+        error: CharacterSheetError
+    }
+}
+
+impl CharacterTemplate {
+
+    pub fn find_archetype(&self, name: &str) -> Option<&Archetype> {
+        self.archetypes.as_ref()?.iter().find(|a| a.name == name)
+    }
+
+    /**
+     *
+     * A new sheet with the archetype's points, perks and weapons, and the health and armor
+     * class they give. The name is left empty for the player to fill in.
+     *
+    */
+    pub fn sheet_from_archetype(&self, name: &str) -> Result<CharacterSheet, ArchetypeError> {
+
+        let archetype = self.find_archetype(name).ok_or_else(|| ArchetypeError::UnknownArchetype(name.to_string()))?;
+        self.build_archetype(archetype)
+
+    }
+
+    /**
+     *
+     * Checks every archetype only refers to what the template defines and makes a sheet the
+     * validator accepts, apart from its missing name and the points left for the player. Templates
+     * that extend another are checked once resolved, since archetypes can use what a parent defines.
+     *
+    */
+    pub fn check_archetypes(&self) -> Result<(), ArchetypeError> {
+
+        for archetype in self.archetypes.iter().flatten() {
+
+            let sheet = self.build_archetype(archetype)?;
+
+            CharacterSheetValidator::new(self, &sheet).as_preset().check().map_err(|error| ArchetypeError::InvalidSheet {
+                archetype: archetype.name.clone(),
+                error
+            })?;
+
+        }
+
+        Ok(())
+
+    }
+
+    fn build_archetype(&self, archetype: &Archetype) -> Result<CharacterSheet, ArchetypeError> {
+
+        let unknown = |kind: &'static str, name: &String| ArchetypeError::UnknownReference {
+            archetype: archetype.name.clone(),
+            kind,
+            name: name.clone()
+        };
+
+        let mut sheet = self.get_base_character_sheet();
+
+        for preset in archetype.attributes.iter().flatten() {
+
+            let attribute = self.find_attribute(&preset.name).ok_or_else(|| unknown("attribute", &preset.name))?;
+            let sheet_attribute = sheet.attributes.iter_mut().find(|a| a.name == attribute.name).unwrap();
+            sheet_attribute.value = preset.value;
+
+        }
+
+        for preset in archetype.skills.iter().flatten() {
+
+            let (attribute, skill) = self.attributes
+                .iter()
+                .find_map(|a| self.find_skill(&a.name, &preset.name).map(|s| (&a.name, &s.name)))
+                .ok_or_else(|| unknown("skill", &preset.name))?;
+
+            let sheet_skill = sheet.attributes
+                .iter_mut()
+                .find(|a| a.name == *attribute)
+                .and_then(|a| a.skills.as_mut())
+                .and_then(|skills| skills.iter_mut().find(|s| s.name == *skill))
+                .unwrap();
+
+            sheet_skill.value = preset.value;
+
+        }
+
+        for reference in archetype.perks.iter().flatten() {
+
+            let perk = self.find_perk(reference).ok_or_else(|| unknown("perk", reference))?;
+            sheet.perks.get_or_insert_with(Vec::new).push(perk.name.clone());
+
+        }
+
+        for reference in archetype.weapon_proficiencies.iter().flatten() {
+
+            let weapon = self.weapon_proficiencies
+                .as_ref()
+                .and_then(|wp| wp.find_weapon(reference))
+                .ok_or_else(|| unknown("weapon", reference))?;
+
+            sheet.weapon_proficiencies.push(weapon.weapon.clone());
+
+        }
+
+        for name in archetype.weapon_categories.iter().flatten() {
+
+            let category = self.weapon_proficiencies
+                .as_ref()
+                .and_then(|wp| wp.find_category(name))
+                .ok_or_else(|| unknown("weapon category", name))?;
+
+            sheet.weapon_categories.get_or_insert_with(Vec::new).push(category.category.clone());
+
+        }
+
+        sheet.health = derived_health(self, &sheet);
+        sheet.armor_class = derived_armor_class(self, &sheet);
+
+        Ok(sheet)
+
+    }
+
+}
+
+#[cfg(test)]
+mod archetype_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+    use crate::character_template::common::UnmetRequirement;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    fn with_archetype(archetype: &str) -> CharacterTemplate {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.archetypes = Some(vec![json5::from_str(archetype).unwrap()]);
+        template
+
+    }
+
+    #[test]
+    fn standard_archetypes_are_valid() {
+
+        assert!(STANDARD_TEMPLATE.check_archetypes().is_ok());
+
+        for archetype in STANDARD_TEMPLATE.archetypes.iter().flatten() {
+
+            let mut sheet = STANDARD_TEMPLATE.sheet_from_archetype(&archetype.name).unwrap();
+            sheet.name = "New player".to_string();
+
+            // Points left to spend are fine, anything else is a problem with the preset
+            let errors = CharacterSheetValidator::new(&STANDARD_TEMPLATE, &sheet).check();
+            assert!(errors.is_ok(), "{} is not valid: {}", archetype.name, errors.err().unwrap());
+
+        }
+
+    }
+
+    #[test]
+    fn sheet_from_archetype() {
+
+        let template = with_archetype(r#"{
+            name: "Scout",
+            description: "Quick and quiet",
+            attributes: [{ name: "agility", value: 4 }],
+            skills: [{ name: "Stealth", value: 5 }],
+            perks: ["small_frame"],
+            weapon_proficiencies: ["Rifle"]
+        }"#);
+
+        let sheet = template.sheet_from_archetype("Scout").unwrap();
+        let agility = sheet.attributes.iter().find(|a| a.name == "Agility").unwrap();
+
+        assert_eq!(agility.value, 4);
+        assert_eq!(agility.skills.as_ref().unwrap().iter().find(|s| s.name == "Stealth").unwrap().value, 5);
+        assert_eq!(sheet.perks, Some(vec!["Small Frame".to_string()]));
+        assert_eq!(sheet.weapon_proficiencies, vec!["Rifle".to_string()]);
+        assert_eq!(sheet.name, "");
+
+        assert_eq!(template.sheet_from_archetype("Pilot").err(), Some(ArchetypeError::UnknownArchetype("Pilot".to_string())));
+
+    }
+
+    #[test]
+    fn invalid_archetypes() {
+
+        let template = with_archetype(r#"{ name: "Bad", description: "", perks: ["Lucky"] }"#);
+        assert_eq!(template.check_archetypes(), Err(ArchetypeError::UnknownReference {
+            archetype: "Bad".to_string(),
+            kind: "perk",
+            name: "Lucky".to_string()
+        }));
+
+        let invalid = |error| Err(ArchetypeError::InvalidSheet { archetype: "Bad".to_string(), error });
+
+        let template = with_archetype(r#"{ name: "Bad", description: "", attributes: [{ name: "Agility", value: 6 }] }"#);
+        assert_eq!(template.check_archetypes(), invalid(CharacterSheetError::TooManyAttributePoints {
+            attribute: "Agility".to_string(),
+            allotted_points: 6,
+            max_points: 5
+        }));
+
+        let template = with_archetype(r#"{ name: "Bad", description: "", attributes: [{ name: "Agility", value: -1 }] }"#);
+        assert_eq!(template.check_archetypes(), invalid(CharacterSheetError::NegativeAttributePoints {
+            offending_attribute: "Agility".to_string(),
+            points: -1
+        }));
+
+        let template = with_archetype(r#"{
+            name: "Bad",
+            description: "",
+            attributes: [{ name: "Agility", value: 5 }, { name: "Charisma", value: 5 }, { name: "Prowess", value: 5 }, { name: "Strength", value: 1 }]
+        }"#);
+        assert_eq!(template.check_archetypes(), invalid(CharacterSheetError::AttributePointsExceeded(16)));
+
+        let template = with_archetype(r#"{ name: "Bad", description: "", perks: ["Veteran", "veteran"] }"#);
+        assert_eq!(template.check_archetypes(), invalid(CharacterSheetError::DuplicatePerk("Veteran".to_string())));
+
+        // Whole categories count towards the most proficiencies allowed
+        let mut template = with_archetype(r#"{ name: "Bad", description: "", weapon_proficiencies: ["Unarmed"], weapon_categories: ["Ranged"] }"#);
+        template.allotments.weapon_proficiencies.as_mut().unwrap().max_proficiencies = Some(1);
+        assert_eq!(template.check_archetypes(), invalid(CharacterSheetError::TooManyWeaponProficiencies {
+            selected_proficiencies: 2,
+            max_proficiencies: 1
+        }));
+
+        let template = with_archetype(r#"{ name: "Bad", description: "", weapon_proficiencies: ["Lightsaber"] }"#);
+        assert_eq!(template.check_archetypes(), invalid(CharacterSheetError::WeaponRequirementNotMet {
+            weapon: "Lightsaber".to_string(),
+            requirement: UnmetRequirement::Perk("Force Sensitive".to_string())
+        }));
+
+    }
+
+    #[test]
+    fn child_archetypes_wait_for_the_parent() {
+
+        // Luck could come from the parent, so only a template that extends none is rejected
+        let mut template = with_archetype(r#"{ name: "Gambler", description: "", attributes: [{ name: "Luck", value: 3 }] }"#);
+        assert!(CharacterTemplate::from_json_str(serde_json::to_string(&template).unwrap()).is_err());
+
+        template.extends = Some("Base".to_string());
+        assert!(CharacterTemplate::from_json_str(serde_json::to_string(&template).unwrap()).is_ok());
+
+    }
+
+    #[test]
+    fn archetypes_leave_points_to_spend() {
+
+        let mut template = with_archetype(r#"{ name: "Scout", description: "", attributes: [{ name: "Agility", value: 4 }] }"#);
+        template.allotments.attributes.must_spend_all = Some(true);
+
+        assert!(template.check_archetypes().is_ok());
+
+    }
+
+}
@@ -1,22 +1,29 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::common::Requirements;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct Skill {
+    /// Stable identifier that sheets can use in place of the name
+    pub id: Option<String>,
     pub name: String,
     pub description: String
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct Attribute {
+    /// Stable identifier that sheets can use in place of the name
+    pub id: Option<String>,
     pub name: String,
     pub description: String,
     pub skills: Option<Vec<Skill>>,
-    pub required: Option<Requirements>
+    pub required: Option<Requirements>,
+    /// The fewest points a sheet can allot to this attribute, on top of the allotment's minimum
+    pub min_points: Option<i64>
 }
\ No newline at end of file
@@ -1,41 +1,190 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
+use crate::character_sheet::CharacterSheet;
+use crate::formula::Formula;
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct AttributeModifier {
     pub name: String,
+    #[serde(default)]
     pub modifier: i64,
+    /// Evaluated in place of `modifier` when set
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+    pub formula: Option<Formula>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct SkillModifier {
     pub name: String,
+    #[serde(default)]
+    pub modifier: i64,
+    /// Evaluated in place of `modifier` when set
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+    pub formula: Option<Formula>,
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct ResourceModifier {
+    pub name: String,
+    #[serde(default)]
     pub modifier: i64,
+    /// Evaluated in place of `modifier` when set
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+    pub formula: Option<Formula>,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+/**
+ * 
+ * Modifiers that only apply in certain situations, e.g. "Darkness" or "Against Force Users".
+ * They apply to a roll when any of their situations is active.
+ * 
+*/
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct SituationalModifier {
+    pub situations: Vec<String>,
+    pub attributes: Option<Vec<AttributeModifier>>,
+    pub skills: Option<Vec<SkillModifier>>
+}
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct AttributeRequirement {
     pub name: String,
     pub greater_than_or_equal_to: i64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct SkillRequirement {
     pub name: String,
     pub greater_than_or_equal_to: i64,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct Requirements {
     pub perks: Option<Vec<String>>,
     pub attributes: Option<Vec<AttributeRequirement>>,
-    pub skills: Option<Vec<SkillRequirement>>
-}
\ No newline at end of file
+    pub skills: Option<Vec<SkillRequirement>>,
+    /// Met when the formula evaluates to anything other than 0
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(skip))]
+    pub formula: Option<Formula>
+}
+
+macro_rules! impl_modifier_value {
+    ($($modifier:ty),*) => {$(
+
+        impl $modifier {
+
+            /// The formula evaluated against the sheet when there is one, otherwise the flat modifier
+            pub fn value(&self, sheet: &CharacterSheet) -> i64 {
+                self.formula.as_ref().map_or(self.modifier, |f| f.evaluate(sheet))
+            }
+
+        }
+
+    )*};
+}
+
+impl_modifier_value!(AttributeModifier, SkillModifier, ResourceModifier);
+
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum UnmetRequirement {
+    #[error("requires the {0} perk")]
+    Perk(String),
+    #[error("requires {name} of at least {required}, but has {actual}")]
+    Attribute {
+        name: String,
+        required: i64,
+        actual: i64
+    },
+    #[error("requires {name} of at least {required}, but has {actual}")]
+    Skill {
+        name: String,
+        required: i64,
+        actual: i64
+    },
+    #[error("requires {0}")]
+    Formula(String)
+}
+
+impl Requirements {
+
+    /// Checks the requirements against the points and perks on a sheet, returning the first one that isn't met
+    pub fn check(&self, sheet: &CharacterSheet) -> Result<(), UnmetRequirement> {
+
+        let sheet_perks = sheet.perks.as_deref().unwrap_or_default();
+
+        for perk in self.perks.iter().flatten() {
+
+            if !sheet_perks.contains(perk) {
+                return Err(UnmetRequirement::Perk(perk.clone()));
+            }
+
+        }
+
+        for requirement in self.attributes.iter().flatten() {
+
+            let actual = sheet.attributes
+                .iter()
+                .find(|a| a.name == requirement.name)
+                .map(|a| a.value)
+                .unwrap_or(0);
+
+            if actual < requirement.greater_than_or_equal_to {
+                return Err(UnmetRequirement::Attribute {
+                    name: requirement.name.clone(),
+                    required: requirement.greater_than_or_equal_to,
+                    actual
+                });
+            }
+
+        }
+
+        for requirement in self.skills.iter().flatten() {
+
+            let actual = sheet.attributes
+                .iter()
+                .filter_map(|a| a.skills.as_ref())
+                .flatten()
+                .find(|s| s.name == requirement.name)
+                .map(|s| s.value)
+                .unwrap_or(0);
+
+            if actual < requirement.greater_than_or_equal_to {
+                return Err(UnmetRequirement::Skill {
+                    name: requirement.name.clone(),
+                    required: requirement.greater_than_or_equal_to,
+                    actual
+                });
+            }
+
+        }
+
+        if let Some(formula) = &self.formula {
+
+            if !formula.evaluate_bool(sheet) {
+                return Err(UnmetRequirement::Formula(formula.to_string()));
+            }
+
+        }
+
+        Ok(())
+
+    }
+
+    pub fn is_met_by(&self, sheet: &CharacterSheet) -> bool {
+        self.check(sheet).is_ok()
+    }
+
+}
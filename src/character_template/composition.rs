@@ -0,0 +1,451 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use super::attributes::Attribute;
+use super::equipment::Equipment;
+use super::perk::Perk;
+use super::resource::Resource;
+use super::weapon_proficiency::{WeaponCategory, WeaponProficiency};
+use super::CharacterTemplate;
+use crate::character_sheet::CharacterSheet;
+use crate::InternalVersion;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum CompositionError {
+    #[error("Template {0} extends a template that could not be found")]
+    UnknownParent(String),
+    #[error("Templates extend each other in a cycle: {}", .0.join(" -> "))]
+    CircularExtends(Vec<String>),
+    #[error("Template does not have a {0} module")]
+    UnknownModule(String)
+}
+
+/// Names of inherited elements to leave out of the flattened template
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct Removals {
+    pub perks: Option<Vec<String>>,
+    pub attributes: Option<Vec<String>>,
+    pub skills: Option<Vec<String>>,
+    pub weapons: Option<Vec<String>>,
+    pub weapon_categories: Option<Vec<String>>,
+    pub equipment: Option<Vec<String>>,
+    pub resources: Option<Vec<String>>
+}
+
+/**
+ * 
+ * An optional add-on to a template, such as an expansion pack, that a sheet can choose to use.
+ * Elements with the same name as an existing one replace it, everything else is added.
+ * 
+*/
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct TemplateModule {
+    pub name: String,
+    pub description: String,
+    pub remove: Option<Removals>,
+    pub perks: Option<Vec<Perk>>,
+    pub attributes: Option<Vec<Attribute>>,
+    pub weapon_categories: Option<Vec<WeaponCategory>>,
+    pub equipment: Option<Vec<Equipment>>,
+    pub resources: Option<Vec<Resource>>
+}
+
+/// The parts of a child template or module that are layered onto a template
+struct Patch<'a> {
+    remove: Option<&'a Removals>,
+    perks: Option<&'a Vec<Perk>>,
+    attributes: Option<&'a Vec<Attribute>>,
+    weapon_categories: Option<&'a Vec<WeaponCategory>>,
+    equipment: Option<&'a Vec<Equipment>>,
+    resources: Option<&'a Vec<Resource>>
+}
+
+impl<'a> From<&'a TemplateModule> for Patch<'a> {
+
+    fn from(module: &'a TemplateModule) -> Self {
+
+        Self {
+            remove: module.remove.as_ref(),
+            perks: module.perks.as_ref(),
+            attributes: module.attributes.as_ref(),
+            weapon_categories: module.weapon_categories.as_ref(),
+            equipment: module.equipment.as_ref(),
+            resources: module.resources.as_ref()
+        }
+
+    }
+
+}
+
+impl CharacterTemplate {
+
+    /**
+     * 
+     * Flattens a template that `extends` another into a single template. `find` looks up
+     * templates by name and by `extends_version` when the child pins one. The child's name, version, description, base values and allotments
+     * are used as is, optional values fall back to the parent's, and perks, attributes,
+     * skills, weapons, equipment, resources and modules are merged by name.
+     * 
+    */
+    pub fn resolve<'a, F>(&self, find: F) -> Result<CharacterTemplate, CompositionError>
+    where
+        F: Fn(&str, Option<&InternalVersion>) -> Option<&'a CharacterTemplate>
+    {
+
+        self.resolve_chain(&find, &mut vec![])
+
+    }
+
+    /// The template with the given modules applied in order
+    pub fn with_modules(&self, modules: &[String]) -> Result<CharacterTemplate, CompositionError> {
+
+        let mut template = self.clone();
+
+        for name in modules {
+
+            let module = self.modules
+                .iter()
+                .flatten()
+                .find(|m| m.name == *name)
+                .ok_or_else(|| CompositionError::UnknownModule(name.clone()))?;
+
+            template.apply(module.into());
+
+        }
+
+        Ok(template)
+
+    }
+
+    /// The template with the modules the sheet uses applied
+    pub fn for_sheet(&self, sheet: &CharacterSheet) -> Result<CharacterTemplate, CompositionError> {
+        self.with_modules(sheet.template.modules.as_deref().unwrap_or_default())
+    }
+
+    fn resolve_chain<'a, F>(&self, find: &F, seen: &mut Vec<String>) -> Result<CharacterTemplate, CompositionError>
+    where
+        F: Fn(&str, Option<&InternalVersion>) -> Option<&'a CharacterTemplate>
+    {
+
+        let Some(parent_name) = &self.extends else {
+            return Ok(self.clone());
+        };
+
+        seen.push(self.name.clone());
+
+        // The chain from the first template in the cycle back round to it
+        if let Some(start) = seen.iter().position(|name| name == parent_name) {
+            let mut chain = seen[start..].to_vec();
+            chain.push(parent_name.clone());
+            return Err(CompositionError::CircularExtends(chain));
+        }
+
+        let parent = find(parent_name, self.extends_version.as_ref()).ok_or_else(|| CompositionError::UnknownParent(self.name.clone()))?;
+        let mut template = parent.resolve_chain(find, seen)?;
+
+        template.name = self.name.clone();
+        template.version = self.version.to_owned();
+        template.description = self.description.clone();
+        template.base_health = self.base_health;
+        template.base_armor_class = self.base_armor_class;
+        template.allotments = self.allotments;
+        template.extends = None;
+        template.extends_version = None;
+        template.remove = None;
+
+        if self.health_formula.is_some() {
+            template.health_formula = self.health_formula.clone();
+        }
+
+        if self.armor_class_formula.is_some() {
+            template.armor_class_formula = self.armor_class_formula.clone();
+        }
+
+        if self.initiative.is_some() {
+            template.initiative = self.initiative.clone();
+        }
+
+        if self.skills_inherit_attribute_modifiers.is_some() {
+            template.skills_inherit_attribute_modifiers = self.skills_inherit_attribute_modifiers;
+        }
+
+        if let Some(child) = &self.weapon_proficiencies {
+
+            let proficiencies = template.weapon_proficiencies.get_or_insert_with(|| child.clone());
+            proficiencies.proficiency_bonus = child.proficiency_bonus.or(proficiencies.proficiency_bonus);
+            proficiencies.non_proficiency_penalty = child.non_proficiency_penalty.or(proficiencies.non_proficiency_penalty);
+
+        }
+
+        if let Some(modules) = &self.modules {
+            merge(template.modules.get_or_insert_with(Vec::new), modules, |m| &m.name);
+        }
+
+        if let Some(archetypes) = &self.archetypes {
+            merge(template.archetypes.get_or_insert_with(Vec::new), archetypes, |a| &a.name);
+        }
+
+        if let Some(translations) = &self.translations {
+            template.translations.get_or_insert_with(Default::default).extend(translations.clone());
+        }
+
+        template.apply(Patch {
+            remove: self.remove.as_ref(),
+            perks: self.perks.as_ref(),
+            attributes: Some(&self.attributes),
+            weapon_categories: self.weapon_proficiencies.as_ref().map(|wp| &wp.categories),
+            equipment: self.equipment.as_ref(),
+            resources: self.resources.as_ref()
+        });
+
+        Ok(template)
+
+    }
+
+    fn apply(&mut self, patch: Patch<'_>) {
+
+        if let Some(remove) = patch.remove {
+
+            let removed = |names: &Option<Vec<String>>, name: &String| names.iter().flatten().any(|n| n == name);
+
+            if let Some(perks) = self.perks.as_mut() {
+                perks.retain(|p| !removed(&remove.perks, &p.name));
+            }
+
+            self.attributes.retain(|a| !removed(&remove.attributes, &a.name));
+
+            for skills in self.attributes.iter_mut().filter_map(|a| a.skills.as_mut()) {
+                skills.retain(|s| !removed(&remove.skills, &s.name));
+            }
+
+            if let Some(proficiencies) = self.weapon_proficiencies.as_mut() {
+
+                proficiencies.categories.retain(|c| !removed(&remove.weapon_categories, &c.category));
+
+                for category in proficiencies.categories.iter_mut() {
+                    category.weapons.retain(|w| !removed(&remove.weapons, &w.weapon));
+                }
+
+            }
+
+            if let Some(equipment) = self.equipment.as_mut() {
+                equipment.retain(|e| !removed(&remove.equipment, &e.name));
+            }
+
+            if let Some(resources) = self.resources.as_mut() {
+                resources.retain(|r| !removed(&remove.resources, &r.name));
+            }
+
+        }
+
+        if let Some(perks) = patch.perks {
+            merge(self.perks.get_or_insert_with(Vec::new), perks, |p| &p.name);
+        }
+
+        for attr in patch.attributes.into_iter().flatten() {
+
+            let Some(existing) = self.attributes.iter_mut().find(|a| a.name == attr.name) else {
+                self.attributes.push(attr.clone());
+                continue;
+            };
+
+            // Skills are merged so an attribute can gain a skill without repeating the others
+            let mut skills = existing.skills.take();
+            if let Some(added) = &attr.skills {
+                merge(skills.get_or_insert_with(Vec::new), added, |s| &s.name);
+            }
+
+            *existing = Attribute { skills, ..attr.clone() };
+
+        }
+
+        if let Some(categories) = patch.weapon_categories {
+
+            // A module can bring weapons to a template that had none
+            let proficiencies = self.weapon_proficiencies.get_or_insert_with(|| WeaponProficiency {
+                categories: vec![],
+                proficiency_bonus: None,
+                non_proficiency_penalty: None
+            });
+
+            for category in categories {
+
+                let Some(existing) = proficiencies.categories.iter_mut().find(|c| c.category == category.category) else {
+                    proficiencies.categories.push(category.clone());
+                    continue;
+                };
+
+                let mut weapons = std::mem::take(&mut existing.weapons);
+                merge(&mut weapons, &category.weapons, |w| &w.weapon);
+
+                *existing = WeaponCategory { weapons, ..category.clone() };
+
+            }
+
+        }
+
+        if let Some(equipment) = patch.equipment {
+            merge(self.equipment.get_or_insert_with(Vec::new), equipment, |e| &e.name);
+        }
+
+        if let Some(resources) = patch.resources {
+            merge(self.resources.get_or_insert_with(Vec::new), resources, |r| &r.name);
+        }
+
+    }
+
+}
+
+/// Replaces the elements that share a name with an addition, and appends the rest
+fn merge<T: Clone>(base: &mut Vec<T>, additions: &[T], name: impl Fn(&T) -> &String) {
+
+    for addition in additions {
+
+        match base.iter_mut().find(|b| name(b) == name(addition)) {
+            Some(existing) => *existing = addition.clone(),
+            None           => base.push(addition.clone())
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod composition_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const VARIANT_TEMPLATE_STR: &str = r#"{
+        name: "Standard Lite",
+        extends: "Standard",
+        version: [1, 0, 0],
+        description: "Standard with fewer perks",
+        base_health: 12,
+        base_armor_class: 7,
+        allotments: {
+            attributes: { given_points: 12, max_points_per_allotment: 5 },
+            skills: { given_points: 20, max_points_per_allotment: 10 },
+            perks: { given_points: 2, max_perks: 2 }
+        },
+        remove: {
+            perks: ["Veteran", "Honest Face"],
+            skills: ["Force Lightning"]
+        },
+        perks: [
+            {
+                name: "Small Frame",
+                description: "A gentler Small Frame",
+                point_cost: 1,
+                attributes: [{ name: "Agility", modifier: 1 }]
+            }
+        ],
+        attributes: [
+            {
+                name: "Force",
+                description: "Connection to the Force",
+                skills: [{ name: "Force Choke", description: "Crush an enemy's throat" }]
+            }
+        ]
+    }"#;
+
+    fn find(name: &str, _: Option<&InternalVersion>) -> Option<&'static CharacterTemplate> {
+        (name == "Standard").then(|| &*STANDARD_TEMPLATE)
+    }
+
+    #[test]
+    fn resolve_extended_template() {
+
+        let variant: CharacterTemplate = json5::from_str(VARIANT_TEMPLATE_STR).unwrap();
+        let template = variant.resolve(find).unwrap();
+
+        assert_eq!(template.name, "Standard Lite");
+        assert_eq!(template.base_health, 12);
+        assert!(template.extends.is_none());
+        assert_eq!(template.initiative, STANDARD_TEMPLATE.initiative);
+
+        let perks: Vec<&str> = template.perks.iter().flatten().map(|p| p.name.as_str()).collect();
+        assert!(!perks.contains(&"Veteran") && !perks.contains(&"Honest Face"));
+        assert_eq!(perks.len(), STANDARD_TEMPLATE.perks.as_ref().unwrap().len() - 2);
+        assert_eq!(template.find_perk("Small Frame").unwrap().description, "A gentler Small Frame");
+
+        let force = template.find_attribute("Force").unwrap();
+        let skills: Vec<&str> = force.skills.iter().flatten().map(|s| s.name.as_str()).collect();
+        assert!(skills.contains(&"Force Push") && skills.contains(&"Force Choke"));
+        assert!(!skills.contains(&"Force Lightning"));
+
+        // Inherited everything else
+        assert_eq!(template.attributes.len(), STANDARD_TEMPLATE.attributes.len());
+        assert!(template.weapon_proficiencies.is_some());
+        assert!(template.find_resource("Stamina").is_some());
+
+    }
+
+    #[test]
+    fn resolve_errors() {
+
+        let mut variant: CharacterTemplate = json5::from_str(VARIANT_TEMPLATE_STR).unwrap();
+        variant.extends = Some("Missing".to_string());
+        assert_eq!(variant.resolve(find).err(), Some(CompositionError::UnknownParent("Standard Lite".to_string())));
+
+        variant.extends = Some("Standard Lite".to_string());
+        assert_eq!(variant.resolve(find).err(), Some(CompositionError::CircularExtends(vec![
+            "Standard Lite".to_string(),
+            "Standard Lite".to_string()
+        ])));
+
+        // A longer cycle names every template in it
+        variant.extends = Some("Standard Heavy".to_string());
+        let mut heavy = variant.clone();
+        heavy.name = "Standard Heavy".to_string();
+        heavy.extends = Some("Standard Lite".to_string());
+
+        let find_cycle = |name: &str, _: Option<&InternalVersion>| match name {
+            "Standard Lite"  => Some(&variant),
+            "Standard Heavy" => Some(&heavy),
+            _                => None
+        };
+
+        let error = variant.resolve(find_cycle).err().unwrap();
+        assert_eq!(error.to_string(), "Templates extend each other in a cycle: Standard Lite -> Standard Heavy -> Standard Lite");
+
+    }
+
+    #[test]
+    fn apply_modules() {
+
+        let template = STANDARD_TEMPLATE.with_modules(&["Jedi Academy".to_string()]).unwrap();
+
+        assert!(template.find_perk("Lightsaber Training").is_some());
+
+        let proficiencies = template.weapon_proficiencies.as_ref().unwrap();
+        assert!(proficiencies.find_weapon("Training Saber").is_some());
+        assert!(proficiencies.find_weapon("Sword").is_some());
+
+        // Weapon categories from a module create the template's proficiencies when it has none
+        let mut unarmed = STANDARD_TEMPLATE.clone();
+        unarmed.weapon_proficiencies = None;
+        let template = unarmed.with_modules(&["Jedi Academy".to_string()]).unwrap();
+        let proficiencies = template.weapon_proficiencies.as_ref().unwrap();
+        assert!(proficiencies.find_weapon("Training Saber").is_some());
+        assert!(proficiencies.find_weapon("Sword").is_none());
+
+        assert_eq!(
+            STANDARD_TEMPLATE.with_modules(&["Missing".to_string()]).err(),
+            Some(CompositionError::UnknownModule("Missing".to_string()))
+        );
+
+    }
+
+}
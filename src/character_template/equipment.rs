@@ -0,0 +1,21 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::common::{AttributeModifier, Requirements, SkillModifier};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct Equipment {
+    pub name: String,
+    pub description: String,
+    /// Only one item can be equipped per slot, e.g. "Armor"
+    pub slot: Option<String>,
+    pub required: Option<Requirements>,
+    pub attributes: Option<Vec<AttributeModifier>>,
+    pub skills: Option<Vec<SkillModifier>>,
+    pub base_health_modifier: Option<i64>,
+    pub base_armor_class_modifier: Option<i64>
+}
@@ -0,0 +1,111 @@
+use std::collections::HashSet;
+
+use thiserror::Error;
+
+use super::attributes::{Attribute, Skill};
+use super::perk::Perk;
+use super::CharacterTemplate;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum IdError {
+    #[error("The {kind} id {id} is used more than once")]
+    DuplicateId {
+        kind: String,
+        id: String
+    },
+    #[error("The {kind} id {id} is the name of a different {kind}")]
+    IdShadowsName {
+        kind: String,
+        id: String
+    }
+}
+
+/// Whether a sheet's reference, either an id or a name, refers to the element
+pub(crate) fn is_reference_to(id: &Option<String>, name: &str, reference: &str) -> bool {
+    name == reference || id.as_deref() == Some(reference)
+}
+
+impl CharacterTemplate {
+
+    /**
+     * 
+     * Checks that every id identifies a single element. Ids must be unique among
+     * attributes, skills, perks and weapons, and must not be the name of another
+     * element of the same kind, otherwise a sheet's reference would be ambiguous.
+     * 
+    */
+    pub fn check_ids(&self) -> Result<(), IdError> {
+
+        let attributes = self.attributes
+            .iter()
+            .map(|a| (a.id.as_deref(), a.name.as_str()));
+
+        let skills = self.attributes
+            .iter()
+            .flat_map(|a| a.skills.iter().flatten())
+            .map(|s| (s.id.as_deref(), s.name.as_str()));
+
+        let perks = self.perks
+            .iter()
+            .flatten()
+            .map(|p| (p.id.as_deref(), p.name.as_str()));
+
+        let weapons = self.weapon_proficiencies
+            .iter()
+            .flat_map(|wp| wp.categories.iter())
+            .flat_map(|c| c.weapons.iter())
+            .map(|w| (w.id.as_deref(), w.weapon.as_str()));
+
+        check_unique_ids("attribute", attributes.collect())?;
+        check_unique_ids("skill", skills.collect())?;
+        check_unique_ids("perk", perks.collect())?;
+        check_unique_ids("weapon", weapons.collect())
+
+    }
+
+    /// Finds an attribute by its id or name
+    pub fn find_attribute(&self, reference: &str) -> Option<&Attribute> {
+        self.attributes.iter().find(|a| is_reference_to(&a.id, &a.name, reference))
+    }
+
+    /// Finds a skill by its id or name, under the attribute with the given id or name
+    pub fn find_skill(&self, attribute: &str, reference: &str) -> Option<&Skill> {
+
+        self.find_attribute(attribute)?
+            .skills
+            .as_ref()?
+            .iter()
+            .find(|s| is_reference_to(&s.id, &s.name, reference))
+
+    }
+
+    /// Finds a perk by its id or name
+    pub fn find_perk(&self, reference: &str) -> Option<&Perk> {
+        self.perks.as_ref()?.iter().find(|p| is_reference_to(&p.id, &p.name, reference))
+    }
+
+}
+
+fn check_unique_ids(kind: &str, elements: Vec<(Option<&str>, &str)>) -> Result<(), IdError> {
+
+    let mut seen = HashSet::new();
+
+    for (id, name) in &elements {
+
+        let Some(id) = id else {
+            continue;
+        };
+
+        if !seen.insert(*id) {
+            return Err(IdError::DuplicateId { kind: kind.to_string(), id: id.to_string() });
+        }
+
+        if elements.iter().any(|(_, other)| other == id && other != name) {
+            return Err(IdError::IdShadowsName { kind: kind.to_string(), id: id.to_string() });
+        }
+
+    }
+
+    Ok(())
+
+}
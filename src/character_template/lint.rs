@@ -0,0 +1,345 @@
+use std::collections::HashSet;
+
+use serde::Serialize;
+
+use super::common::{AttributeModifier, Requirements, SkillModifier};
+use super::CharacterTemplate;
+use crate::dice::DiceExpression;
+
+#[derive(Serialize, Debug, Clone, Copy, PartialEq)]
+pub enum LintLevel {
+    /// Likely a mistake, but the template still works
+    Warning,
+    /// The template will be rejected or misbehave
+    Error
+}
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct TemplateLint {
+    pub level: LintLevel,
+    /// Where the problem is, e.g. "perk Small Frame"
+    pub location: String,
+    pub message: String
+}
+
+impl CharacterTemplate {
+
+    /**
+     * 
+     * Looks for problems in a template that deserializing it doesn't catch: invalid formulas,
+     * ids and damage dice, duplicate names, and modifiers or requirements that refer to
+     * attributes, skills or perks the template doesn't define.
+     * 
+    */
+    pub fn lint(&self) -> Vec<TemplateLint> {
+
+        let mut lints = Linter { template: self, lints: vec![] };
+
+        if let Err(e) = self.check_formulas() {
+            lints.error("formulas", e.to_string());
+        }
+
+        if let Err(e) = self.check_ids() {
+            lints.error("ids", e.to_string());
+        }
+
+        // A child's archetypes can use what its parent defines, the registry checks those
+        if let Some(e) = self.check_archetypes().err().filter(|_| self.extends.is_none()) {
+            lints.error("archetypes", e.to_string());
+        }
+
+        lints.duplicates("attribute", self.attributes.iter().map(|a| &a.name));
+        lints.duplicates("skill", self.attributes.iter().flat_map(|a| a.skills.iter().flatten()).map(|s| &s.name));
+        lints.duplicates("perk", self.perks.iter().flatten().map(|p| &p.name));
+        lints.duplicates("equipment", self.equipment.iter().flatten().map(|e| &e.name));
+        lints.duplicates("resource", self.resources.iter().flatten().map(|r| &r.name));
+        lints.duplicates("archetype", self.archetypes.iter().flatten().map(|a| &a.name));
+
+        if let Some(initiative) = &self.initiative {
+
+            if !lints.has_attribute(initiative) && !lints.has_skill(initiative) {
+                lints.warning("initiative", format!("{} is not an attribute or skill", initiative));
+            }
+
+        }
+
+        for attr in &self.attributes {
+            lints.requirements(&format!("attribute {}", attr.name), attr.required.as_ref());
+        }
+
+        for perk in self.perks.iter().flatten() {
+
+            let location = format!("perk {}", perk.name);
+            lints.modifiers(&location, perk.attributes.as_ref(), perk.skills.as_ref());
+
+            for situational in perk.situational.iter().flatten() {
+                lints.modifiers(&location, situational.attributes.as_ref(), situational.skills.as_ref());
+            }
+
+            for modifier in perk.resources.iter().flatten() {
+
+                if self.find_resource(&modifier.name).is_none() {
+                    lints.warning(&location, format!("modifies unknown resource {}", modifier.name));
+                }
+
+            }
+
+        }
+
+        for item in self.equipment.iter().flatten() {
+
+            let location = format!("equipment {}", item.name);
+            lints.modifiers(&location, item.attributes.as_ref(), item.skills.as_ref());
+            lints.requirements(&location, item.required.as_ref());
+
+        }
+
+        for resource in self.resources.iter().flatten() {
+
+            let location = format!("resource {}", resource.name);
+            lints.modifiers(&location, resource.attributes.as_ref(), None);
+            lints.requirements(&location, resource.required.as_ref());
+
+        }
+
+        let weapons = self.weapon_proficiencies
+            .iter()
+            .flat_map(|wp| wp.categories.iter())
+            .flat_map(|c| c.weapons.iter());
+
+        lints.duplicates("weapon", weapons.clone().map(|w| &w.weapon));
+
+        for weapon in weapons {
+
+            let location = format!("weapon {}", weapon.weapon);
+            lints.requirements(&location, weapon.required.as_ref());
+
+            if let Some(attr) = weapon.attribute.as_ref().filter(|a| !lints.has_attribute(a)) {
+                lints.warning(&location, format!("attacks with unknown attribute {}", attr));
+            }
+
+            if let Some(skill) = weapon.skill.as_ref().filter(|s| !lints.has_skill(s)) {
+                lints.warning(&location, format!("attacks with unknown skill {}", skill));
+            }
+
+            if let Some(damage) = weapon.damage.as_ref().filter(|d| d.parse::<DiceExpression>().is_err()) {
+                lints.error(&location, format!("invalid damage expression {}", damage));
+            }
+
+        }
+
+        lints.minimums();
+
+        lints.lints
+
+    }
+
+}
+
+struct Linter<'a> {
+    template: &'a CharacterTemplate,
+    lints: Vec<TemplateLint>
+}
+
+impl Linter<'_> {
+
+    fn warning(&mut self, location: &str, message: String) {
+        self.lints.push(TemplateLint { level: LintLevel::Warning, location: location.to_string(), message });
+    }
+
+    fn error(&mut self, location: &str, message: String) {
+        self.lints.push(TemplateLint { level: LintLevel::Error, location: location.to_string(), message });
+    }
+
+    fn has_attribute(&self, name: &str) -> bool {
+        self.template.attributes.iter().any(|a| a.name == name)
+    }
+
+    fn has_skill(&self, name: &str) -> bool {
+        self.template.attributes.iter().flat_map(|a| a.skills.iter().flatten()).any(|s| s.name == name)
+    }
+
+    fn has_perk(&self, name: &str) -> bool {
+        self.template.perks.iter().flatten().any(|p| p.name == name)
+    }
+
+    /// Minimums no sheet can meet, either above the maximum or needing more points than are given
+    fn minimums(&mut self) {
+
+        let allotments = self.template.allotments;
+        let attribute_points = allotments.attributes;
+
+        let mut attribute_total = 0;
+        for attr in &self.template.attributes {
+
+            let min = attribute_points.min_points_per_allotment.unwrap_or(0).max(attr.min_points.unwrap_or(0));
+            attribute_total += min;
+
+            if min > attribute_points.max_points_per_allotment.unwrap_or(i64::MAX) {
+                self.error(&format!("attribute {}", attr.name), format!("minimum of {} points is above the maximum per attribute", min));
+            }
+
+        }
+
+        if attribute_total > attribute_points.given_points {
+            self.error("allotments", format!("attribute minimums need {} points, but only {} are given", attribute_total, attribute_points.given_points));
+        }
+
+        if let Some(skill_points) = allotments.skills {
+
+            let skills = self.template.attributes.iter().flat_map(|a| a.skills.iter().flatten()).count() as i64;
+            let skill_total = skills * skill_points.min_points_per_allotment.unwrap_or(0);
+
+            if skill_total > skill_points.given_points {
+                self.error("allotments", format!("skill minimums need {} points, but only {} are given", skill_total, skill_points.given_points));
+            }
+
+        }
+
+        let perks = allotments.perks.map(|p| (p.min_perks, p.max_perks));
+        let weapons = allotments.weapon_proficiencies.map(|w| (w.min_proficiencies, w.max_proficiencies));
+
+        for (kind, limits) in [("perks", perks), ("weapon proficiencies", weapons)] {
+
+            if let Some((Some(min), Some(max))) = limits.filter(|(min, max)| min > max && max.is_some()) {
+                self.error("allotments", format!("at least {} {} are required, but at most {} can be selected", min, kind, max));
+            }
+
+        }
+
+    }
+
+    fn duplicates<'b>(&mut self, kind: &str, names: impl Iterator<Item = &'b String>) {
+
+        let mut seen = HashSet::new();
+
+        for name in names {
+
+            if !seen.insert(name) {
+                self.error(&format!("{} {}", kind, name), format!("more than one {} is named {}", kind, name));
+            }
+
+        }
+
+    }
+
+    fn modifiers(&mut self, location: &str, attributes: Option<&Vec<AttributeModifier>>, skills: Option<&Vec<SkillModifier>>) {
+
+        for modifier in attributes.into_iter().flatten() {
+
+            if !self.has_attribute(&modifier.name) {
+                self.warning(location, format!("modifies unknown attribute {}", modifier.name));
+            }
+
+        }
+
+        for modifier in skills.into_iter().flatten() {
+
+            if !self.has_skill(&modifier.name) {
+                self.warning(location, format!("modifies unknown skill {}", modifier.name));
+            }
+
+        }
+
+    }
+
+    fn requirements(&mut self, location: &str, requirements: Option<&Requirements>) {
+
+        let Some(requirements) = requirements else {
+            return;
+        };
+
+        for perk in requirements.perks.iter().flatten() {
+
+            if !self.has_perk(perk) {
+                self.warning(location, format!("requires unknown perk {}", perk));
+            }
+
+        }
+
+        for attr in requirements.attributes.iter().flatten() {
+
+            if !self.has_attribute(&attr.name) {
+                self.warning(location, format!("requires unknown attribute {}", attr.name));
+            }
+
+        }
+
+        for skill in requirements.skills.iter().flatten() {
+
+            if !self.has_skill(&skill.name) {
+                self.warning(location, format!("requires unknown skill {}", skill.name));
+            }
+
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod lint_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    #[test]
+    fn lint_standard() {
+
+        let lints = STANDARD_TEMPLATE.lint();
+        assert!(lints.iter().all(|l| l.level == LintLevel::Warning), "{:?}", lints);
+
+    }
+
+    #[test]
+    fn lint_problems() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        let perks = template.perks.as_mut().unwrap();
+        perks.push(perks[0].clone());
+        perks[1].attributes.as_mut().unwrap()[0].name = "Luck".to_string();
+
+        let weapons = &mut template.weapon_proficiencies.as_mut().unwrap().categories[0].weapons;
+        weapons[0].damage = Some("lots".to_string());
+
+        let lints = template.lint();
+
+        assert!(lints.contains(&TemplateLint {
+            level: LintLevel::Error,
+            location: "perk Force Sensitive".to_string(),
+            message: "more than one perk is named Force Sensitive".to_string()
+        }));
+        assert!(lints.contains(&TemplateLint {
+            level: LintLevel::Warning,
+            location: "perk Small Frame".to_string(),
+            message: "modifies unknown attribute Luck".to_string()
+        }));
+        assert!(lints.iter().any(|l| l.level == LintLevel::Error && l.message == "invalid damage expression lots"));
+
+    }
+
+    #[test]
+    fn lint_minimums() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.allotments.attributes.min_points_per_allotment = Some(3);
+        template.allotments.perks.as_mut().unwrap().min_perks = Some(5);
+
+        let messages: Vec<String> = template.lint()
+            .into_iter()
+            .filter(|l| l.level == LintLevel::Error)
+            .map(|l| l.message)
+            .collect();
+
+        let needed = 3 * template.attributes.len() as i64;
+        assert!(messages.contains(&format!("attribute minimums need {} points, but only 15 are given", needed)));
+        assert!(messages.contains(&"at least 5 perks are required, but at most 4 can be selected".to_string()));
+
+    }
+
+}
@@ -0,0 +1,246 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use std::collections::HashMap;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::CharacterTemplate;
+
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct TextTranslation {
+    pub name: Option<String>,
+    pub description: Option<String>
+}
+
+/**
+ * 
+ * The template's text in one locale. Elements are keyed by their id, or by their
+ * name when they don't have one. Anything left out falls back to the template's own text.
+ * 
+*/
+#[derive(Serialize, Deserialize, JsonSchema, Debug, Clone, Default)]
+pub struct Translation {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub attributes: Option<HashMap<String, TextTranslation>>,
+    pub skills: Option<HashMap<String, TextTranslation>>,
+    pub perks: Option<HashMap<String, TextTranslation>>,
+    pub equipment: Option<HashMap<String, TextTranslation>>,
+    pub resources: Option<HashMap<String, TextTranslation>>,
+    /// Validation messages keyed by error code, with {placeholders} for the error's values
+    pub errors: Option<HashMap<String, String>>
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl CharacterTemplate {
+
+    /**
+     * 
+     * A copy of the template with its names and descriptions in the given locale, for display.
+     * "fr-CA" falls back to "fr", then to the template's own text. Ids are kept, but sheets
+     * should still be validated and rolled against the original template.
+     * 
+    */
+    pub fn localized(&self, locale: String) -> CharacterTemplate {
+
+        let chain = self.translation_chain(&locale);
+        let mut template = self.clone();
+
+        localize(&chain, |t| t.name.as_ref(), &mut template.name);
+        localize(&chain, |t| t.description.as_ref(), &mut template.description);
+
+        for attr in template.attributes.iter_mut() {
+
+            let key = attr.id.clone().unwrap_or_else(|| attr.name.clone());
+            localize_text(&chain, |t| t.attributes.as_ref(), &key, &mut attr.name, &mut attr.description);
+
+            for skill in attr.skills.iter_mut().flatten() {
+
+                let key = skill.id.clone().unwrap_or_else(|| skill.name.clone());
+                localize_text(&chain, |t| t.skills.as_ref(), &key, &mut skill.name, &mut skill.description);
+
+            }
+
+        }
+
+        for perk in template.perks.iter_mut().flatten() {
+
+            let key = perk.id.clone().unwrap_or_else(|| perk.name.clone());
+            localize_text(&chain, |t| t.perks.as_ref(), &key, &mut perk.name, &mut perk.description);
+
+        }
+
+        for item in template.equipment.iter_mut().flatten() {
+
+            let key = item.name.clone();
+            localize_text(&chain, |t| t.equipment.as_ref(), &key, &mut item.name, &mut item.description);
+
+        }
+
+        for resource in template.resources.iter_mut().flatten() {
+
+            let key = resource.name.clone();
+            localize_text(&chain, |t| t.resources.as_ref(), &key, &mut resource.name, &mut resource.description);
+
+        }
+
+        template
+
+    }
+
+    /// The locales the template has translations for
+    pub fn locales(&self) -> Vec<String> {
+
+        let mut locales: Vec<String> = self.translations.iter().flat_map(|t| t.keys().cloned()).collect();
+        locales.sort();
+        locales
+
+    }
+
+}
+
+impl CharacterTemplate {
+
+    /// The translations to try for a locale, most specific first
+    fn translation_chain(&self, locale: &str) -> Vec<&Translation> {
+
+        let Some(translations) = &self.translations else {
+            return vec![];
+        };
+
+        let language = locale.split(['-', '_']).next().unwrap_or(locale);
+
+        [locale, language]
+            .iter()
+            .filter_map(|l| translations.get(*l))
+            .collect()
+
+    }
+
+    /// The translated validation message for an error code, if the locale has one
+    pub fn error_message(&self, locale: &str, code: &str) -> Option<&str> {
+
+        self.translation_chain(locale)
+            .iter()
+            .find_map(|t| t.errors.as_ref()?.get(code))
+            .map(|m| m.as_str())
+
+    }
+
+}
+
+/**
+ * 
+ * Replaces each `{name}` in a translated message with its parameter's value, in a single
+ * pass so a value that itself contains `{name}` is left as it is. Unknown placeholders are kept.
+ * 
+*/
+pub fn fill_placeholders(message: &str, params: &[(&str, String)]) -> String {
+
+    let mut filled = String::with_capacity(message.len());
+    let mut rest = message;
+
+    while let Some(start) = rest.find('{') {
+
+        filled.push_str(&rest[..start]);
+        rest = &rest[start..];
+
+        let value = rest
+            .find('}')
+            .and_then(|end| params.iter().find(|(name, _)| *name == &rest[1..end]).map(|(_, value)| (end, value)));
+
+        match value {
+            Some((end, value)) => {
+                filled.push_str(value);
+                rest = &rest[end + 1..];
+            },
+            None => {
+                filled.push('{');
+                rest = &rest[1..];
+            }
+        }
+
+    }
+
+    filled.push_str(rest);
+    filled
+
+}
+
+fn localize<'a, F>(chain: &[&'a Translation], pick: F, text: &mut String)
+where
+    F: Fn(&'a Translation) -> Option<&'a String>
+{
+
+    if let Some(translated) = chain.iter().find_map(|t| pick(t)) {
+        *text = translated.clone();
+    }
+
+}
+
+fn localize_text<'a, F>(chain: &[&'a Translation], table: F, key: &str, name: &mut String, description: &mut String)
+where
+    F: Fn(&'a Translation) -> Option<&'a HashMap<String, TextTranslation>>
+{
+
+    localize(chain, |t| table(t)?.get(key)?.name.as_ref(), name);
+    localize(chain, |t| table(t)?.get(key)?.description.as_ref(), description);
+
+}
+
+#[cfg(test)]
+mod localization_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    #[test]
+    fn localized_template() {
+
+        let french = STANDARD_TEMPLATE.localized("fr".to_string());
+
+        assert_eq!(french.find_attribute("strength").unwrap().name, "Force physique");
+        assert_eq!(french.find_perk("small_frame").unwrap().name, "Petite carrure");
+
+        // Untranslated text falls back to the template's own
+        let original = STANDARD_TEMPLATE.find_perk("veteran").unwrap();
+        let veteran = french.find_perk("veteran").unwrap();
+        assert_eq!(veteran.name, original.name);
+        assert_eq!(veteran.description, original.description);
+
+    }
+
+    #[test]
+    fn region_falls_back_to_language() {
+
+        let swiss = STANDARD_TEMPLATE.localized("de-CH".to_string());
+        let german = STANDARD_TEMPLATE.localized("de".to_string());
+        assert_eq!(swiss.description, german.description);
+        assert_ne!(swiss.description, STANDARD_TEMPLATE.description);
+
+        let unknown = STANDARD_TEMPLATE.localized("ja".to_string());
+        assert_eq!(unknown.description, STANDARD_TEMPLATE.description);
+
+        assert_eq!(STANDARD_TEMPLATE.locales(), vec!["de".to_string(), "fr".to_string()]);
+
+    }
+
+    #[test]
+    fn placeholders_are_filled_once() {
+
+        let params = [("perk", "{maximum}".to_string()), ("maximum", "3".to_string())];
+
+        assert_eq!(fill_placeholders("{perk} over {maximum}", &params), "{maximum} over 3");
+        assert_eq!(fill_placeholders("{unknown} {perk", &params), "{unknown} {perk");
+        assert_eq!(fill_placeholders("no placeholders, é", &params), "no placeholders, é");
+
+    }
+
+}
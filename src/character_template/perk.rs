@@ -1,18 +1,23 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
-use super::common::{AttributeModifier, SkillModifier};
+use super::common::{AttributeModifier, ResourceModifier, SituationalModifier, SkillModifier};
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct Perk {
+    /// Stable identifier that sheets can use in place of the name
+    pub id: Option<String>,
     pub name: String,
     pub description: String,
     pub point_cost: i64,
     pub attributes: Option<Vec<AttributeModifier>>,
     pub skills: Option<Vec<SkillModifier>>,
+    pub resources: Option<Vec<ResourceModifier>>,
+    pub situational: Option<Vec<SituationalModifier>>,
     pub base_health_modifier: Option<i64>,
     pub base_armor_class_modifier: Option<i64>
 }
\ No newline at end of file
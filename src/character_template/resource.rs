@@ -0,0 +1,34 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+
+use super::common::{AttributeModifier, Requirements};
+
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct ResourceRecovery {
+    /// What causes the recovery, e.g. "Short Rest" or "Long Rest"
+    pub trigger: String,
+    /// Restores the resource to its maximum when not set
+    pub amount: Option<i64>
+}
+
+/**
+ * 
+ * A pool a character spends from, such as Force points, stamina or credits.
+ * The maximum is the base value, plus each attribute's value multiplied by its
+ * modifier, plus any perk modifiers.
+ * 
+*/
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct Resource {
+    pub name: String,
+    pub description: String,
+    pub base_value: i64,
+    pub required: Option<Requirements>,
+    pub attributes: Option<Vec<AttributeModifier>>,
+    pub recovery: Option<Vec<ResourceRecovery>>
+}
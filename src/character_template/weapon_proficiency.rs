@@ -1,26 +1,134 @@
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
 
 use super::common::Requirements;
+use super::ids::is_reference_to;
+use crate::character_sheet::CharacterSheet;
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct WeaponProficiency {
-    pub categories: Vec<WeaponCategory>
+    pub categories: Vec<WeaponCategory>,
+    /// Added to attack rolls made with a weapon the character is proficient in
+    pub proficiency_bonus: Option<i64>,
+    /// Subtracted from attack rolls made with a weapon the character is not proficient in
+    pub non_proficiency_penalty: Option<i64>
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct WeaponCategory {
     pub category: String,
-    pub weapons: Vec<Weapon>
+    pub weapons: Vec<Weapon>,
+    /// Cost of taking the whole category as a single proficiency
+    pub point_cost: Option<i64>,
+    /// Every character is proficient in this category without selecting it
+    pub granted: Option<bool>
 }
 
-#[derive(Serialize, Deserialize, Clone)]
+#[derive(Serialize, Deserialize, JsonSchema, Clone)]
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
 pub struct Weapon {
+    /// Stable identifier that sheets can use in place of the name
+    pub id: Option<String>,
     pub weapon: String,
-    pub required: Option<Requirements>
-}
\ No newline at end of file
+    pub required: Option<Requirements>,
+    pub point_cost: Option<i64>,
+    /// The attribute used for attack rolls
+    pub attribute: Option<String>,
+    /// The skill used for attack rolls, takes precedence over `attribute`
+    pub skill: Option<String>,
+    /// Dice expression rolled for damage, e.g. "1d8+1"
+    pub damage: Option<String>,
+    pub range: Option<i64>,
+    pub tags: Option<Vec<String>>
+}
+
+impl WeaponProficiency {
+
+    /// Finds a weapon by its id or name
+    pub fn find_weapon(&self, reference: &str) -> Option<&Weapon> {
+
+        self.categories
+            .iter()
+            .flat_map(|c| c.weapons.iter())
+            .find(|w| is_reference_to(&w.id, &w.weapon, reference))
+
+    }
+
+    pub fn find_category(&self, name: &str) -> Option<&WeaponCategory> {
+        self.categories.iter().find(|c| c.category == name)
+    }
+
+    /// The categories a sheet is proficient in as a whole, the ones it lists and the granted ones
+    pub fn sheet_categories<'a>(&'a self, sheet: &'a CharacterSheet) -> impl Iterator<Item = &'a WeaponCategory> {
+
+        let listed = sheet.weapon_categories.as_deref().unwrap_or_default();
+        self.categories.iter().filter(move |c| c.granted.unwrap_or(false) || listed.contains(&c.category))
+
+    }
+
+    /**
+     * 
+     * The weapons a sheet lists on their own and pays for. A weapon whose category the sheet
+     * already has is covered by the category, so it isn't charged or counted again.
+     * 
+    */
+    pub fn charged_weapons<'a>(&'a self, sheet: &'a CharacterSheet) -> impl Iterator<Item = &'a Weapon> {
+
+        let covered: Vec<&String> = self.sheet_categories(sheet)
+            .flat_map(|c| c.weapons.iter())
+            .map(|w| &w.weapon)
+            .collect();
+
+        sheet.weapon_proficiencies
+            .iter()
+            .filter_map(|w| self.find_weapon(w))
+            .filter(move |w| !covered.contains(&&w.weapon))
+
+    }
+
+    /// How many proficiencies a sheet selects against the allotment, its charged weapons and listed categories
+    pub fn selected_count(&self, sheet: &CharacterSheet) -> usize {
+        self.charged_weapons(sheet).count() + sheet.weapon_categories.as_ref().map_or(0, |c| c.len())
+    }
+
+    /**
+     * 
+     * All the weapons a sheet is proficient in, from the weapons it lists directly and
+     * the categories it lists or is granted. Weapons from a category are only included
+     * when the sheet meets their requirements, the validator checks the requirements of
+     * weapons the sheet lists directly.
+     * 
+    */
+    pub fn proficient_weapons<'a>(&'a self, sheet: &'a CharacterSheet) -> Vec<&'a Weapon> {
+
+        let from_categories = self.sheet_categories(sheet)
+            .flat_map(|c| c.weapons.iter())
+            .filter(|w| w.required.as_ref().is_none_or(|r| r.is_met_by(sheet)));
+
+        let mut weapons: Vec<&Weapon> = sheet.weapon_proficiencies
+            .iter()
+            .filter_map(|w| self.find_weapon(w))
+            .collect();
+
+        for weapon in from_categories {
+
+            if !weapons.iter().any(|w| w.weapon == weapon.weapon) {
+                weapons.push(weapon);
+            }
+
+        }
+
+        weapons
+
+    }
+
+    pub fn is_proficient(&self, sheet: &CharacterSheet, weapon: &str) -> bool {
+        self.proficient_weapons(sheet).iter().any(|w| w.weapon == weapon)
+    }
+
+}
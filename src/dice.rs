@@ -0,0 +1,202 @@
+use std::fmt;
+use std::str::FromStr;
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum DiceError {
+    #[error("Invalid dice expression {0}")]
+    InvalidExpression(String),
+    #[error("Dice expression {0} has too many dice")]
+    TooManyDice(String),
+    #[error("Dice expression {0} is too large")]
+    Overflow(String)
+}
+
+const MAX_DICE: i64 = 100;
+
+/**
+ *
+ * A dice expression in the usual tabletop notation, e.g. `2d6+3`, `d20`, `1d8-1` or `4`
+ *
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq)]
+pub struct DiceExpression {
+    pub count: i64,
+    pub sides: i64,
+    pub modifier: i64
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct DiceRollResult {
+    pub expression: String,
+    pub rolls: Vec<i64>,
+    pub modifier: i64,
+    pub total: i64
+}
+
+impl DiceExpression {
+
+    pub fn roll<R: Rng + ?Sized>(&self, rng: &mut R) -> DiceRollResult {
+
+        let rolls: Vec<i64> = (0..self.count)
+            .map(|_| rng.gen_range(1..=self.sides))
+            .collect();
+
+        DiceRollResult {
+            expression: self.to_string(),
+            // Expressions built by hand rather than parsed can be too large to add up
+            total: rolls.iter().fold(self.modifier, |total, roll| total.saturating_add(*roll)),
+            rolls,
+            modifier: self.modifier
+        }
+
+    }
+
+}
+
+impl FromStr for DiceExpression {
+
+    type Err = DiceError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+
+        let invalid = || DiceError::InvalidExpression(s.to_string());
+        let expr: String = s.chars().filter(|c| !c.is_whitespace()).collect::<String>().to_lowercase();
+
+        if expr.is_empty() {
+            return Err(invalid());
+        }
+
+        // The modifier starts at the first sign that isn't the leading character
+        let sign = expr.char_indices().skip(1).find(|(_, c)| *c == '+' || *c == '-');
+        let (dice, modifier) = match sign {
+            Some((idx, _)) => expr.split_at(idx),
+            None           => (expr.as_str(), "")
+        };
+
+        let modifier: i64 = if modifier.is_empty() {
+            0
+        } else {
+            modifier.parse().map_err(|_| invalid())?
+        };
+
+        let Some((count, sides)) = dice.split_once('d') else {
+
+            // A flat value such as "4"
+            let flat: i64 = dice.parse().map_err(|_| invalid())?;
+            let modifier = flat.checked_add(modifier).ok_or_else(|| DiceError::Overflow(s.to_string()))?;
+            return Ok(Self { count: 0, sides: 0, modifier });
+
+        };
+
+        let count: i64 = if count.is_empty() { 1 } else { count.parse().map_err(|_| invalid())? };
+        let sides: i64 = sides.parse().map_err(|_| invalid())?;
+
+        if count < 1 || sides < 1 {
+            return Err(invalid());
+        }
+
+        if count > MAX_DICE {
+            return Err(DiceError::TooManyDice(s.to_string()));
+        }
+
+        // The highest roll has to fit, the lowest always does since there is at least one die
+        if count.checked_mul(sides).and_then(|highest| highest.checked_add(modifier)).is_none() {
+            return Err(DiceError::Overflow(s.to_string()));
+        }
+
+        Ok(Self { count, sides, modifier })
+
+    }
+
+}
+
+impl fmt::Display for DiceExpression {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+
+        if self.count == 0 {
+            return write!(f, "{}", self.modifier);
+        }
+
+        write!(f, "{}d{}", self.count, self.sides)?;
+
+        match self.modifier {
+            0           => Ok(()),
+            m if m > 0  => write!(f, "+{}", m),
+            m           => write!(f, "{}", m)
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod dice_tests {
+
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use super::*;
+
+    #[test]
+    fn parse_expressions() {
+
+        assert_eq!("2d6+3".parse(), Ok(DiceExpression { count: 2, sides: 6, modifier: 3 }));
+        assert_eq!("d20".parse(), Ok(DiceExpression { count: 1, sides: 20, modifier: 0 }));
+        assert_eq!("1D8 - 1".parse(), Ok(DiceExpression { count: 1, sides: 8, modifier: -1 }));
+        assert_eq!("4".parse(), Ok(DiceExpression { count: 0, sides: 0, modifier: 4 }));
+        assert_eq!("-2".parse(), Ok(DiceExpression { count: 0, sides: 0, modifier: -2 }));
+
+    }
+
+    #[test]
+    fn parse_invalid_expressions() {
+
+        assert_eq!("".parse::<DiceExpression>(), Err(DiceError::InvalidExpression("".to_string())));
+        assert_eq!("2d".parse::<DiceExpression>(), Err(DiceError::InvalidExpression("2d".to_string())));
+        assert_eq!("0d6".parse::<DiceExpression>(), Err(DiceError::InvalidExpression("0d6".to_string())));
+        assert_eq!("sword".parse::<DiceExpression>(), Err(DiceError::InvalidExpression("sword".to_string())));
+        assert_eq!("1000d6".parse::<DiceExpression>(), Err(DiceError::TooManyDice("1000d6".to_string())));
+        assert_eq!("éd6".parse::<DiceExpression>(), Err(DiceError::InvalidExpression("éd6".to_string())));
+        assert_eq!("2d6+é".parse::<DiceExpression>(), Err(DiceError::InvalidExpression("2d6+é".to_string())));
+
+        for overflow in [format!("{}+1", i64::MAX), format!("2d{}", i64::MAX), format!("1d6+{}", i64::MAX)] {
+            assert_eq!(overflow.parse::<DiceExpression>(), Err(DiceError::Overflow(overflow.clone())));
+        }
+
+        // Right at the limit still rolls
+        let largest: DiceExpression = format!("1d6+{}", i64::MAX - 6).parse().unwrap();
+        assert!(largest.roll(&mut StdRng::seed_from_u64(1)).total > i64::MAX - 6);
+
+    }
+
+    #[test]
+    fn display_round_trip() {
+
+        for expr in ["2d6+3", "1d20", "1d8-1", "4"] {
+            assert_eq!(expr.parse::<DiceExpression>().unwrap().to_string(), expr);
+        }
+
+    }
+
+    #[test]
+    fn roll_within_bounds() {
+
+        let mut rng = StdRng::seed_from_u64(7);
+        let expr: DiceExpression = "3d6+2".parse().unwrap();
+
+        for _ in 0..100 {
+
+            let result = expr.roll(&mut rng);
+            assert_eq!(result.rolls.len(), 3);
+            assert!(result.rolls.iter().all(|r| (1..=6).contains(r)));
+            assert!((5..=20).contains(&result.total));
+
+        }
+
+    }
+
+}
@@ -0,0 +1,269 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use serde::{Deserialize, Serialize};
+
+use crate::character_sheet::CharacterSheet;
+use crate::character_template::resource::Resource;
+use crate::character_template::CharacterTemplate;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct EffectiveSkill {
+    pub name: String,
+    /// Points allotted on the sheet
+    pub points: i64,
+    /// Points plus every perk and equipment modifier, including the attribute's when the template's
+    /// skills inherit them
+    pub value: i64
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct EffectiveAttribute {
+    pub name: String,
+    /// Points allotted on the sheet
+    pub points: i64,
+    /// Points plus every perk and equipment modifier
+    pub value: i64,
+    pub skills: Vec<EffectiveSkill>
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct EffectiveResource {
+    pub name: String,
+    pub maximum: i64
+}
+
+/**
+ *
+ * A sheet's values after perks and equipped items have been applied, along with
+ * the health and armor class derived from the template's base values.
+ *
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct EffectiveStats {
+    pub health: i64,
+    pub armor_class: i64,
+    pub attributes: Vec<EffectiveAttribute>,
+    /// Only the resources the sheet meets the requirements for
+    pub resources: Vec<EffectiveResource>
+}
+
+impl EffectiveStats {
+
+    pub fn new(template: &CharacterTemplate, sheet: &CharacterSheet) -> Self {
+
+        let sheet = &sheet.resolve_ids(template);
+
+        let attributes = sheet.attributes
+            .iter()
+            .map(|attr| {
+
+                let modifier = attribute_modifier(template, sheet, &attr.name);
+                let inherited = if template.skills_inherit_attribute_modifiers() { modifier } else { 0 };
+
+                let skills = attr.skills
+                    .iter()
+                    .flatten()
+                    .map(|skill| EffectiveSkill {
+                        name: skill.name.clone(),
+                        points: skill.value,
+                        value: skill.value + skill_modifier(template, sheet, &skill.name) + inherited
+                    })
+                    .collect();
+
+                EffectiveAttribute {
+                    name: attr.name.clone(),
+                    points: attr.value,
+                    value: attr.value + modifier,
+                    skills
+                }
+
+            })
+            .collect();
+
+        let resources = template.resources
+            .iter()
+            .flatten()
+            .filter(|r| r.required.as_ref().is_none_or(|req| req.is_met_by(sheet)))
+            .map(|r| EffectiveResource {
+                name: r.name.clone(),
+                maximum: resource_maximum(template, sheet, r)
+            })
+            .collect();
+
+        Self {
+            health: derived_health(template, sheet),
+            armor_class: derived_armor_class(template, sheet),
+            attributes,
+            resources
+        }
+
+    }
+
+    pub fn attribute(&self, name: &str) -> Option<&EffectiveAttribute> {
+        self.attributes.iter().find(|a| a.name == name)
+    }
+
+    pub fn resource(&self, name: &str) -> Option<&EffectiveResource> {
+        self.resources.iter().find(|r| r.name == name)
+    }
+
+    pub fn skill(&self, name: &str) -> Option<&EffectiveSkill> {
+
+        self.attributes
+            .iter()
+            .flat_map(|a| a.skills.iter())
+            .find(|s| s.name == name)
+
+    }
+
+}
+
+/// The template's base health, or its health formula, plus perk and equipment modifiers
+pub fn derived_health(template: &CharacterTemplate, sheet: &CharacterSheet) -> i64 {
+
+    let perks: i64 = template.sheet_perks(sheet).filter_map(|p| p.base_health_modifier).sum();
+    let equipment: i64 = template.sheet_equipped(sheet).filter_map(|e| e.base_health_modifier).sum();
+
+    let base = template.health_formula.as_ref().map_or(template.base_health, |f| f.evaluate(sheet));
+    base + perks + equipment
+
+}
+
+/// The template's base armor class, or its armor class formula, plus perk and equipment modifiers
+pub fn derived_armor_class(template: &CharacterTemplate, sheet: &CharacterSheet) -> i64 {
+
+    let perks: i64 = template.sheet_perks(sheet).filter_map(|p| p.base_armor_class_modifier).sum();
+    let equipment: i64 = template.sheet_equipped(sheet).filter_map(|e| e.base_armor_class_modifier).sum();
+
+    let base = template.armor_class_formula.as_ref().map_or(template.base_armor_class, |f| f.evaluate(sheet));
+    base + perks + equipment
+
+}
+
+/// The resource's base value plus its attribute scaling and perk modifiers, never below zero
+pub fn resource_maximum(template: &CharacterTemplate, sheet: &CharacterSheet, resource: &Resource) -> i64 {
+
+    let from_attributes: i64 = resource.attributes
+        .iter()
+        .flatten()
+        .map(|m| {
+
+            let value = sheet.attributes
+                .iter()
+                .find(|a| a.name == m.name)
+                .map_or(0, |a| a.value + attribute_modifier(template, sheet, &a.name));
+
+            value * m.value(sheet)
+
+        })
+        .sum();
+
+    let from_perks: i64 = template
+        .sheet_perks(sheet)
+        .filter_map(|p| p.resources.as_ref())
+        .flatten()
+        .filter(|m| m.name == resource.name)
+        .map(|m| m.value(sheet))
+        .sum();
+
+    (resource.base_value + from_attributes + from_perks).max(0)
+
+}
+
+fn attribute_modifier(template: &CharacterTemplate, sheet: &CharacterSheet, name: &str) -> i64 {
+
+    let perks = template.sheet_perks(sheet).filter_map(|p| p.attributes.as_ref());
+    let equipment = template.sheet_equipped(sheet).filter_map(|e| e.attributes.as_ref());
+
+    perks
+        .chain(equipment)
+        .flatten()
+        .filter(|m| m.name == name)
+        .map(|m| m.value(sheet))
+        .sum()
+
+}
+
+fn skill_modifier(template: &CharacterTemplate, sheet: &CharacterSheet, name: &str) -> i64 {
+
+    let perks = template.sheet_perks(sheet).filter_map(|p| p.skills.as_ref());
+    let equipment = template.sheet_equipped(sheet).filter_map(|e| e.skills.as_ref());
+
+    perks
+        .chain(equipment)
+        .flatten()
+        .filter(|m| m.name == name)
+        .map(|m| m.value(sheet))
+        .sum()
+
+}
+
+#[cfg(test)]
+mod effective_stats_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    #[test]
+    fn perks_apply_to_stats() {
+
+        let stats = EffectiveStats::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE);
+
+        // Small Frame
+        assert_eq!(stats.attribute("Agility").unwrap().value, 4 + 2);
+        assert_eq!(stats.attribute("Strength").unwrap().value, -3);
+
+        // Charismatic
+        assert_eq!(stats.skill("Persuasion").unwrap().value, 3 + 3);
+
+        // Stealth inherits Small Frame's Agility modifier
+        assert_eq!(stats.skill("Stealth").unwrap().value, 2);
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.skills_inherit_attribute_modifiers = Some(false);
+
+        let stats = EffectiveStats::new(&template, &CHARACTER_SHEET_SAMPLE);
+        assert_eq!(stats.skill("Stealth").unwrap().value, 0);
+
+        assert_eq!(stats.health, STANDARD_TEMPLATE.base_health);
+        assert_eq!(stats.armor_class, STANDARD_TEMPLATE.base_armor_class);
+
+    }
+
+    #[test]
+    fn equipment_applies_to_stats() {
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        sheet.inventory = Some(vec!["Light Armor".to_string(), "Medkit".to_string()]);
+        sheet.equipped  = Some(vec!["Light Armor".to_string()]);
+
+        let light_armor = STANDARD_TEMPLATE.find_equipment("Light Armor").unwrap();
+        let stats = EffectiveStats::new(&STANDARD_TEMPLATE, &sheet);
+
+        assert_eq!(stats.armor_class, STANDARD_TEMPLATE.base_armor_class + light_armor.base_armor_class_modifier.unwrap());
+
+        // The medkit is carried but not equipped
+        assert_eq!(stats.skill("Medicine").unwrap().value, 10);
+
+        sheet.equipped = Some(vec!["Light Armor".to_string(), "Medkit".to_string()]);
+        let stats = EffectiveStats::new(&STANDARD_TEMPLATE, &sheet);
+        assert_eq!(stats.skill("Medicine").unwrap().value, 12);
+
+    }
+
+}
@@ -0,0 +1,503 @@
+use std::cmp::Ordering;
+
+use rand::prelude::*;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::character_roll::{CharacterRoll, RollError, RollTarget};
+use crate::character_sheet::CharacterSheet;
+use crate::character_state::CharacterState;
+use crate::character_template::CharacterTemplate;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EncounterError {
+    #[error("Failed to roll initiative: {0}")]
+    Roll(#[from] RollError),
+    #[error("{0} is not in the encounter")]
+    UnknownCombatant(String),
+    #[error("{0} is not delaying their turn")]
+    NotDelayed(String),
+    #[error("The encounter has no combatants")]
+    NoCombatants
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Combatant {
+    pub name: String,
+    pub initiative: i64,
+    pub initiative_modifier: i64,
+    /// Rolled when joining, breaks ties between equal initiative and modifier
+    pub tiebreaker: i64,
+    pub delayed: bool,
+    pub state: CharacterState
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct ExpiredCondition {
+    pub combatant: String,
+    pub condition: String
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct Turn {
+    pub round: i64,
+    pub combatant: String,
+    /// Conditions that ran out when a new round started
+    pub expired_conditions: Vec<ExpiredCondition>
+}
+
+/**
+ *
+ * Turn order for a group of characters. Combatants are kept sorted by initiative,
+ * and conditions on every combatant tick down once at the start of each new round.
+ *
+*/
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Encounter {
+    /// Set once the first turn ends, combatants joining before then can take the first turn
+    pub started: bool,
+    pub round: i64,
+    /// Index into `combatants` of whoever is currently acting
+    pub turn: usize,
+    pub combatants: Vec<Combatant>
+}
+
+impl Default for Encounter {
+
+    fn default() -> Self {
+        Self::new()
+    }
+
+}
+
+impl Encounter {
+
+    pub fn new() -> Self {
+
+        Self {
+            started: false,
+            round: 1,
+            turn: 0,
+            combatants: vec![]
+        }
+
+    }
+
+    pub fn add_combatant(&mut self, template: &CharacterTemplate, sheet: &CharacterSheet, state: Option<CharacterState>) -> Result<&Combatant, EncounterError> {
+        self.add_combatant_with_rng(template, sheet, state, &mut thread_rng())
+    }
+
+    /**
+     *
+     * Rolls initiative for the character and slots them into the turn order. A character whose
+     * name is already in the encounter is numbered, so a third "Stormtrooper" joins as "Stormtrooper 3".
+     *
+    */
+    pub fn add_combatant_with_rng<R: Rng + ?Sized>(&mut self, template: &CharacterTemplate, sheet: &CharacterSheet, state: Option<CharacterState>, rng: &mut R) -> Result<&Combatant, EncounterError> {
+
+        let name = self.unique_name(&sheet.name);
+        let state = state.unwrap_or_else(|| CharacterState::new(template, sheet));
+
+        let (initiative, initiative_modifier) = match initiative_target(template) {
+            Some(target) => {
+
+                let result = CharacterRoll::new(template, sheet, target)
+                    .with_state(&state)
+                    .roll_with_rng(rng)?;
+
+                (result.value, result.modifier)
+
+            },
+            None => (rng.gen_range(1..=20), 0)
+        };
+
+        let combatant = Combatant {
+            name,
+            initiative,
+            initiative_modifier,
+            tiebreaker: rng.gen_range(1..=20),
+            delayed: false,
+            state
+        };
+
+        let idx = self.combatants
+            .iter()
+            .position(|c| turn_order(&combatant, c) == Ordering::Less)
+            .unwrap_or(self.combatants.len());
+
+        // Once started, keep the current turn pointing at the same combatant
+        if self.started && idx <= self.turn {
+            self.turn += 1;
+        }
+
+        self.combatants.insert(idx, combatant);
+        Ok(&self.combatants[idx])
+
+    }
+
+    pub fn remove_combatant(&mut self, name: &str) -> Result<Combatant, EncounterError> {
+
+        let idx = self.position(name)?;
+        let combatant = self.combatants.remove(idx);
+
+        if idx < self.turn {
+            self.turn -= 1;
+        } else if self.turn >= self.combatants.len() {
+            self.turn = 0;
+        }
+
+        Ok(combatant)
+
+    }
+
+    pub fn current(&self) -> Option<&Combatant> {
+        self.combatants.get(self.turn)
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Combatant> {
+        self.combatants.iter().find(|c| c.name == name)
+    }
+
+    pub fn find_mut(&mut self, name: &str) -> Option<&mut Combatant> {
+        self.combatants.iter_mut().find(|c| c.name == name)
+    }
+
+    /// Ends the current turn and moves to the next combatant that isn't delaying. When everyone is
+    /// delaying, the turn comes back round to a combatant who stops delaying and acts in their own slot.
+    pub fn next_turn(&mut self) -> Result<Turn, EncounterError> {
+
+        if self.combatants.is_empty() {
+            return Err(EncounterError::NoCombatants);
+        }
+
+        self.started = true;
+        let mut expired_conditions = vec![];
+
+        for _ in 0..self.combatants.len() {
+
+            self.turn += 1;
+
+            if self.turn >= self.combatants.len() {
+
+                self.turn = 0;
+                self.round += 1;
+                expired_conditions.extend(self.tick_conditions());
+
+            }
+
+            if !self.combatants[self.turn].delayed {
+                break;
+            }
+
+        }
+
+        self.combatants[self.turn].delayed = false;
+
+        Ok(Turn {
+            round: self.round,
+            combatant: self.combatants[self.turn].name.clone(),
+            expired_conditions
+        })
+
+    }
+
+    /// The current combatant holds their turn until `resume` is called
+    pub fn delay(&mut self) -> Result<Turn, EncounterError> {
+
+        let current = self.combatants
+            .get_mut(self.turn)
+            .ok_or(EncounterError::NoCombatants)?;
+
+        current.delayed = true;
+        self.next_turn()
+
+    }
+
+    /// A delayed combatant acts now, taking the initiative of whoever's turn it is
+    pub fn resume(&mut self, name: &str) -> Result<Turn, EncounterError> {
+
+        let idx = self.position(name)?;
+        if !self.combatants[idx].delayed {
+            return Err(EncounterError::NotDelayed(name.to_string()));
+        }
+
+        // It's already their turn, they act in their own slot
+        if idx == self.turn {
+
+            self.combatants[idx].delayed = false;
+
+            return Ok(Turn {
+                round: self.round,
+                combatant: name.to_string(),
+                expired_conditions: vec![]
+            });
+
+        }
+
+        let mut combatant = self.combatants.remove(idx);
+        if idx < self.turn {
+            self.turn -= 1;
+        }
+
+        let current = &self.combatants[self.turn];
+        combatant.delayed    = false;
+        combatant.initiative = current.initiative;
+        combatant.initiative_modifier = current.initiative_modifier;
+        combatant.tiebreaker = current.tiebreaker;
+
+        self.combatants.insert(self.turn, combatant);
+
+        Ok(Turn {
+            round: self.round,
+            combatant: name.to_string(),
+            expired_conditions: vec![]
+        })
+
+    }
+
+    pub fn as_json_str(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    pub fn from_json_str(json: &str) -> Result<Encounter, String> {
+        serde_json::from_str(json).map_err(|_| "Failed to parse JSON".to_string())
+    }
+
+    fn tick_conditions(&mut self) -> Vec<ExpiredCondition> {
+
+        self.combatants
+            .iter_mut()
+            .flat_map(|c| {
+
+                let name = c.name.clone();
+                c.state
+                    .tick_conditions()
+                    .into_iter()
+                    .map(move |condition| ExpiredCondition { combatant: name.clone(), condition })
+
+            })
+            .collect()
+
+    }
+
+    fn unique_name(&self, name: &str) -> String {
+
+        if self.find(name).is_none() {
+            return name.to_string();
+        }
+
+        (2..)
+            .map(|n| format!("{} {}", name, n))
+            .find(|numbered| self.find(numbered).is_none())
+            .unwrap()
+
+    }
+
+    fn position(&self, name: &str) -> Result<usize, EncounterError> {
+
+        self.combatants
+            .iter()
+            .position(|c| c.name == name)
+            .ok_or_else(|| EncounterError::UnknownCombatant(name.to_string()))
+
+    }
+
+}
+
+/// Resolves the template's initiative against its attributes first, then its skills
+fn initiative_target(template: &CharacterTemplate) -> Option<RollTarget> {
+
+    let name = template.initiative.as_ref()?;
+
+    if template.attributes.iter().any(|a| a.name == *name) {
+        return Some(RollTarget::Attribute(name.clone()));
+    }
+
+    Some(RollTarget::Skill(name.clone()))
+
+}
+
+/// Higher initiative goes first, then the higher modifier, then the tiebreaker roll
+fn turn_order(a: &Combatant, b: &Combatant) -> Ordering {
+
+    b.initiative.cmp(&a.initiative)
+        .then(b.initiative_modifier.cmp(&a.initiative_modifier))
+        .then(b.tiebreaker.cmp(&a.tiebreaker))
+
+}
+
+#[cfg(test)]
+mod encounter_tests {
+
+    use std::sync::LazyLock;
+    use rand::rngs::StdRng;
+    use super::*;
+    use crate::character_state::Condition;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    fn sheet_named(name: &str) -> CharacterSheet {
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        sheet.name = name.to_string();
+        sheet
+
+    }
+
+    fn encounter_of(names: &[&str]) -> Encounter {
+
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut encounter = Encounter::new();
+
+        for name in names {
+            encounter.add_combatant_with_rng(&STANDARD_TEMPLATE, &sheet_named(name), None, &mut rng).unwrap();
+        }
+
+        encounter
+
+    }
+
+    #[test]
+    fn combatants_sorted_by_initiative() {
+
+        let encounter = encounter_of(&["Alpha", "Bravo", "Charlie", "Delta"]);
+
+        assert!(encounter.combatants
+            .windows(2)
+            .all(|w| turn_order(&w[0], &w[1]) != Ordering::Greater));
+
+        // Agility 4 and Small Frame +2
+        assert!(encounter.combatants.iter().all(|c| c.initiative_modifier == 6));
+
+    }
+
+    #[test]
+    fn duplicate_names_are_numbered() {
+
+        let mut encounter = encounter_of(&["Stormtrooper", "Stormtrooper", "Stormtrooper 2"]);
+
+        let mut names: Vec<&str> = encounter.combatants.iter().map(|c| c.name.as_str()).collect();
+        names.sort();
+        assert_eq!(names, vec!["Stormtrooper", "Stormtrooper 2", "Stormtrooper 2 2"]);
+
+        let combatant = encounter.add_combatant(&STANDARD_TEMPLATE, &sheet_named("Stormtrooper"), None).unwrap();
+        assert_eq!(combatant.name, "Stormtrooper 3");
+
+        assert!(encounter.remove_combatant("Stormtrooper 2").is_ok());
+        assert!(encounter.find("Stormtrooper").is_some());
+
+    }
+
+    #[test]
+    fn rounds_advance_and_tick_conditions() {
+
+        let mut encounter = encounter_of(&["Alpha", "Bravo"]);
+        encounter.find_mut("Bravo").unwrap().state.add_condition(Condition {
+            name: "Stunned".to_string(),
+            duration: Some(1),
+            roll_modifier: Some(-2),
+            attributes: None,
+            skills: None
+        });
+
+        let first = encounter.current().unwrap().name.clone();
+
+        let turn = encounter.next_turn().unwrap();
+        assert_eq!(turn.round, 1);
+        assert!(turn.expired_conditions.is_empty());
+
+        let turn = encounter.next_turn().unwrap();
+        assert_eq!(turn.round, 2);
+        assert_eq!(turn.combatant, first);
+        assert_eq!(turn.expired_conditions, vec![ExpiredCondition {
+            combatant: "Bravo".to_string(),
+            condition: "Stunned".to_string()
+        }]);
+
+    }
+
+    #[test]
+    fn delay_and_resume() {
+
+        let mut encounter = encounter_of(&["Alpha", "Bravo", "Charlie"]);
+        let order: Vec<String> = encounter.combatants.iter().map(|c| c.name.clone()).collect();
+
+        // The first combatant delays, the second acts, then the first jumps in before the third
+        let turn = encounter.delay().unwrap();
+        assert_eq!(turn.combatant, order[1]);
+
+        let turn = encounter.next_turn().unwrap();
+        assert_eq!(turn.combatant, order[2]);
+
+        encounter.resume(&order[0]).unwrap();
+        assert_eq!(encounter.current().unwrap().name, order[0]);
+
+        let turn = encounter.next_turn().unwrap();
+        assert_eq!(turn.combatant, order[2]);
+
+        let turn = encounter.next_turn().unwrap();
+        assert_eq!(turn.round, 2);
+        assert_eq!(turn.combatant, order[1]);
+
+        assert_eq!(encounter.resume(&order[1]).unwrap_err(), EncounterError::NotDelayed(order[1].clone()));
+
+    }
+
+    #[test]
+    fn everyone_delayed() {
+
+        let mut encounter = encounter_of(&["Alpha"]);
+
+        // With no one else to act, the turn comes straight back and the delay is dropped
+        let turn = encounter.delay().unwrap();
+        assert_eq!(turn.combatant, "Alpha");
+        assert_eq!(turn.round, 2);
+        assert!(!encounter.current().unwrap().delayed);
+
+        assert_eq!(encounter.resume("Alpha").unwrap_err(), EncounterError::NotDelayed("Alpha".to_string()));
+
+    }
+
+    #[test]
+    fn resume_last_slot() {
+
+        let mut encounter = encounter_of(&["Alpha", "Bravo"]);
+        let order: Vec<String> = encounter.combatants.iter().map(|c| c.name.clone()).collect();
+
+        // Both delay, the turn wraps round and the last one acts in their own slot
+        encounter.delay().unwrap();
+        encounter.delay().unwrap();
+        assert_eq!(encounter.turn, 1);
+        assert!(!encounter.current().unwrap().delayed);
+
+        let turn = encounter.next_turn().unwrap();
+        assert_eq!(turn.round, 3);
+        assert_eq!(turn.combatant, order[1]);
+
+        encounter.resume(&order[0]).unwrap();
+        assert_eq!(encounter.current().unwrap().name, order[0]);
+        assert_eq!(encounter.combatants.len(), 2);
+
+    }
+
+    #[test]
+    fn json_round_trip() {
+
+        let mut encounter = encounter_of(&["Alpha", "Bravo"]);
+        encounter.next_turn().unwrap();
+
+        let restored = Encounter::from_json_str(&encounter.as_json_str()).unwrap();
+        assert_eq!(restored.round, encounter.round);
+        assert_eq!(restored.current().unwrap().name, encounter.current().unwrap().name);
+
+    }
+
+}
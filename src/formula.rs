@@ -0,0 +1,754 @@
+use std::fmt;
+
+use schemars::gen::SchemaGenerator;
+use schemars::schema::Schema;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::character_sheet::CharacterSheet;
+
+#[derive(Error, Debug, PartialEq, Clone)]
+pub enum FormulaError {
+    #[error("Invalid formula {formula}: {message}")]
+    Invalid {
+        formula: String,
+        message: String
+    },
+    #[error("Formula {formula} references unknown name {identifier}")]
+    UnknownIdentifier {
+        formula: String,
+        identifier: String
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Function {
+    Floor,
+    Ceil,
+    Round,
+    Abs,
+    Min,
+    Max,
+    If,
+    Perk
+}
+
+impl Function {
+
+    fn from_name(name: &str) -> Option<Self> {
+
+        match name {
+            "floor" => Some(Function::Floor),
+            "ceil"  => Some(Function::Ceil),
+            "round" => Some(Function::Round),
+            "abs"   => Some(Function::Abs),
+            "min"   => Some(Function::Min),
+            "max"   => Some(Function::Max),
+            "if"    => Some(Function::If),
+            "perk"  => Some(Function::Perk),
+            _       => None
+        }
+
+    }
+
+    fn arity(&self) -> (usize, usize) {
+
+        match self {
+            Function::Min | Function::Max => (1, usize::MAX),
+            Function::If                  => (3, 3),
+            _                             => (1, 1)
+        }
+
+    }
+
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+    Rem,
+    Eq,
+    Ne,
+    Lt,
+    Le,
+    Gt,
+    Ge,
+    And,
+    Or
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Expr {
+    Number(f64),
+    Text(String),
+    Variable(String),
+    Negate(Box<Expr>),
+    Not(Box<Expr>),
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    Call(Function, Vec<Expr>)
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Identifier(String),
+    Text(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma
+}
+
+/**
+ *
+ * A small arithmetic expression evaluated against a character sheet, e.g. `10 + Prowess * 2`
+ * or `7 + floor(Agility / 2)`.
+ *
+ * Names refer to the points allotted on the sheet, in order: `level`, attributes, skills, then
+ * perks which count as 1 when the sheet has them and 0 otherwise. Names with spaces are written
+ * in brackets, `[Sleight of Hand]`, and perks can also be checked with `perk("Force Sensitive")`.
+ * Comparisons and `&&`, `||`, `!` treat non-zero as true and produce 1 or 0.
+ *
+ * Evaluation never fails, names the sheet doesn't have count as 0 and dividing by zero gives 0.
+ * Use `CharacterTemplate::check_formulas` to catch names the template doesn't define.
+ *
+*/
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Formula {
+    source: String,
+    expr: Expr
+}
+
+impl Formula {
+
+    pub fn parse(source: &str) -> Result<Self, FormulaError> {
+
+        let invalid = |message: String| FormulaError::Invalid {
+            formula: source.to_string(),
+            message
+        };
+
+        let tokens = tokenize(source).map_err(invalid)?;
+        if tokens.len() > MAX_TOKENS {
+            return Err(invalid(format!("longer than {} tokens", MAX_TOKENS)));
+        }
+
+        let mut parser = Parser { tokens: &tokens, pos: 0, depth: 0 };
+
+        let expr = parser.expression().map_err(invalid)?;
+        if parser.pos != tokens.len() {
+            return Err(invalid(format!("unexpected {:?}", tokens[parser.pos])));
+        }
+
+        Ok(Self {
+            source: source.to_string(),
+            expr
+        })
+
+    }
+
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Evaluates the formula, rounding the result down to a whole number
+    pub fn evaluate(&self, sheet: &CharacterSheet) -> i64 {
+        evaluate(&self.expr, sheet).floor() as i64
+    }
+
+    pub fn evaluate_bool(&self, sheet: &CharacterSheet) -> bool {
+        evaluate(&self.expr, sheet) != 0.0
+    }
+
+    /// Every attribute, skill or perk name the formula refers to
+    pub fn identifiers(&self) -> Vec<&str> {
+
+        let mut identifiers = vec![];
+        collect_identifiers(&self.expr, &mut identifiers);
+        identifiers
+
+    }
+
+}
+
+impl TryFrom<String> for Formula {
+
+    type Error = FormulaError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Formula::parse(&value)
+    }
+
+}
+
+impl From<Formula> for String {
+
+    fn from(value: Formula) -> Self {
+        value.source
+    }
+
+}
+
+/// Formulas are written as strings in templates
+impl JsonSchema for Formula {
+
+    fn schema_name() -> String {
+        "Formula".to_string()
+    }
+
+    fn json_schema(gen: &mut SchemaGenerator) -> Schema {
+
+        let mut schema = String::json_schema(gen).into_object();
+        schema.metadata().description = Some("A formula such as \"10 + Prowess * 2\" or \"Agility + Stealth >= 3\"".to_string());
+        schema.into()
+
+    }
+
+}
+
+impl fmt::Display for Formula {
+
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+
+}
+
+fn tokenize(source: &str) -> Result<Vec<Token>, String> {
+
+    let chars: Vec<char> = source.chars().collect();
+    let mut tokens = vec![];
+    let mut i = 0;
+
+    while i < chars.len() {
+
+        let c = chars[i];
+
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+
+        if c.is_ascii_digit() || c == '.' {
+
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_digit() || chars[i] == '.') {
+                i += 1;
+            }
+
+            let text: String = chars[start..i].iter().collect();
+            let number = text.parse().map_err(|_| format!("invalid number {}", text))?;
+            tokens.push(Token::Number(number));
+            continue;
+
+        }
+
+        if c.is_alphabetic() || c == '_' {
+
+            let start = i;
+            while i < chars.len() && (chars[i].is_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+
+            tokens.push(Token::Identifier(chars[start..i].iter().collect()));
+            continue;
+
+        }
+
+        if c == '[' || c == '"' {
+
+            let close = if c == '[' { ']' } else { '"' };
+            let start = i + 1;
+            let end = chars[start..]
+                .iter()
+                .position(|ch| *ch == close)
+                .map(|p| start + p)
+                .ok_or_else(|| format!("missing closing {}", close))?;
+
+            let text: String = chars[start..end].iter().collect();
+            tokens.push(if c == '[' { Token::Identifier(text) } else { Token::Text(text) });
+
+            i = end + 1;
+            continue;
+
+        }
+
+        let two: String = chars[i..(i + 2).min(chars.len())].iter().collect();
+        let op = ["==", "!=", "<=", ">=", "&&", "||"]
+            .into_iter()
+            .find(|op| *op == two);
+
+        if let Some(op) = op {
+            tokens.push(Token::Op(op));
+            i += 2;
+            continue;
+        }
+
+        let token = match c {
+            '(' => Token::LParen,
+            ')' => Token::RParen,
+            ',' => Token::Comma,
+            '+' => Token::Op("+"),
+            '-' => Token::Op("-"),
+            '*' => Token::Op("*"),
+            '/' => Token::Op("/"),
+            '%' => Token::Op("%"),
+            '<' => Token::Op("<"),
+            '>' => Token::Op(">"),
+            '!' => Token::Op("!"),
+            _   => return Err(format!("unexpected character {}", c))
+        };
+
+        tokens.push(token);
+        i += 1;
+
+    }
+
+    Ok(tokens)
+
+}
+
+/// How deep parentheses, function calls and unary operators can nest, deeper formulas would overflow the stack
+const MAX_NESTING: usize = 64;
+
+/**
+ * Every node of a parsed formula takes at least one token, so this bounds how deep evaluating,
+ * collecting identifiers and dropping a formula recurse, even for a flat chain like 1+1+1...
+ * that doesn't nest.
+*/
+const MAX_TOKENS: usize = 1000;
+
+struct Parser<'a> {
+    tokens: &'a [Token],
+    pos: usize,
+    depth: usize
+}
+
+impl Parser<'_> {
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(format!("expected {:?} but found {:?}", expected, token)),
+            None        => Err(format!("expected {:?} but the formula ended", expected))
+        }
+
+    }
+
+    /// Parses a chain of left associative binary operators, one precedence level at a time
+    fn binary(&mut self, level: usize) -> Result<Expr, String> {
+
+        const LEVELS: [&[(&str, BinaryOp)]; 5] = [
+            &[("||", BinaryOp::Or)],
+            &[("&&", BinaryOp::And)],
+            &[("==", BinaryOp::Eq), ("!=", BinaryOp::Ne), ("<", BinaryOp::Lt), ("<=", BinaryOp::Le), (">", BinaryOp::Gt), (">=", BinaryOp::Ge)],
+            &[("+", BinaryOp::Add), ("-", BinaryOp::Sub)],
+            &[("*", BinaryOp::Mul), ("/", BinaryOp::Div), ("%", BinaryOp::Rem)]
+        ];
+
+        if level == LEVELS.len() {
+            return self.unary();
+        }
+
+        let mut lhs = self.binary(level + 1)?;
+
+        while let Some(Token::Op(op)) = self.peek() {
+
+            let Some((_, binary_op)) = LEVELS[level].iter().find(|(o, _)| o == op) else {
+                break;
+            };
+
+            self.pos += 1;
+            let rhs = self.binary(level + 1)?;
+            lhs = Expr::Binary(*binary_op, Box::new(lhs), Box::new(rhs));
+
+        }
+
+        Ok(lhs)
+
+    }
+
+    fn expression(&mut self) -> Result<Expr, String> {
+        self.nested(|parser| parser.binary(0))
+    }
+
+    fn unary(&mut self) -> Result<Expr, String> {
+
+        match self.peek() {
+            Some(Token::Op("-")) => {
+                self.pos += 1;
+                Ok(Expr::Negate(Box::new(self.nested(Self::unary)?)))
+            },
+            Some(Token::Op("!")) => {
+                self.pos += 1;
+                Ok(Expr::Not(Box::new(self.nested(Self::unary)?)))
+            },
+            _ => self.primary()
+        }
+
+    }
+
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> Result<Expr, String>) -> Result<Expr, String> {
+
+        if self.depth == MAX_NESTING {
+            return Err(format!("nested more than {} deep", MAX_NESTING));
+        }
+
+        self.depth += 1;
+        let expr = parse(self);
+        self.depth -= 1;
+
+        expr
+
+    }
+
+    fn primary(&mut self) -> Result<Expr, String> {
+
+        match self.next() {
+            Some(Token::Number(n))  => Ok(Expr::Number(n)),
+            Some(Token::Text(text)) => Ok(Expr::Text(text)),
+            Some(Token::LParen)     => {
+
+                let expr = self.expression()?;
+                self.expect(Token::RParen)?;
+                Ok(expr)
+
+            },
+            Some(Token::Identifier(name)) => {
+
+                if self.peek() != Some(&Token::LParen) {
+                    return Ok(Expr::Variable(name));
+                }
+
+                let function = Function::from_name(&name).ok_or_else(|| format!("unknown function {}", name))?;
+                self.pos += 1;
+
+                let mut args = vec![];
+                if self.peek() != Some(&Token::RParen) {
+
+                    args.push(self.expression()?);
+                    while self.peek() == Some(&Token::Comma) {
+                        self.pos += 1;
+                        args.push(self.expression()?);
+                    }
+
+                }
+
+                self.expect(Token::RParen)?;
+
+                let (min, max) = function.arity();
+                if args.len() < min || args.len() > max {
+                    return Err(format!("wrong number of arguments to {}", name));
+                }
+
+                if function == Function::Perk && !matches!(args[0], Expr::Text(_) | Expr::Variable(_)) {
+                    return Err("perk expects a name".to_string());
+                }
+
+                Ok(Expr::Call(function, args))
+
+            },
+            Some(token) => Err(format!("unexpected {:?}", token)),
+            None        => Err("the formula ended unexpectedly".to_string())
+        }
+
+    }
+
+}
+
+fn truthy(value: bool) -> f64 {
+    if value { 1.0 } else { 0.0 }
+}
+
+fn lookup(sheet: &CharacterSheet, name: &str) -> f64 {
+
+    if name == "level" {
+        return sheet.level.unwrap_or(1) as f64;
+    }
+
+    if let Some(attr) = sheet.attributes.iter().find(|a| a.name == name) {
+        return attr.value as f64;
+    }
+
+    let skill = sheet.attributes
+        .iter()
+        .filter_map(|a| a.skills.as_ref())
+        .flatten()
+        .find(|s| s.name == name);
+
+    if let Some(skill) = skill {
+        return skill.value as f64;
+    }
+
+    truthy(sheet.perks.as_ref().is_some_and(|p| p.iter().any(|p| p == name)))
+
+}
+
+fn evaluate(expr: &Expr, sheet: &CharacterSheet) -> f64 {
+
+    match expr {
+        Expr::Number(n)       => *n,
+        Expr::Text(_)         => 0.0,
+        Expr::Variable(name)  => lookup(sheet, name),
+        Expr::Negate(inner)   => -evaluate(inner, sheet),
+        Expr::Not(inner)      => truthy(evaluate(inner, sheet) == 0.0),
+        Expr::Binary(op, lhs, rhs) => {
+
+            let lhs = evaluate(lhs, sheet);
+            let rhs = evaluate(rhs, sheet);
+
+            match op {
+                BinaryOp::Add => lhs + rhs,
+                BinaryOp::Sub => lhs - rhs,
+                BinaryOp::Mul => lhs * rhs,
+                BinaryOp::Div => if rhs == 0.0 { 0.0 } else { lhs / rhs },
+                BinaryOp::Rem => if rhs == 0.0 { 0.0 } else { lhs % rhs },
+                BinaryOp::Eq  => truthy(lhs == rhs),
+                BinaryOp::Ne  => truthy(lhs != rhs),
+                BinaryOp::Lt  => truthy(lhs < rhs),
+                BinaryOp::Le  => truthy(lhs <= rhs),
+                BinaryOp::Gt  => truthy(lhs > rhs),
+                BinaryOp::Ge  => truthy(lhs >= rhs),
+                BinaryOp::And => truthy(lhs != 0.0 && rhs != 0.0),
+                BinaryOp::Or  => truthy(lhs != 0.0 || rhs != 0.0)
+            }
+
+        },
+        Expr::Call(function, args) => {
+
+            let mut values = args.iter().map(|a| evaluate(a, sheet));
+
+            match function {
+                Function::Floor => values.next().unwrap().floor(),
+                Function::Ceil  => values.next().unwrap().ceil(),
+                Function::Round => values.next().unwrap().round(),
+                Function::Abs   => values.next().unwrap().abs(),
+                Function::Min   => values.fold(f64::INFINITY, f64::min),
+                Function::Max   => values.fold(f64::NEG_INFINITY, f64::max),
+                Function::If    => {
+
+                    if evaluate(&args[0], sheet) != 0.0 {
+                        evaluate(&args[1], sheet)
+                    } else {
+                        evaluate(&args[2], sheet)
+                    }
+
+                },
+                Function::Perk  => {
+
+                    let (Expr::Text(name) | Expr::Variable(name)) = &args[0] else {
+                        return 0.0;
+                    };
+
+                    truthy(sheet.perks.as_ref().is_some_and(|p| p.contains(name)))
+
+                }
+            }
+
+        }
+    }
+
+}
+
+fn collect_identifiers<'a>(expr: &'a Expr, identifiers: &mut Vec<&'a str>) {
+
+    match expr {
+        Expr::Number(_) => {},
+        Expr::Text(text) | Expr::Variable(text) => identifiers.push(text),
+        Expr::Negate(inner) | Expr::Not(inner) => collect_identifiers(inner, identifiers),
+        Expr::Binary(_, lhs, rhs) => {
+            collect_identifiers(lhs, identifiers);
+            collect_identifiers(rhs, identifiers);
+        },
+        Expr::Call(_, args) => args.iter().for_each(|a| collect_identifiers(a, identifiers))
+    }
+
+}
+
+#[cfg(test)]
+mod formula_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+    use crate::character_template::CharacterTemplate;
+    use crate::effective_stats::EffectiveStats;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    fn eval(source: &str) -> i64 {
+        Formula::parse(source).unwrap().evaluate(&CHARACTER_SHEET_SAMPLE)
+    }
+
+    #[test]
+    fn arithmetic() {
+
+        assert_eq!(eval("1 + 2 * 3"), 7);
+        assert_eq!(eval("(1 + 2) * 3"), 9);
+        assert_eq!(eval("-4 + 10 % 4"), -2);
+        assert_eq!(eval("7 / 2"), 3);
+        assert_eq!(eval("ceil(7 / 2)"), 4);
+        assert_eq!(eval("floor(-1 / 2)"), -1);
+        assert_eq!(eval("min(3, 1, 2) + max(4, 5)"), 6);
+        assert_eq!(eval("10 / 0"), 0);
+
+    }
+
+    #[test]
+    fn sheet_references() {
+
+        // Prowess 1, Agility 4, Persuasion 3
+        assert_eq!(eval("10 + Prowess * 2"), 12);
+        assert_eq!(eval("7 + floor(Agility / 2)"), 9);
+        assert_eq!(eval("[Sleight of Hand] + Persuasion"), 3);
+        assert_eq!(eval("level"), 1);
+
+        assert_eq!(eval("perk(\"Small Frame\")"), 1);
+        assert_eq!(eval("[Force Sensitive]"), 0);
+        assert_eq!(eval("if(perk(\"Charismatic\") && Charisma >= 5, 2, 0)"), 2);
+        assert_eq!(eval("Nonexistent"), 0);
+
+    }
+
+    #[test]
+    fn identifiers() {
+
+        let formula = Formula::parse("Agility + perk(\"Small Frame\") + floor([Sleight of Hand] / 2)").unwrap();
+        assert_eq!(formula.identifiers(), vec!["Agility", "Small Frame", "Sleight of Hand"]);
+
+    }
+
+    #[test]
+    fn invalid_formulas() {
+
+        for source in ["", "1 +", "(1", "sqrt(4)", "min()", "1 $ 2", "[Agility", "perk(1)"] {
+            assert!(Formula::parse(source).is_err(), "{} should not parse", source);
+        }
+
+    }
+
+    #[test]
+    fn nesting_limit() {
+
+        let nested = |depth: usize| format!("{}1{}", "(".repeat(depth), ")".repeat(depth));
+
+        assert!(Formula::parse(&nested(MAX_NESTING - 1)).is_ok());
+        assert_eq!(Formula::parse(&nested(MAX_NESTING)), Err(FormulaError::Invalid {
+            formula: nested(MAX_NESTING),
+            message: format!("nested more than {} deep", MAX_NESTING)
+        }));
+
+        // Far past the limit, this would overflow the stack without it
+        assert!(Formula::parse(&nested(100_000)).is_err());
+        assert!(Formula::parse(&format!("{}1", "-".repeat(100_000))).is_err());
+
+    }
+
+    #[test]
+    fn length_limit() {
+
+        let chain = |operators: usize| format!("1{}", "+1".repeat(operators));
+
+        // 1 and then a + and a 1 per operator
+        let longest = chain((MAX_TOKENS - 1) / 2);
+        assert_eq!(eval(&longest), 1 + (MAX_TOKENS as i64 - 1) / 2);
+
+        assert_eq!(Formula::parse(&chain(MAX_TOKENS / 2)), Err(FormulaError::Invalid {
+            formula: chain(MAX_TOKENS / 2),
+            message: format!("longer than {} tokens", MAX_TOKENS)
+        }));
+
+        // A flat chain doesn't nest, but evaluating or dropping it would still overflow the stack
+        assert!(Formula::parse(&chain(20_000)).is_err());
+
+    }
+
+    #[test]
+    fn serde_round_trip() {
+
+        let formula: Formula = serde_json::from_str("\"7 + floor(Agility / 2)\"").unwrap();
+        assert_eq!(serde_json::to_string(&formula).unwrap(), "\"7 + floor(Agility / 2)\"");
+
+        assert!(serde_json::from_str::<Formula>("\"7 +\"").is_err());
+
+    }
+
+    #[test]
+    fn template_formulas() {
+
+        assert!(STANDARD_TEMPLATE.check_formulas().is_ok());
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.health_formula = Some(Formula::parse("10 + Prowess * 2").unwrap());
+        template.armor_class_formula = Some(Formula::parse("7 + floor(Agility / 2)").unwrap());
+
+        let stats = EffectiveStats::new(&template, &CHARACTER_SHEET_SAMPLE);
+        assert_eq!(stats.health, 12);
+        assert_eq!(stats.armor_class, 9);
+
+        template.health_formula = Some(Formula::parse("10 + Charm").unwrap());
+        assert_eq!(template.check_formulas(), Err(FormulaError::UnknownIdentifier {
+            formula: "10 + Charm".to_string(),
+            identifier: "Charm".to_string()
+        }));
+
+        // Situational modifiers are checked too
+        let mut template = STANDARD_TEMPLATE.clone();
+        let situational = template.perks.as_mut().unwrap().iter_mut().find_map(|p| p.situational.as_mut()).unwrap();
+        situational[0].skills.get_or_insert_with(Vec::new)[0].formula = Some(Formula::parse("Charm").unwrap());
+        assert_eq!(template.check_formulas(), Err(FormulaError::UnknownIdentifier {
+            formula: "Charm".to_string(),
+            identifier: "Charm".to_string()
+        }));
+
+        assert_eq!(template.evaluate_formula(&CHARACTER_SHEET_SAMPLE, "Agility * 2".to_string()), Ok(8));
+        assert!(template.evaluate_formula(&CHARACTER_SHEET_SAMPLE, "Charm * 2".to_string()).is_err());
+
+    }
+
+    #[test]
+    fn formula_modifiers_and_requirements() {
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        sheet.perks = Some(vec!["Veteran".to_string()]);
+        sheet.level = Some(7);
+
+        // 1 + floor(7 / 3)
+        let stats = EffectiveStats::new(&STANDARD_TEMPLATE, &sheet);
+        assert_eq!(stats.skill("Survival").unwrap().value, 3);
+
+        let generator = STANDARD_TEMPLATE.find_equipment("Stealth Field Generator").unwrap();
+        let required = generator.required.as_ref().unwrap();
+        assert!(required.is_met_by(&sheet));
+
+        sheet.attributes.iter_mut().find(|a| a.name == "Agility").unwrap().value = 2;
+        assert!(!required.is_met_by(&sheet));
+
+    }
+
+}
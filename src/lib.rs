@@ -2,12 +2,30 @@ use character_sheet::config::{CHARACTER_SHEET_CONFIG, CharacterSheetConfig};
 #[cfg(target_arch = "wasm32")]
 use wasm_bindgen::prelude::*;
 
+pub mod budget;
 pub mod character_template;
 pub mod character_sheet;
 pub mod character_roll;
+pub mod character_sheet_validator;
+pub mod character_state;
+pub mod dice;
+pub mod effective_stats;
+pub mod encounter;
+pub mod formula;
+pub mod schema;
+pub mod sheet_diff;
+pub mod sheet_history;
 
 #[cfg(not(target_arch = "wasm32"))]
-pub mod character_sheet_validator;
+pub mod character_generator;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod template_registry;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod batch;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod sheet_repairer;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod point_optimizer;
 
 #[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
 pub fn get_sheet_config() -> CharacterSheetConfig {
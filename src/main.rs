@@ -0,0 +1,635 @@
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use clap::{Parser, Subcommand, ValueEnum};
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+use serde::de::{self, MapAccess, SeqAccess, Visitor};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+
+use character_sheet::character_generator::CharacterGenerator;
+use character_sheet::character_roll::{CharacterRoll, RollTarget};
+use character_sheet::character_sheet::CharacterSheet;
+use character_sheet::character_sheet_validator::ValidationMode;
+use character_sheet::character_template::lint::LintLevel;
+use character_sheet::character_template::CharacterTemplate;
+use character_sheet::point_optimizer::PointOptimizer;
+use character_sheet::sheet_repairer::RepairChange;
+use character_sheet::template_registry::{RegistryError, TemplateRegistry};
+
+/// Validate, lint, roll and convert character sheets and templates
+#[derive(Parser)]
+#[command(name = "character-sheet", version)]
+struct Cli {
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Validate sheets against a template, exits with 1 if any sheet is invalid
+    Validate {
+        template: PathBuf,
+        #[arg(required = true)]
+        sheets: Vec<PathBuf>,
+        /// Templates the template extends
+        #[arg(short, long = "include")]
+        include: Vec<PathBuf>,
+        /// Check the template against the JSON Schema first
+        #[arg(long)]
+        prevalidate: bool,
+        /// Accept sheets that leave out attributes and skills
+        #[arg(long)]
+        lenient: bool,
+        #[arg(short, long, value_enum, default_value_t = Format::Human)]
+        format: Format
+    },
+    /// Validate every sheet in a directory in parallel and summarize the failures, exits with 1 if any are invalid
+    Batch {
+        dir: PathBuf,
+        /// Templates, or directories of templates, the sheets were made with
+        #[arg(short, long = "template", required = true)]
+        templates: Vec<PathBuf>,
+        /// Accept sheets that leave out attributes and skills
+        #[arg(long)]
+        lenient: bool,
+        #[arg(short, long, value_enum, default_value_t = Format::Human)]
+        format: Format
+    },
+    /// Report problems in templates, exits with 1 if any are errors
+    LintTemplate {
+        #[arg(required = true)]
+        templates: Vec<PathBuf>,
+        /// Also exit with 1 on warnings
+        #[arg(long)]
+        deny_warnings: bool,
+        #[arg(short, long, value_enum, default_value_t = Format::Human)]
+        format: Format
+    },
+    /// Roll an attribute, skill or weapon attack for a sheet
+    Roll {
+        sheet: PathBuf,
+        /// The attribute, skill or weapon to roll
+        target: String,
+        /// Templates to find the sheet's template in
+        #[arg(short, long = "template", required = true)]
+        templates: Vec<PathBuf>,
+        /// Roll an attack with the target weapon
+        #[arg(long)]
+        attack: bool,
+        /// Situations in play, e.g. Darkness
+        #[arg(short, long = "situation")]
+        situations: Vec<String>,
+        #[arg(long)]
+        seed: Option<u64>,
+        #[arg(short, long, value_enum, default_value_t = Format::Human)]
+        format: Format
+    },
+    /// Show how many points a sheet has spent of each allotment
+    Budget {
+        sheet: PathBuf,
+        /// Templates to find the sheet's template in
+        #[arg(short, long = "template", required = true)]
+        templates: Vec<PathBuf>,
+        #[arg(short, long, value_enum, default_value_t = Format::Human)]
+        format: Format
+    },
+    /// Generate a random valid sheet, e.g. for an NPC, and print it as JSON
+    Generate {
+        template: PathBuf,
+        /// Templates the template extends
+        #[arg(short, long = "include")]
+        include: Vec<PathBuf>,
+        /// Attributes to favor, e.g. Agility
+        #[arg(long)]
+        focus: Vec<String>,
+        #[arg(long)]
+        seed: Option<u64>
+    },
+    /// Start a sheet from one of the template's archetypes, e.g. Smuggler, and print it as JSON
+    New {
+        template: PathBuf,
+        #[arg(short, long)]
+        archetype: String,
+        /// Templates the template extends
+        #[arg(short, long = "include")]
+        include: Vec<PathBuf>,
+        /// The character's name
+        #[arg(long)]
+        name: Option<String>
+    },
+    /// Spend a sheet's remaining points for the best modifiers on the targets and print it as JSON
+    Optimize {
+        sheet: PathBuf,
+        /// The attributes and skills to maximize
+        #[arg(required = true)]
+        targets: Vec<String>,
+        /// Templates to find the sheet's template in
+        #[arg(short, long = "template", required = true)]
+        templates: Vec<PathBuf>,
+        /// Print every sheet that trades one target for another, with its modifiers
+        #[arg(long)]
+        pareto: bool
+    },
+    /// Convert a JSON5 file to JSON
+    Convert {
+        input: PathBuf,
+        /// Defaults to the input with a .json extension
+        #[arg(short, long)]
+        output: Option<PathBuf>
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Format {
+    Human,
+    Json
+}
+
+#[derive(Serialize)]
+struct SheetReport {
+    sheet: String,
+    valid: bool,
+    code: Option<&'static str>,
+    message: Option<String>
+}
+
+fn main() -> ExitCode {
+
+    let cli = Cli::parse();
+
+    let result = match cli.command {
+        Command::Validate { template, sheets, include, prevalidate, lenient, format } => validate(&template, &sheets, &include, prevalidate, lenient, format),
+        Command::Batch { dir, templates, lenient, format } => batch(&dir, &templates, lenient, format),
+        Command::LintTemplate { templates, deny_warnings, format } => lint_template(&templates, deny_warnings, format),
+        Command::Roll { sheet, target, templates, attack, situations, seed, format } => roll(&sheet, target, &templates, attack, &situations, seed, format),
+        Command::Budget { sheet, templates, format } => budget(&sheet, &templates, format),
+        Command::Generate { template, include, focus, seed } => generate(&template, &include, &focus, seed),
+        Command::New { template, archetype, include, name } => new_sheet(&template, &include, &archetype, name),
+        Command::Optimize { sheet, targets, templates, pareto } => optimize(&sheet, targets, &templates, pareto),
+        Command::Convert { input, output } => convert(&input, output)
+    };
+
+    match result {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::from(2)
+        }
+    }
+
+}
+
+fn validate(template: &Path, sheets: &[PathBuf], include: &[PathBuf], prevalidate: bool, lenient: bool, format: Format) -> Result<ExitCode, String> {
+
+    let registry = TemplateRegistry::new().with_validation_mode(validation_mode(lenient));
+    let mut registry = if prevalidate { registry.with_prevalidation() } else { registry };
+    for path in include.iter().chain([template.to_path_buf()].iter()) {
+        add_template(&mut registry, path)?;
+    }
+
+    let reports: Vec<SheetReport> = sheets
+        .iter()
+        .map(|path| {
+
+            let (code, message) = match read_sheet(path) {
+                Err(e)    => (Some("unreadable_sheet"), Some(e)),
+                Ok(sheet) => match registry.validate(&sheet) {
+                    Ok(())  => (None, None),
+                    Err(e)  => (Some(e.code()), Some(e.to_string()))
+                }
+            };
+
+            SheetReport {
+                sheet: path.display().to_string(),
+                valid: code.is_none(),
+                code,
+                message
+            }
+
+        })
+        .collect();
+
+    match format {
+        Format::Json  => println!("{}", serde_json::to_string_pretty(&reports).unwrap()),
+        Format::Human => for report in &reports {
+            println!("{}: {}", report.sheet, report.message.as_deref().unwrap_or("ok"));
+        }
+    }
+
+    Ok(exit_code(reports.iter().all(|r| r.valid)))
+
+}
+
+fn batch(dir: &Path, templates: &[PathBuf], lenient: bool, format: Format) -> Result<ExitCode, String> {
+
+    let registry = load_templates(templates)?.with_validation_mode(validation_mode(lenient));
+    let report = registry.validate_dir(dir).map_err(|e| e.to_string())?;
+
+    match format {
+        Format::Json  => println!("{}", serde_json::to_string_pretty(&report).unwrap()),
+        Format::Human => print!("{}", report.render_table())
+    }
+
+    Ok(exit_code(report.is_success()))
+
+}
+
+fn lint_template(templates: &[PathBuf], deny_warnings: bool, format: Format) -> Result<ExitCode, String> {
+
+    let mut reports = vec![];
+    let mut failed = false;
+
+    for path in templates {
+
+        let mut registry = TemplateRegistry::new().with_prevalidation();
+        let contents = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+
+        // Templates that can't be loaded at all are reported as a single error, the
+        // formula and id errors that make a template invalid are included in its lints
+        let template: CharacterTemplate = match registry.add_str(&path.display().to_string(), &contents) {
+            Ok(()) | Err(RegistryError::InvalidTemplate { .. }) => json5::from_str(&contents).map_err(|e| e.to_string())?,
+            Err(e) => {
+
+                failed = true;
+                reports.push(json!({ "template": path.display().to_string(), "level": LintLevel::Error, "location": "", "message": e.to_string() }));
+                continue;
+
+            }
+        };
+
+        let lints = template.lint();
+
+        for lint in lints {
+
+            failed |= lint.level == LintLevel::Error || deny_warnings;
+            reports.push(json!({ "template": path.display().to_string(), "level": lint.level, "location": lint.location, "message": lint.message }));
+
+        }
+
+    }
+
+    match format {
+        Format::Json  => println!("{}", serde_json::to_string_pretty(&reports).unwrap()),
+        Format::Human => {
+
+            for report in &reports {
+                println!("{}: {} {}: {}", report["template"].as_str().unwrap(), report["level"].as_str().unwrap().to_lowercase(), report["location"].as_str().unwrap(), report["message"].as_str().unwrap());
+            }
+
+            if reports.is_empty() {
+                println!("no problems found");
+            }
+
+        }
+    }
+
+    Ok(exit_code(!failed))
+
+}
+
+fn roll(sheet: &Path, target: String, templates: &[PathBuf], attack: bool, situations: &[String], seed: Option<u64>, format: Format) -> Result<ExitCode, String> {
+
+    let registry = load_templates(templates)?;
+    let sheet = read_sheet(sheet)?;
+
+    let template = registry
+        .template_for(&sheet.template)
+        .and_then(|t| Ok(t.for_sheet(&sheet)?))
+        .map_err(|e| e.to_string())?;
+
+    let target = if attack {
+        RollTarget::Attack(target)
+    } else {
+        roll_target(&template, target)
+    };
+
+    let result = CharacterRoll::new(&template, &sheet, target)
+        .with_situations(situations)
+        .roll_with_rng(&mut seeded_rng(seed))
+        .map_err(|e| e.to_string())?;
+
+    match format {
+        Format::Json  => println!("{}", serde_json::to_string_pretty(&result).unwrap()),
+        Format::Human => println!("{}", result.render())
+    }
+
+    Ok(ExitCode::SUCCESS)
+
+}
+
+fn budget(sheet: &Path, templates: &[PathBuf], format: Format) -> Result<ExitCode, String> {
+
+    let registry = load_templates(templates)?;
+    let sheet = read_sheet(sheet)?;
+
+    let template = registry
+        .template_for(&sheet.template)
+        .and_then(|t| Ok(t.for_sheet(&sheet)?))
+        .map_err(|e| e.to_string())?;
+
+    let budget = template.get_budget(&sheet);
+
+    match format {
+        Format::Json  => println!("{}", serde_json::to_string_pretty(&budget).unwrap()),
+        Format::Human => for (category, points) in budget.categories() {
+            println!("{:<22} {:>3} / {:<3} ({} remaining)", category, points.spent, points.given, points.remaining());
+        }
+    }
+
+    Ok(exit_code(budget.categories().iter().all(|(_, points)| points.remaining() >= 0)))
+
+}
+
+fn generate(template: &Path, include: &[PathBuf], focus: &[String], seed: Option<u64>) -> Result<ExitCode, String> {
+
+    let template = load_resolved(template, include)?;
+
+    let generator = focus
+        .iter()
+        .fold(CharacterGenerator::new(&template), |generator, attribute| generator.with_focus(attribute));
+
+    let sheet = generator.generate(&mut seeded_rng(seed)).map_err(|e| e.to_string())?;
+    println!("{}", String::from_utf8(pretty_json(&sheet)).unwrap());
+
+    Ok(ExitCode::SUCCESS)
+
+}
+
+fn optimize(sheet: &Path, targets: Vec<String>, templates: &[PathBuf], pareto: bool) -> Result<ExitCode, String> {
+
+    let registry = load_templates(templates)?;
+    let sheet = read_sheet(sheet)?;
+
+    let template = registry
+        .template_for(&sheet.template)
+        .and_then(|t| Ok(t.for_sheet(&sheet)?))
+        .map_err(|e| e.to_string())?;
+
+    let targets = targets.into_iter().map(|target| roll_target(&template, target)).collect();
+    let optimizer = PointOptimizer::new(&template, targets);
+
+    // Repairs are the same for every sheet, they describe the sheet that was given
+    let report_repairs = |repairs: &[RepairChange]| for repair in repairs {
+        eprintln!("repaired {}: {}", repair.location, repair.message);
+    };
+
+    if !pareto {
+        let best = optimizer.optimize(&sheet).map_err(|e| e.to_string())?;
+        report_repairs(&best.repairs);
+        println!("{}", String::from_utf8(pretty_json(&best.sheet)).unwrap());
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let front = optimizer.pareto(&sheet).map_err(|e| e.to_string())?;
+    report_repairs(&front[0].repairs);
+
+    let front: Vec<_> = front
+        .into_iter()
+        .map(|candidate| json!({ "modifiers": candidate.modifiers, "sheet": candidate.sheet }))
+        .collect();
+
+    println!("{}", String::from_utf8(pretty_json(&front)).unwrap());
+
+    Ok(ExitCode::SUCCESS)
+
+}
+
+fn new_sheet(template: &Path, include: &[PathBuf], archetype: &str, name: Option<String>) -> Result<ExitCode, String> {
+
+    let template = load_resolved(template, include)?;
+
+    let mut sheet = template.sheet_from_archetype(archetype).map_err(|e| e.to_string())?;
+    sheet.name = name.unwrap_or_default();
+
+    println!("{}", String::from_utf8(pretty_json(&sheet)).unwrap());
+
+    Ok(ExitCode::SUCCESS)
+
+}
+
+fn convert(input: &Path, output: Option<PathBuf>) -> Result<ExitCode, String> {
+
+    let output = output.unwrap_or_else(|| input.with_extension("json"));
+    if output == input {
+        return Err(format!("{} would overwrite the input, pass an --output", output.display()));
+    }
+
+    let contents = fs::read_to_string(input).map_err(|e| format!("failed to read {}: {}", input.display(), e))?;
+    let value: OrderedValue = json5::from_str(&contents).map_err(|e| format!("failed to parse {}: {}", input.display(), e))?;
+
+    fs::write(&output, pretty_json(&value)).map_err(|e| format!("failed to write {}: {}", output.display(), e))?;
+    println!("wrote {}", output.display());
+
+    Ok(ExitCode::SUCCESS)
+
+}
+
+/// JSON indented with four spaces, like the sample sheet
+fn pretty_json(value: &impl Serialize) -> Vec<u8> {
+
+    let mut json = Vec::new();
+    let mut serializer = serde_json::Serializer::with_formatter(&mut json, serde_json::ser::PrettyFormatter::with_indent(b"    "));
+    value.serialize(&mut serializer).unwrap();
+
+    json
+
+}
+
+fn seeded_rng(seed: Option<u64>) -> StdRng {
+
+    match seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None       => StdRng::from_entropy()
+    }
+
+}
+
+/// An attribute when the template has one by that name, otherwise a skill
+fn roll_target(template: &CharacterTemplate, target: String) -> RollTarget {
+
+    if template.attributes.iter().any(|a| a.name == target) {
+        RollTarget::Attribute(target)
+    } else {
+        RollTarget::Skill(target)
+    }
+
+}
+
+/// Loads a template and the ones it extends, and resolves it
+fn load_resolved(template: &Path, include: &[PathBuf]) -> Result<CharacterTemplate, String> {
+
+    let mut registry = TemplateRegistry::new();
+    for path in include.iter().chain([template.to_path_buf()].iter()) {
+        add_template(&mut registry, path)?;
+    }
+
+    // The registry resolves the template against the ones it extends
+    let contents = fs::read_to_string(template).map_err(|e| format!("failed to read {}: {}", template.display(), e))?;
+    let parsed: CharacterTemplate = json5::from_str(&contents).map_err(|e| format!("failed to parse {}: {}", template.display(), e))?;
+
+    registry
+        .template_for(&parsed.get_base_character_sheet().template)
+        .map_err(|e| e.to_string())
+
+}
+
+fn read_sheet(path: &Path) -> Result<CharacterSheet, String> {
+
+    let contents = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    json5::from_str(&contents).map_err(|e| format!("failed to parse {}: {}", path.display(), e))
+
+}
+
+fn add_template(registry: &mut TemplateRegistry, path: &Path) -> Result<(), String> {
+
+    if path.is_dir() {
+        return registry.load_dir(path).map(|_| ()).map_err(|e| e.to_string());
+    }
+
+    let contents = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path.display(), e))?;
+    registry.add_str(&path.display().to_string(), &contents).map_err(|e| e.to_string())
+
+}
+
+fn validation_mode(lenient: bool) -> ValidationMode {
+    if lenient { ValidationMode::Lenient } else { ValidationMode::Strict }
+}
+
+fn load_templates(paths: &[PathBuf]) -> Result<TemplateRegistry, String> {
+
+    let mut registry = TemplateRegistry::new();
+    for path in paths {
+        add_template(&mut registry, path)?;
+    }
+
+    Ok(registry)
+
+}
+
+fn exit_code(success: bool) -> ExitCode {
+    if success { ExitCode::SUCCESS } else { ExitCode::FAILURE }
+}
+
+/**
+ *
+ * A JSON value that keeps object keys in the order they were written, so `convert` doesn't
+ * reorder a template's fields. `serde_json::Value` sorts them.
+ *
+*/
+enum OrderedValue {
+    Null,
+    Bool(bool),
+    Number(serde_json::Number),
+    String(String),
+    Array(Vec<OrderedValue>),
+    Object(Vec<(String, OrderedValue)>)
+}
+
+impl Serialize for OrderedValue {
+
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+
+        use serde::ser::SerializeMap;
+
+        match self {
+            OrderedValue::Null          => serializer.serialize_unit(),
+            OrderedValue::Bool(b)       => serializer.serialize_bool(*b),
+            OrderedValue::Number(n)     => n.serialize(serializer),
+            OrderedValue::String(s)     => serializer.serialize_str(s),
+            OrderedValue::Array(values) => values.serialize(serializer),
+            OrderedValue::Object(entries) => {
+
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (key, value) in entries {
+                    map.serialize_entry(key, value)?;
+                }
+                map.end()
+
+            }
+        }
+
+    }
+
+}
+
+impl<'de> Deserialize<'de> for OrderedValue {
+
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_any(OrderedValueVisitor)
+    }
+
+}
+
+struct OrderedValueVisitor;
+
+impl<'de> Visitor<'de> for OrderedValueVisitor {
+
+    type Value = OrderedValue;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("a JSON value")
+    }
+
+    fn visit_unit<E>(self) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Null)
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Bool(b))
+    }
+
+    fn visit_i64<E>(self, n: i64) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Number(n.into()))
+    }
+
+    fn visit_u64<E>(self, n: u64) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::Number(n.into()))
+    }
+
+    fn visit_f64<E: de::Error>(self, n: f64) -> Result<OrderedValue, E> {
+
+        serde_json::Number::from_f64(n)
+            .map(OrderedValue::Number)
+            .ok_or_else(|| E::custom(format!("{} can't be written as JSON", n)))
+
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::String(s.to_string()))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<OrderedValue, E> {
+        Ok(OrderedValue::String(s))
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<OrderedValue, A::Error> {
+
+        let mut values = vec![];
+        while let Some(value) = seq.next_element()? {
+            values.push(value);
+        }
+
+        Ok(OrderedValue::Array(values))
+
+    }
+
+    fn visit_map<A: MapAccess<'de>>(self, mut map: A) -> Result<OrderedValue, A::Error> {
+
+        let mut entries = vec![];
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+
+        Ok(OrderedValue::Object(entries))
+
+    }
+
+}
@@ -0,0 +1,566 @@
+use thiserror::Error;
+
+use crate::budget::SheetBudget;
+use crate::character_roll::{CharacterRoll, RollError, RollTarget};
+use crate::character_sheet::CharacterSheet;
+use crate::character_sheet_validator::{CharacterSheetError, CharacterSheetValidator};
+use crate::character_template::CharacterTemplate;
+use crate::sheet_repairer::{RepairChange, SheetRepairer};
+
+/// Perks tried in every combination, beyond this only the first ones that help are considered
+const MAX_PERK_CANDIDATES: usize = 12;
+
+/// The most sheets one search validates, each combination of perks and allocations is one
+pub const MAX_CANDIDATES: usize = 20_000;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum OptimizerError {
+    #[error("Nothing to optimize, no targets were given")]
+    NoTargets,
+    #[error(transparent)]
+    Roll(#[from] RollError),
+    #[error("No allocation of the remaining points is valid: {0}")]
+    NoValidSheet(CharacterSheetError),
+    #[error("More than {0} sheets would have to be tried, optimize for fewer targets or spend some of the points first")]
+    TooManyCandidates(usize)
+}
+
+pub struct OptimizedSheet {
+    pub sheet: CharacterSheet,
+    /// The roll modifier for each target, in the order they were given
+    pub modifiers: Vec<i64>,
+    /// What was repaired in the given sheet before spending its points, e.g. missing skills added
+    pub repairs: Vec<RepairChange>
+}
+
+/**
+ *
+ * Finds how to spend a sheet's remaining points to get the best roll modifiers for one or more
+ * targets, e.g. the best Persuasion. Points and perks already on the sheet are kept, the
+ * optimizer only adds to them. Attribute and skill points are only put where they raise a target
+ * by themselves, so points that only matter past a formula's threshold are not found.
+ *
+ * Every combination of helpful perks and allocations is validated, so a search that would try
+ * more than `MAX_CANDIDATES` sheets fails with `TooManyCandidates` before trying any. That
+ * happens with many targets sharing a lot of unspent points.
+ *
+*/
+pub struct PointOptimizer<'a> {
+    template: &'a CharacterTemplate,
+    targets: Vec<RollTarget>
+}
+
+impl<'a> PointOptimizer<'a> {
+
+    pub fn new(template: &'a CharacterTemplate, targets: Vec<RollTarget>) -> Self {
+
+        Self {
+            template,
+            targets
+        }
+
+    }
+
+    /// The sheet with the highest total modifier across the targets, with the fewest perks and points for it
+    pub fn optimize(&self, sheet: &CharacterSheet) -> Result<OptimizedSheet, OptimizerError> {
+
+        let candidates = self.pareto(sheet)?;
+
+        let best = candidates
+            .into_iter()
+            .rev()
+            .max_by_key(|c| c.modifiers.iter().sum::<i64>())
+            .unwrap();
+
+        Ok(best)
+
+    }
+
+    /**
+     *
+     * Every sheet where no target can be raised without lowering another, highest modifiers first.
+     * With a single target this is the best sheet, and with several it lists the trade-offs.
+     *
+    */
+    pub fn pareto(&self, sheet: &CharacterSheet) -> Result<Vec<OptimizedSheet>, OptimizerError> {
+
+        if self.targets.is_empty() {
+            return Err(OptimizerError::NoTargets);
+        }
+
+        // Fills in missing attributes and skills and raises values to their minimums
+        let repaired = SheetRepairer::new(self.template).repair(sheet);
+        let base = repaired.sheet;
+        let base_modifiers = self.modifiers(&base)?;
+
+        let perks = self.helpful_perks(&base, &base_modifiers)?;
+        let attributes = self.attribute_group(&base, &base_modifiers)?;
+        let skills = self.skill_group(&base, &base_modifiers)?;
+
+        let attribute_allocations = attributes.allocations(MAX_CANDIDATES).ok_or(OptimizerError::TooManyCandidates(MAX_CANDIDATES))?;
+        let skill_allocations = skills.allocations(MAX_CANDIDATES).ok_or(OptimizerError::TooManyCandidates(MAX_CANDIDATES))?;
+
+        let total = (1usize << perks.len())
+            .saturating_mul(attribute_allocations.len())
+            .saturating_mul(skill_allocations.len());
+
+        if total > MAX_CANDIDATES {
+            return Err(OptimizerError::TooManyCandidates(MAX_CANDIDATES));
+        }
+
+        let mut candidates: Vec<OptimizedSheet> = vec![];
+        let mut last_error = None;
+
+        for mask in 0..(1u32 << perks.len()) {
+
+            let mut with_perks = base.clone();
+            let selected = perks.iter().enumerate().filter(|(i, _)| mask & (1 << i) != 0).map(|(_, p)| p.clone());
+            with_perks.perks.get_or_insert_with(Vec::new).extend(selected);
+
+            for attribute_points in &attribute_allocations {
+
+                for skill_points in &skill_allocations {
+
+                    let mut candidate = with_perks.clone();
+                    attributes.apply(&mut candidate, attribute_points);
+                    skills.apply(&mut candidate, skill_points);
+
+                    if let Err(e) = CharacterSheetValidator::new(self.template, &candidate).check() {
+                        last_error = Some(e);
+                        continue;
+                    }
+
+                    let modifiers = self.modifiers(&candidate)?;
+                    candidates.push(OptimizedSheet { sheet: candidate, modifiers, repairs: repaired.changes.clone() });
+
+                }
+
+            }
+
+        }
+
+        if candidates.is_empty() {
+            return Err(OptimizerError::NoValidSheet(last_error.unwrap()));
+        }
+
+        Ok(self.pareto_front(candidates))
+
+    }
+
+    fn modifiers(&self, sheet: &CharacterSheet) -> Result<Vec<i64>, RollError> {
+
+        self.targets
+            .iter()
+            .map(|target| CharacterRoll::new(self.template, sheet, target.clone()).modifier())
+            .collect()
+
+    }
+
+    fn improves(&self, sheet: &CharacterSheet, base_modifiers: &[i64]) -> Result<bool, RollError> {
+
+        let modifiers = self.modifiers(sheet)?;
+        Ok(modifiers.iter().zip(base_modifiers).any(|(m, base)| m > base))
+
+    }
+
+    /// Perks that raise a target on their own, and perks that give perk points back
+    fn helpful_perks(&self, base: &CharacterSheet, base_modifiers: &[i64]) -> Result<Vec<String>, RollError> {
+
+        if self.template.allotments.perks.is_none() {
+            return Ok(vec![]);
+        }
+
+        let selected = base.perks.as_deref().unwrap_or_default();
+        let mut helpful = vec![];
+
+        for perk in self.template.perks.iter().flatten().filter(|p| !selected.contains(&p.name)) {
+
+            let mut sheet = base.clone();
+            sheet.perks.get_or_insert_with(Vec::new).push(perk.name.clone());
+
+            if perk.point_cost < 0 || self.improves(&sheet, base_modifiers)? {
+                helpful.push(perk.name.clone());
+            }
+
+        }
+
+        helpful.truncate(MAX_PERK_CANDIDATES);
+        Ok(helpful)
+
+    }
+
+    fn attribute_group(&self, base: &CharacterSheet, base_modifiers: &[i64]) -> Result<PointGroup, RollError> {
+
+        let points = &self.template.allotments.attributes;
+        let max = points.max_points_per_allotment.unwrap_or(i64::MAX);
+
+        let slots = (0..base.attributes.len())
+            .map(|a| Slot { attribute: a, skill: None, room: max.saturating_sub(base.attributes[a].value) })
+            .collect();
+
+        PointGroup::new(self, base, base_modifiers, slots, points.given_points, points.must_spend_all == Some(true))
+
+    }
+
+    fn skill_group(&self, base: &CharacterSheet, base_modifiers: &[i64]) -> Result<PointGroup, RollError> {
+
+        let Some(points) = &self.template.allotments.skills else {
+            return PointGroup::new(self, base, base_modifiers, vec![], 0, false);
+        };
+
+        let max = points.max_points_per_allotment.unwrap_or(i64::MAX);
+
+        let slots = base.attributes
+            .iter()
+            .enumerate()
+            .flat_map(|(a, attribute)| {
+
+                attribute.skills
+                    .iter()
+                    .flatten()
+                    .enumerate()
+                    .map(move |(s, skill)| Slot { attribute: a, skill: Some(s), room: max.saturating_sub(skill.value) })
+
+            })
+            .collect();
+
+        PointGroup::new(self, base, base_modifiers, slots, points.given_points, points.must_spend_all == Some(true))
+
+    }
+
+    /// Drops dominated candidates and duplicates, keeping the one with the fewest perks and then points
+    fn pareto_front(&self, candidates: Vec<OptimizedSheet>) -> Vec<OptimizedSheet> {
+
+        let dominates = |a: &[i64], b: &[i64]| a.iter().zip(b).all(|(x, y)| x >= y) && a != b;
+        let cost = |sheet: &CharacterSheet| {
+            let spent = SheetBudget::new(self.template, sheet).categories().iter().map(|(_, b)| b.spent).sum::<i64>();
+            (sheet.perks.as_ref().map_or(0, |p| p.len()), spent)
+        };
+
+        let mut front: Vec<OptimizedSheet> = vec![];
+
+        for candidate in candidates {
+
+            if front.iter().any(|f| dominates(&f.modifiers, &candidate.modifiers)) {
+                continue;
+            }
+
+            if let Some(same) = front.iter_mut().find(|f| f.modifiers == candidate.modifiers) {
+
+                if cost(&candidate.sheet) < cost(&same.sheet) {
+                    *same = candidate;
+                }
+
+                continue;
+
+            }
+
+            front.retain(|f| !dominates(&candidate.modifiers, &f.modifiers));
+            front.push(candidate);
+
+        }
+
+        front.sort_by(|a, b| b.modifiers.cmp(&a.modifiers));
+        front
+
+    }
+
+}
+
+/// An attribute, or one of its skills, and how many more points it can take
+#[derive(Clone, Copy)]
+struct Slot {
+    attribute: usize,
+    skill: Option<usize>,
+    room: i64
+}
+
+impl Slot {
+
+    fn current(&self, sheet: &CharacterSheet) -> i64 {
+
+        let attribute = &sheet.attributes[self.attribute];
+
+        match self.skill {
+            Some(s) => attribute.skills.as_ref().unwrap()[s].value,
+            None    => attribute.value
+        }
+
+    }
+
+    fn value<'s>(&self, sheet: &'s mut CharacterSheet) -> &'s mut i64 {
+
+        let attribute = &mut sheet.attributes[self.attribute];
+
+        match self.skill {
+            Some(s) => &mut attribute.skills.as_mut().unwrap()[s].value,
+            None    => &mut attribute.value
+        }
+
+    }
+
+}
+
+/// The attributes or skills sharing one allotment, split into the ones that raise a target and the rest
+struct PointGroup {
+    helpful: Vec<Slot>,
+    others: Vec<Slot>,
+    remaining: i64,
+    must_spend_all: bool
+}
+
+impl PointGroup {
+
+    fn new(optimizer: &PointOptimizer, base: &CharacterSheet, base_modifiers: &[i64], slots: Vec<Slot>, given: i64, must_spend_all: bool) -> Result<Self, RollError> {
+
+        let spent: i64 = slots.iter().map(|slot| slot.current(base)).sum();
+        let remaining = (given - spent).max(0);
+
+        let mut helpful = vec![];
+        let mut others = vec![];
+
+        // No slot can take more than the points left, which also keeps templates without a
+        // maximum per attribute or skill from overflowing when rooms are added up
+        let slots = slots.into_iter().map(|slot| Slot { room: slot.room.min(remaining), ..slot });
+
+        for slot in slots.filter(|s| s.room > 0) {
+
+            let mut sheet = base.clone();
+            *slot.value(&mut sheet) += 1;
+
+            if optimizer.improves(&sheet, base_modifiers)? {
+                helpful.push(slot);
+            } else {
+                others.push(slot);
+            }
+
+        }
+
+        Ok(Self {
+            helpful,
+            others,
+            remaining,
+            must_spend_all
+        })
+
+    }
+
+    /**
+     *
+     * Every way to spend the remaining points on the helpful slots where no slot could take another
+     * point, or None when there are more than `limit` of them.
+     *
+    */
+    fn allocations(&self, limit: usize) -> Option<Vec<Vec<i64>>> {
+
+        let mut allocations = vec![];
+        self.allocate(&mut vec![], self.remaining, limit, &mut allocations).then_some(allocations)
+
+    }
+
+    fn allocate(&self, current: &mut Vec<i64>, remaining: i64, limit: usize, allocations: &mut Vec<Vec<i64>>) -> bool {
+
+        let index = current.len();
+
+        if index == self.helpful.len() {
+
+            allocations.push(current.clone());
+            return allocations.len() <= limit;
+
+        }
+
+        let most = self.helpful[index].room.min(remaining);
+
+        // Whatever the later slots can't take has to go here, anything less leaves a point that
+        // could have helped. So every allocation tried is one that is kept
+        let later = self.helpful[index + 1..].iter().fold(0i64, |sum, slot| sum.saturating_add(slot.room));
+        let least = (remaining - later).clamp(0, most);
+
+        for points in least..=most {
+
+            current.push(points);
+            let within_limit = self.allocate(current, remaining - points, limit, allocations);
+            current.pop();
+
+            if !within_limit {
+                return false;
+            }
+
+        }
+
+        true
+
+    }
+
+    fn apply(&self, sheet: &mut CharacterSheet, allocation: &[i64]) {
+
+        for (slot, points) in self.helpful.iter().zip(allocation) {
+            *slot.value(sheet) += points;
+        }
+
+        if !self.must_spend_all {
+            return;
+        }
+
+        // Points that don't help any target still have to go somewhere
+        let mut leftover = self.remaining - allocation.iter().sum::<i64>();
+
+        for slot in self.others.iter() {
+
+            let points = slot.room.min(leftover);
+            *slot.value(sheet) += points;
+            leftover -= points;
+
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod point_optimizer_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    fn empty_sheet() -> CharacterSheet {
+
+        let mut sheet = STANDARD_TEMPLATE.get_base_character_sheet();
+        sheet.name = "Optimus".to_string();
+        sheet
+
+    }
+
+    fn value(sheet: &CharacterSheet, name: &str) -> i64 {
+
+        sheet.attributes
+            .iter()
+            .flat_map(|a| std::iter::once((&a.name, a.value)).chain(a.skills.iter().flatten().map(|s| (&s.name, s.value))))
+            .find(|(n, _)| *n == name)
+            .unwrap()
+            .1
+
+    }
+
+    #[test]
+    fn best_persuasion() {
+
+        let optimizer = PointOptimizer::new(&STANDARD_TEMPLATE, vec![RollTarget::Skill("Persuasion".to_string())]);
+        let best = optimizer.optimize(&empty_sheet()).unwrap();
+
+        let attribute_max = STANDARD_TEMPLATE.allotments.attributes.max_points_per_allotment.unwrap();
+        let skill_max = STANDARD_TEMPLATE.allotments.skills.unwrap().max_points_per_allotment.unwrap();
+
+        // Maxed Charisma and Persuasion, plus Charismatic's +3
+        assert_eq!(best.modifiers, vec![attribute_max + skill_max + 3]);
+        assert_eq!(value(&best.sheet, "Charisma"), attribute_max);
+        assert_eq!(value(&best.sheet, "Persuasion"), skill_max);
+        assert!(best.sheet.perks.as_ref().unwrap().contains(&"Charismatic".to_string()));
+
+        // Nothing is spent that doesn't help
+        assert_eq!(value(&best.sheet, "Strength"), 0);
+        assert!(CharacterSheetValidator::new(&STANDARD_TEMPLATE, &best.sheet).check().is_ok());
+
+    }
+
+    #[test]
+    fn keeps_what_the_sheet_already_has() {
+
+        let mut sheet = empty_sheet();
+        sheet.attributes.iter_mut().find(|a| a.name == "Strength").unwrap().value = 3;
+
+        let optimizer = PointOptimizer::new(&STANDARD_TEMPLATE, vec![RollTarget::Attribute("Agility".to_string())]);
+        let best = optimizer.optimize(&sheet).unwrap();
+
+        assert_eq!(value(&best.sheet, "Strength"), 3);
+        assert_eq!(value(&best.sheet, "Agility"), 5);
+
+    }
+
+    #[test]
+    fn pareto_set_for_competing_targets() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.allotments.attributes.given_points = 6;
+
+        // Small Frame trades Strength for Agility, leave it out to see only the points split
+        template.allotments.perks = None;
+
+        let targets = vec![RollTarget::Attribute("Agility".to_string()), RollTarget::Attribute("Strength".to_string())];
+        let front = PointOptimizer::new(&template, targets).pareto(&empty_sheet()).unwrap();
+
+        // Six points split between two attributes capped at five
+        let agility: Vec<i64> = front.iter().map(|c| value(&c.sheet, "Agility")).collect();
+        assert_eq!(agility, vec![5, 4, 3, 2, 1]);
+
+        for candidate in &front {
+            assert_eq!(value(&candidate.sheet, "Agility") + value(&candidate.sheet, "Strength"), 6);
+        }
+
+    }
+
+    #[test]
+    fn no_maximum_per_attribute() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.allotments.attributes.max_points_per_allotment = None;
+        template.allotments.perks = None;
+
+        let targets = ["Agility", "Strength", "Charisma"].iter().map(|name| RollTarget::Attribute(name.to_string())).collect();
+        let front = PointOptimizer::new(&template, targets).pareto(&empty_sheet()).unwrap();
+
+        // Every split of the attribute points between the three
+        let given = template.allotments.attributes.given_points;
+        assert_eq!(front.len() as i64, (given + 1) * (given + 2) / 2);
+
+        for candidate in &front {
+            assert_eq!(candidate.modifiers.iter().sum::<i64>(), given);
+        }
+
+    }
+
+    #[test]
+    fn repairs_are_returned() {
+
+        let mut sheet = empty_sheet();
+        sheet.attributes.retain(|a| a.name != "Strength");
+
+        let optimizer = PointOptimizer::new(&STANDARD_TEMPLATE, vec![RollTarget::Attribute("Agility".to_string())]);
+        let best = optimizer.optimize(&sheet).unwrap();
+
+        assert_eq!(best.repairs.iter().map(|r| r.location.as_str()).collect::<Vec<_>>(), vec!["attribute Strength"]);
+        assert!(optimizer.optimize(&empty_sheet()).unwrap().repairs.is_empty());
+
+    }
+
+    #[test]
+    fn search_is_bounded() {
+
+        let targets = STANDARD_TEMPLATE.attributes
+            .iter()
+            .flat_map(|a| a.skills.iter().flatten())
+            .map(|s| RollTarget::Skill(s.name.clone()))
+            .collect();
+
+        let optimizer = PointOptimizer::new(&STANDARD_TEMPLATE, targets);
+        assert_eq!(optimizer.pareto(&empty_sheet()).err(), Some(OptimizerError::TooManyCandidates(MAX_CANDIDATES)));
+
+    }
+
+    #[test]
+    fn unknown_target() {
+
+        let optimizer = PointOptimizer::new(&STANDARD_TEMPLATE, vec![RollTarget::Skill("Juggling".to_string())]);
+        assert_eq!(optimizer.optimize(&empty_sheet()).err(), Some(OptimizerError::Roll(RollError::InvalidSkill("Juggling".to_string()))));
+
+        let optimizer = PointOptimizer::new(&STANDARD_TEMPLATE, vec![]);
+        assert_eq!(optimizer.pareto(&empty_sheet()).err(), Some(OptimizerError::NoTargets));
+
+    }
+
+}
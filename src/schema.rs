@@ -0,0 +1,140 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use schemars::schema_for;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::character_sheet::CharacterSheet;
+use crate::character_template::CharacterTemplate;
+
+/// A place where a document doesn't match its schema
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct SchemaViolation {
+    /// JSON pointer to the offending value, e.g. "/perks/0/point_cost"
+    pub path: String,
+    pub message: String
+}
+
+impl std::fmt::Display for SchemaViolation {
+
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+
+        let path = if self.path.is_empty() { "/" } else { &self.path };
+        write!(f, "{}: {}", path, self.message)
+
+    }
+
+}
+
+/// The JSON Schema for templates, generated from `CharacterTemplate`
+pub fn template_schema() -> Value {
+    serde_json::to_value(schema_for!(CharacterTemplate)).unwrap()
+}
+
+/// The JSON Schema for sheets, generated from `CharacterSheet`
+pub fn sheet_schema() -> Value {
+    serde_json::to_value(schema_for!(CharacterSheet)).unwrap()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn get_template_schema() -> String {
+    serde_json::to_string_pretty(&template_schema()).unwrap()
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub fn get_sheet_schema() -> String {
+    serde_json::to_string_pretty(&sheet_schema()).unwrap()
+}
+
+/**
+ * 
+ * Checks a template's JSON against the schema before it's deserialized, reporting every
+ * mismatch with its location rather than the first error serde runs into.
+ * 
+*/
+#[cfg(not(target_arch = "wasm32"))]
+pub fn prevalidate_template(template: &Value) -> Result<(), Vec<SchemaViolation>> {
+    prevalidate(&template_schema(), template)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub fn prevalidate_sheet(sheet: &Value) -> Result<(), Vec<SchemaViolation>> {
+    prevalidate(&sheet_schema(), sheet)
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn prevalidate(schema: &Value, document: &Value) -> Result<(), Vec<SchemaViolation>> {
+
+    let validator = jsonschema::validator_for(schema).expect("generated schemas are valid");
+
+    let violations: Vec<SchemaViolation> = validator
+        .iter_errors(document)
+        .map(|e| SchemaViolation {
+            path: e.instance_path().to_string(),
+            message: e.to_string()
+        })
+        .collect();
+
+    if violations.is_empty() {
+        return Ok(());
+    }
+
+    Err(violations)
+
+}
+
+#[cfg(test)]
+mod schema_tests {
+
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+
+    const TEMPLATE_SCHEMA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/schemas/character_template.schema.json");
+    const SHEET_SCHEMA_PATH: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/schemas/character_sheet.schema.json");
+
+    /// Run with UPDATE_SCHEMAS=1 to regenerate the checked in schemas after changing the types
+    #[test]
+    fn schema_files_are_up_to_date() {
+
+        for (path, schema) in [(TEMPLATE_SCHEMA_PATH, get_template_schema()), (SHEET_SCHEMA_PATH, get_sheet_schema())] {
+
+            if std::env::var_os("UPDATE_SCHEMAS").is_some() {
+                std::fs::write(path, format!("{}\n", schema)).unwrap();
+            }
+
+            let checked_in = std::fs::read_to_string(path).unwrap();
+            assert_eq!(checked_in.trim_end(), schema, "{} is out of date, run the tests with UPDATE_SCHEMAS=1", path);
+
+        }
+
+    }
+
+    #[test]
+    fn standard_files_match_schemas() {
+
+        let template: Value = json5::from_str(STANDARD_TEMPLATE_STR).unwrap();
+        assert_eq!(prevalidate_template(&template), Ok(()));
+
+        let sheet: Value = serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap();
+        assert_eq!(prevalidate_sheet(&sheet), Ok(()));
+
+    }
+
+    #[test]
+    fn violations_are_located() {
+
+        let mut template: Value = json5::from_str(STANDARD_TEMPLATE_STR).unwrap();
+        template["perks"][0]["point_cost"] = Value::String("two".to_string());
+        template.as_object_mut().unwrap().remove("base_health");
+
+        let violations = prevalidate_template(&template).unwrap_err();
+        assert_eq!(violations.len(), 2);
+        assert!(violations.iter().any(|v| v.path == "/perks/0/point_cost"));
+        assert!(violations.iter().any(|v| v.path.is_empty() && v.message.contains("base_health")));
+
+    }
+
+}
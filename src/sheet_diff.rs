@@ -0,0 +1,424 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::budget::{PointBudget, SheetBudget};
+use crate::character_sheet::CharacterSheet;
+use crate::character_template::CharacterTemplate;
+use crate::character_template::composition::CompositionError;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum DiffError {
+    #[error("Can not compare a {before} sheet with a {after} sheet")]
+    TemplateMismatch {
+        before: String,
+        after: String
+    },
+    /// Budgets and costs come from one template, so both sheets have to be on the same version of it
+    #[error("Can not compare a sheet made with version {before} of the template with one made with version {after}")]
+    VersionMismatch {
+        before: String,
+        after: String
+    },
+    #[error(transparent)]
+    Composition(#[from] CompositionError)
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct TextChange {
+    pub before: Option<String>,
+    pub after: Option<String>
+}
+
+/// Points in an attribute or skill before and after, entries missing from a sheet count as 0
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct ValueChange {
+    pub name: String,
+    pub before: i64,
+    pub after: i64
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct BudgetChange {
+    /// One of the categories in `SheetBudget::categories`
+    pub category: String,
+    pub before: PointBudget,
+    pub after: PointBudget
+}
+
+/**
+ *
+ * What changed between two versions of a sheet made with the same template, for a GM to
+ * approve. Only changes are listed, an unchanged sheet has an empty diff.
+ *
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct SheetDiff {
+    pub name: Option<TextChange>,
+    pub description: Option<TextChange>,
+    pub attributes: Vec<ValueChange>,
+    pub skills: Vec<ValueChange>,
+    pub perks_added: Vec<String>,
+    pub perks_removed: Vec<String>,
+    pub weapons_added: Vec<String>,
+    pub weapons_removed: Vec<String>,
+    pub weapon_categories_added: Vec<String>,
+    pub weapon_categories_removed: Vec<String>,
+    /// Only the allotments where the points spent changed
+    pub budget: Vec<BudgetChange>
+}
+
+impl ValueChange {
+
+    pub fn delta(&self) -> i64 {
+        self.after - self.before
+    }
+
+}
+
+impl SheetDiff {
+
+    pub fn new(template: &CharacterTemplate, before: &CharacterSheet, after: &CharacterSheet) -> Result<Self, DiffError> {
+
+        if before.template.name != after.template.name {
+            return Err(DiffError::TemplateMismatch {
+                before: before.template.name.clone(),
+                after: after.template.name.clone()
+            });
+        }
+
+        if before.template.version != after.template.version {
+
+            let version = |sheet: &CharacterSheet| sheet.template.version.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(".");
+
+            return Err(DiffError::VersionMismatch {
+                before: version(before),
+                after: version(after)
+            });
+
+        }
+
+        // Each sheet is read with the modules it uses
+        let before_template = template.for_sheet(before)?;
+        let after_template = template.for_sheet(after)?;
+
+        let before = before.resolve_ids(&before_template);
+        let after = after.resolve_ids(&after_template);
+
+        let attributes = value_changes(
+            before.attributes.iter().map(|a| (&a.name, a.value)),
+            after.attributes.iter().map(|a| (&a.name, a.value))
+        );
+
+        let skills = value_changes(
+            before.attributes.iter().flat_map(|a| a.skills.iter().flatten()).map(|s| (&s.name, s.value)),
+            after.attributes.iter().flat_map(|a| a.skills.iter().flatten()).map(|s| (&s.name, s.value))
+        );
+
+        let (perks_added, perks_removed) = list_changes(before.perks.as_deref(), after.perks.as_deref());
+        let (weapons_added, weapons_removed) = list_changes(Some(&before.weapon_proficiencies), Some(&after.weapon_proficiencies));
+        let (weapon_categories_added, weapon_categories_removed) = list_changes(before.weapon_categories.as_deref(), after.weapon_categories.as_deref());
+
+        let before_budget = SheetBudget::new(&before_template, &before);
+        let after_budget = SheetBudget::new(&after_template, &after);
+
+        let budget = before_budget
+            .categories()
+            .into_iter()
+            .zip(after_budget.categories())
+            .filter(|((_, b), (_, a))| b.spent != a.spent)
+            .map(|((category, b), (_, a))| BudgetChange {
+                category: category.to_string(),
+                before: b.clone(),
+                after: a.clone()
+            })
+            .collect();
+
+        Ok(Self {
+            name: text_change(Some(&before.name), Some(&after.name)),
+            description: text_change(before.description.as_ref(), after.description.as_ref()),
+            attributes,
+            skills,
+            perks_added,
+            perks_removed,
+            weapons_added,
+            weapons_removed,
+            weapon_categories_added,
+            weapon_categories_removed,
+            budget
+        })
+
+    }
+
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl SheetDiff {
+
+    pub fn is_empty(&self) -> bool {
+
+        self.name.is_none()
+            && self.description.is_none()
+            && self.attributes.is_empty()
+            && self.skills.is_empty()
+            && self.perks_added.is_empty()
+            && self.perks_removed.is_empty()
+            && self.weapons_added.is_empty()
+            && self.weapons_removed.is_empty()
+            && self.weapon_categories_added.is_empty()
+            && self.weapon_categories_removed.is_empty()
+
+    }
+
+    /// One line per change, e.g. "Agility: 4 -> 5 (+1)"
+    pub fn render(&self) -> String {
+
+        let mut lines = vec![];
+
+        if let Some(name) = &self.name {
+            lines.push(format!("Name: {} -> {}", name.before.as_deref().unwrap_or_default(), name.after.as_deref().unwrap_or_default()));
+        }
+
+        if let Some(description) = &self.description {
+
+            let line = match (&description.before, &description.after) {
+                (None, _) => "Description added".to_string(),
+                (_, None) => "Description removed".to_string(),
+                _         => "Description changed".to_string()
+            };
+
+            lines.push(line);
+
+        }
+
+        for change in self.attributes.iter().chain(self.skills.iter()) {
+            lines.push(format!("{}: {} -> {} ({:+})", change.name, change.before, change.after, change.delta()));
+        }
+
+        for (label, names) in [
+            ("Perks added", &self.perks_added),
+            ("Perks removed", &self.perks_removed),
+            ("Weapons added", &self.weapons_added),
+            ("Weapons removed", &self.weapons_removed),
+            ("Weapon categories added", &self.weapon_categories_added),
+            ("Weapon categories removed", &self.weapon_categories_removed)
+        ] {
+
+            if !names.is_empty() {
+                lines.push(format!("{}: {}", label, names.join(", ")));
+            }
+
+        }
+
+        for change in &self.budget {
+            lines.push(format!("{} points spent: {}/{} -> {}/{}", change.category, change.before.spent, change.before.given, change.after.spent, change.after.given));
+        }
+
+        lines.join("\n")
+
+    }
+
+    pub fn as_json_str(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl CharacterTemplate {
+
+    /// The changes from one version of a sheet made with this template to another
+    pub fn diff_sheets(&self, before: &CharacterSheet, after: &CharacterSheet) -> Result<SheetDiff, String> {
+        SheetDiff::new(self, before, after).map_err(|e| e.to_string())
+    }
+
+}
+
+fn text_change(before: Option<&String>, after: Option<&String>) -> Option<TextChange> {
+
+    if before == after {
+        return None;
+    }
+
+    Some(TextChange {
+        before: before.cloned(),
+        after: after.cloned()
+    })
+
+}
+
+/// Changed values in the order of the new sheet, then entries only the old sheet has
+fn value_changes<'a>(before: impl Iterator<Item = (&'a String, i64)>, after: impl Iterator<Item = (&'a String, i64)>) -> Vec<ValueChange> {
+
+    let before: Vec<(&String, i64)> = before.collect();
+    let after: Vec<(&String, i64)> = after.collect();
+
+    let value = |entries: &[(&String, i64)], name: &str| entries.iter().find(|(n, _)| *n == name).map_or(0, |(_, v)| *v);
+
+    let removed = before.iter().filter(|(name, _)| !after.iter().any(|(n, _)| n == name));
+
+    after
+        .iter()
+        .chain(removed)
+        .map(|(name, _)| ValueChange {
+            name: name.to_string(),
+            before: value(&before, name),
+            after: value(&after, name)
+        })
+        .filter(|change| change.before != change.after)
+        .collect()
+
+}
+
+/// The entries added to and removed from a list
+fn list_changes(before: Option<&[String]>, after: Option<&[String]>) -> (Vec<String>, Vec<String>) {
+
+    let before = before.unwrap_or_default();
+    let after = after.unwrap_or_default();
+
+    let added = after.iter().filter(|e| !before.contains(e)).cloned().collect();
+    let removed = before.iter().filter(|e| !after.contains(e)).cloned().collect();
+
+    (added, removed)
+
+}
+
+#[cfg(test)]
+mod sheet_diff_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    fn set_value(sheet: &mut CharacterSheet, name: &str, value: i64) {
+
+        for attribute in sheet.attributes.iter_mut() {
+
+            if attribute.name == name {
+                attribute.value = value;
+            }
+
+            for skill in attribute.skills.iter_mut().flatten().filter(|s| s.name == name) {
+                skill.value = value;
+            }
+
+        }
+
+    }
+
+    #[test]
+    fn unchanged_sheet() {
+
+        let diff = SheetDiff::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, &CHARACTER_SHEET_SAMPLE).unwrap();
+
+        assert!(diff.is_empty());
+        assert!(diff.budget.is_empty());
+        assert_eq!(diff.render(), "");
+
+    }
+
+    #[test]
+    fn reports_changes() {
+
+        let mut after = CHARACTER_SHEET_SAMPLE.clone();
+        after.name = "Eliza".to_string();
+        after.description = Some("A smuggler".to_string());
+        after.perks = Some(vec!["Small Frame".to_string(), "Honest Face".to_string()]);
+        after.weapon_proficiencies.push("Rifle".to_string());
+        set_value(&mut after, "Agility", 5);
+        set_value(&mut after, "Stealth", 2);
+
+        let diff = SheetDiff::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, &after).unwrap();
+
+        assert_eq!(diff.attributes, vec![ValueChange { name: "Agility".to_string(), before: 4, after: 5 }]);
+        assert_eq!(diff.skills, vec![ValueChange { name: "Stealth".to_string(), before: 0, after: 2 }]);
+        assert_eq!(diff.perks_added, vec!["Honest Face".to_string()]);
+        assert_eq!(diff.perks_removed, vec!["Charismatic".to_string()]);
+        assert_eq!(diff.weapons_added, vec!["Rifle".to_string()]);
+
+        let categories: Vec<&str> = diff.budget.iter().map(|b| b.category.as_str()).collect();
+        assert_eq!(categories, vec!["attributes", "skills", "perks", "weapon_proficiencies"]);
+
+        let rendered = diff.render();
+        assert!(rendered.contains("Name: Elizala -> Eliza"));
+        assert!(rendered.contains("Description changed"));
+        assert!(rendered.contains("Agility: 4 -> 5 (+1)"));
+        assert!(rendered.contains("Perks removed: Charismatic"));
+        assert!(rendered.contains("attributes points spent: 15/15 -> 16/15"));
+
+    }
+
+    #[test]
+    fn removed_entries_count_as_zero() {
+
+        let mut after = CHARACTER_SHEET_SAMPLE.clone();
+        after.attributes.retain(|a| a.name != "Charisma");
+
+        let diff = SheetDiff::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, &after).unwrap();
+
+        assert_eq!(diff.attributes, vec![ValueChange { name: "Charisma".to_string(), before: 5, after: 0 }]);
+        assert_eq!(diff.skills.len(), 2);
+        assert!(diff.skills.iter().all(|s| s.after == 0));
+
+    }
+
+    #[test]
+    fn different_templates() {
+
+        let mut after = CHARACTER_SHEET_SAMPLE.clone();
+        after.template.name = "Homebrew".to_string();
+
+        assert_eq!(SheetDiff::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, &after), Err(DiffError::TemplateMismatch {
+            before: "Standard".to_string(),
+            after: "Homebrew".to_string()
+        }));
+
+    }
+
+    #[test]
+    fn different_versions() {
+
+        let mut after = CHARACTER_SHEET_SAMPLE.clone();
+        after.template.version[1] = 1;
+
+        assert_eq!(SheetDiff::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, &after), Err(DiffError::VersionMismatch {
+            before: "1.0.0".to_string(),
+            after: "1.1.0".to_string()
+        }));
+
+    }
+
+    #[test]
+    fn module_perks_are_charged() {
+
+        let mut after = CHARACTER_SHEET_SAMPLE.clone();
+        after.template.modules = Some(vec!["Jedi Academy".to_string()]);
+        after.perks.as_mut().unwrap().push("lightsaber_training".to_string());
+
+        let diff = SheetDiff::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, &after).unwrap();
+        assert_eq!(diff.perks_added, vec!["Lightsaber Training".to_string()]);
+
+        let perks = diff.budget.iter().find(|b| b.category == "perks").unwrap();
+        assert_eq!(perks.after.spent - perks.before.spent, 1);
+
+        after.template.modules = Some(vec!["Missing".to_string()]);
+        assert!(matches!(SheetDiff::new(&STANDARD_TEMPLATE, &CHARACTER_SHEET_SAMPLE, &after), Err(DiffError::Composition(_))));
+
+    }
+
+}
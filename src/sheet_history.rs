@@ -0,0 +1,455 @@
+#[cfg(target_arch = "wasm32")]
+use wasm_bindgen::prelude::*;
+
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+use crate::budget::SheetBudget;
+use crate::character_sheet::CharacterSheet;
+use crate::character_sheet_validator::CharacterSheetValidator;
+use crate::character_template::CharacterTemplate;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum EditError {
+    #[error("Sheet does not have a {0} attribute")]
+    AttributeNotFound(String),
+    #[error("Sheet does not have a {skill} skill in the {attribute} attribute")]
+    SkillNotFound {
+        attribute: String,
+        skill: String
+    },
+    #[error("{0} is already on the sheet")]
+    AlreadyPresent(String),
+    #[error("{0} is not on the sheet")]
+    NotPresent(String),
+    #[error("{0} is equipped and has to be unequipped first")]
+    ItemEquipped(String)
+}
+
+/**
+ *
+ * A single change to a sheet. Lists are edited by name, `index` puts an entry back where it
+ * was when a removal is undone and appends it when not set.
+ *
+*/
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub enum SheetEdit {
+    SetName { name: String },
+    SetDescription { description: Option<String> },
+    SetLevel { level: Option<i64> },
+    SetAttribute { attribute: String, value: i64 },
+    SetSkill { attribute: String, skill: String, value: i64 },
+    AddPerk { perk: String, index: Option<usize> },
+    RemovePerk { perk: String },
+    AddWeapon { weapon: String, index: Option<usize> },
+    RemoveWeapon { weapon: String },
+    AddItem { item: String, index: Option<usize> },
+    RemoveItem { item: String },
+    Equip { item: String, index: Option<usize> },
+    Unequip { item: String }
+}
+
+impl SheetEdit {
+
+    pub fn description(&self) -> String {
+
+        use SheetEdit::*;
+
+        match self {
+            SetName { name }                       => format!("Rename to {}", name),
+            SetDescription { .. }                  => "Change description".to_string(),
+            SetLevel { level }                     => format!("Set level to {}", level.unwrap_or(1)),
+            SetAttribute { attribute, value }      => format!("Set {} to {}", attribute, value),
+            SetSkill { skill, value, .. }          => format!("Set {} to {}", skill, value),
+            AddPerk { perk, .. }                   => format!("Add perk {}", perk),
+            RemovePerk { perk }                    => format!("Remove perk {}", perk),
+            AddWeapon { weapon, .. }               => format!("Add weapon proficiency {}", weapon),
+            RemoveWeapon { weapon }                => format!("Remove weapon proficiency {}", weapon),
+            AddItem { item, .. }                   => format!("Add {} to the inventory", item),
+            RemoveItem { item }                    => format!("Remove {} from the inventory", item),
+            Equip { item, .. }                     => format!("Equip {}", item),
+            Unequip { item }                       => format!("Unequip {}", item)
+        }
+
+    }
+
+    /// Applies the edit, returning the edit that undoes it
+    pub fn apply(&self, sheet: &mut CharacterSheet) -> Result<SheetEdit, EditError> {
+
+        use SheetEdit::*;
+
+        let inverse = match self {
+
+            SetName { name } => SetName { name: std::mem::replace(&mut sheet.name, name.clone()) },
+            SetDescription { description } => SetDescription { description: std::mem::replace(&mut sheet.description, description.clone()) },
+            SetLevel { level } => SetLevel { level: std::mem::replace(&mut sheet.level, *level) },
+
+            SetAttribute { attribute, value } => {
+
+                let sheet_attribute = sheet.attributes
+                    .iter_mut()
+                    .find(|a| a.name == *attribute)
+                    .ok_or_else(|| EditError::AttributeNotFound(attribute.clone()))?;
+
+                SetAttribute { attribute: attribute.clone(), value: std::mem::replace(&mut sheet_attribute.value, *value) }
+
+            },
+
+            SetSkill { attribute, skill, value } => {
+
+                let sheet_skill = sheet.attributes
+                    .iter_mut()
+                    .find(|a| a.name == *attribute)
+                    .and_then(|a| a.skills.as_mut())
+                    .and_then(|skills| skills.iter_mut().find(|s| s.name == *skill))
+                    .ok_or_else(|| EditError::SkillNotFound { attribute: attribute.clone(), skill: skill.clone() })?;
+
+                SetSkill { attribute: attribute.clone(), skill: skill.clone(), value: std::mem::replace(&mut sheet_skill.value, *value) }
+
+            },
+
+            AddPerk { perk, index } => {
+                insert(sheet.perks.get_or_insert_with(Vec::new), perk, *index)?;
+                RemovePerk { perk: perk.clone() }
+            },
+            RemovePerk { perk } => AddPerk { perk: perk.clone(), index: Some(remove(sheet.perks.as_mut(), perk)?) },
+
+            AddWeapon { weapon, index } => {
+                insert(&mut sheet.weapon_proficiencies, weapon, *index)?;
+                RemoveWeapon { weapon: weapon.clone() }
+            },
+            RemoveWeapon { weapon } => AddWeapon { weapon: weapon.clone(), index: Some(remove(Some(&mut sheet.weapon_proficiencies), weapon)?) },
+
+            AddItem { item, index } => {
+                insert(sheet.inventory.get_or_insert_with(Vec::new), item, *index)?;
+                RemoveItem { item: item.clone() }
+            },
+            RemoveItem { item } => {
+
+                if sheet.equipped.iter().flatten().any(|e| e == item) {
+                    return Err(EditError::ItemEquipped(item.clone()));
+                }
+
+                AddItem { item: item.clone(), index: Some(remove(sheet.inventory.as_mut(), item)?) }
+
+            },
+
+            Equip { item, index } => {
+                insert(sheet.equipped.get_or_insert_with(Vec::new), item, *index)?;
+                Unequip { item: item.clone() }
+            },
+            Unequip { item } => Equip { item: item.clone(), index: Some(remove(sheet.equipped.as_mut(), item)?) }
+
+        };
+
+        Ok(inverse)
+
+    }
+
+}
+
+fn insert(entries: &mut Vec<String>, entry: &str, index: Option<usize>) -> Result<(), EditError> {
+
+    if entries.iter().any(|e| e == entry) {
+        return Err(EditError::AlreadyPresent(entry.to_string()));
+    }
+
+    let index = index.unwrap_or(entries.len()).min(entries.len());
+    entries.insert(index, entry.to_string());
+
+    Ok(())
+
+}
+
+fn remove(entries: Option<&mut Vec<String>>, entry: &str) -> Result<usize, EditError> {
+
+    let not_present = || EditError::NotPresent(entry.to_string());
+
+    let entries = entries.ok_or_else(not_present)?;
+    let index = entries.iter().position(|e| e == entry).ok_or_else(not_present)?;
+    entries.remove(index);
+
+    Ok(index)
+
+}
+
+/// An edit that has been applied, with what it takes to undo it
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct EditCommand {
+    pub description: String,
+    pub edit: SheetEdit,
+    pub undo: SheetEdit
+}
+
+impl EditCommand {
+
+    pub fn apply(edit: SheetEdit, sheet: &mut CharacterSheet) -> Result<Self, EditError> {
+
+        let undo = edit.apply(sheet)?;
+
+        Ok(Self {
+            description: edit.description(),
+            edit,
+            undo
+        })
+
+    }
+
+    pub fn revert(&self, sheet: &mut CharacterSheet) -> Result<(), EditError> {
+        self.undo.apply(sheet).map(|_| ())
+    }
+
+    pub fn reapply(&self, sheet: &mut CharacterSheet) -> Result<(), EditError> {
+        self.edit.apply(sheet).map(|_| ())
+    }
+
+}
+
+/// The sheet after a step of the history: its budget, and why it doesn't pass the validator if it doesn't
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen(getter_with_clone))]
+pub struct StepCheck {
+    pub budget: SheetBudget,
+    pub error: Option<String>
+}
+
+/**
+ *
+ * A sheet being edited, with undo and redo. The oldest steps are dropped once the history
+ * holds `limit` of them, and applying a new edit clears what could be redone. Every step is
+ * checked against the template, but invalid sheets are kept since most sheets pass through
+ * invalid states while they are being filled in.
+ *
+*/
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+pub struct SheetHistory {
+    template: CharacterTemplate,
+    sheet: CharacterSheet,
+    undo: VecDeque<EditCommand>,
+    redo: Vec<EditCommand>,
+    limit: usize
+}
+
+impl SheetHistory {
+
+    pub fn apply(&mut self, edit: SheetEdit) -> Result<StepCheck, EditError> {
+
+        let command = EditCommand::apply(edit, &mut self.sheet)?;
+
+        self.redo.clear();
+        self.undo.push_back(command);
+
+        if self.undo.len() > self.limit {
+            self.undo.pop_front();
+        }
+
+        Ok(self.check())
+
+    }
+
+    pub fn undo_stack(&self) -> impl Iterator<Item = &EditCommand> {
+        self.undo.iter()
+    }
+
+}
+
+#[cfg_attr(target_arch = "wasm32", wasm_bindgen)]
+impl SheetHistory {
+
+    #[cfg_attr(target_arch = "wasm32", wasm_bindgen(constructor))]
+    pub fn new(template: CharacterTemplate, sheet: CharacterSheet, limit: usize) -> Self {
+
+        Self {
+            template,
+            sheet,
+            undo: VecDeque::new(),
+            redo: vec![],
+            limit
+        }
+
+    }
+
+    /// Applies an edit given as JSON, e.g. {"SetAttribute":{"attribute":"Agility","value":5}}
+    pub fn apply_json(&mut self, edit: String) -> Result<StepCheck, String> {
+
+        let edit: SheetEdit = serde_json::from_str(&edit).map_err(|e| e.to_string())?;
+        self.apply(edit).map_err(|e| e.to_string())
+
+    }
+
+    /// Undoes the last edit, returns nothing when there is nothing to undo
+    pub fn undo(&mut self) -> Option<StepCheck> {
+
+        let command = self.undo.pop_back()?;
+
+        if command.revert(&mut self.sheet).is_err() {
+            self.undo.push_back(command);
+            return None;
+        }
+
+        self.redo.push(command);
+        Some(self.check())
+
+    }
+
+    /// Redoes the last undone edit, returns nothing when there is nothing to redo
+    pub fn redo(&mut self) -> Option<StepCheck> {
+
+        let command = self.redo.pop()?;
+
+        if command.reapply(&mut self.sheet).is_err() {
+            self.redo.push(command);
+            return None;
+        }
+
+        self.undo.push_back(command);
+        Some(self.check())
+
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    /// What undo would revert, e.g. "Set Agility to 5"
+    pub fn undo_description(&self) -> Option<String> {
+        self.undo.back().map(|c| c.description.clone())
+    }
+
+    pub fn redo_description(&self) -> Option<String> {
+        self.redo.last().map(|c| c.description.clone())
+    }
+
+    pub fn sheet(&self) -> CharacterSheet {
+        self.sheet.clone()
+    }
+
+    pub fn check(&self) -> StepCheck {
+
+        StepCheck {
+            budget: SheetBudget::new(&self.template, &self.sheet),
+            error: CharacterSheetValidator::new(&self.template, &self.sheet).check().err().map(|e| e.to_string())
+        }
+
+    }
+
+}
+
+#[cfg(test)]
+mod sheet_history_tests {
+
+    use std::sync::LazyLock;
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    fn history(limit: usize) -> SheetHistory {
+        SheetHistory::new(STANDARD_TEMPLATE.clone(), CHARACTER_SHEET_SAMPLE.clone(), limit)
+    }
+
+    fn agility(history: &SheetHistory) -> i64 {
+        history.sheet.attributes.iter().find(|a| a.name == "Agility").unwrap().value
+    }
+
+    #[test]
+    fn undo_and_redo() {
+
+        let mut history = history(10);
+
+        let check = history.apply(SheetEdit::SetAttribute { attribute: "Agility".to_string(), value: 5 }).unwrap();
+        assert_eq!(check.budget.attributes.spent, 16);
+        assert!(check.error.is_some());
+
+        assert_eq!(history.undo_description(), Some("Set Agility to 5".to_string()));
+
+        let check = history.undo().unwrap();
+        assert_eq!(agility(&history), 4);
+        assert_eq!(check.error, None);
+
+        history.redo().unwrap();
+        assert_eq!(agility(&history), 5);
+
+        assert!(history.redo().is_none());
+
+    }
+
+    #[test]
+    fn list_edits_restore_order() {
+
+        let mut history = history(10);
+
+        history.apply(SheetEdit::RemovePerk { perk: "Charismatic".to_string() }).unwrap();
+        history.apply(SheetEdit::AddPerk { perk: "Honest Face".to_string(), index: None }).unwrap();
+        assert_eq!(history.sheet.perks, Some(vec!["Small Frame".to_string(), "Honest Face".to_string()]));
+
+        history.undo().unwrap();
+        history.undo().unwrap();
+        assert_eq!(history.sheet.perks, CHARACTER_SHEET_SAMPLE.perks);
+
+        assert_eq!(
+            history.apply(SheetEdit::AddPerk { perk: "Small Frame".to_string(), index: None }).unwrap_err(),
+            EditError::AlreadyPresent("Small Frame".to_string())
+        );
+
+    }
+
+    #[test]
+    fn new_edit_clears_redo() {
+
+        let mut history = history(10);
+
+        history.apply(SheetEdit::SetName { name: "Eliza".to_string() }).unwrap();
+        history.undo().unwrap();
+        assert!(history.can_redo());
+
+        history.apply(SheetEdit::SetLevel { level: Some(2) }).unwrap();
+        assert!(!history.can_redo());
+
+    }
+
+    #[test]
+    fn history_is_bounded() {
+
+        let mut history = history(2);
+
+        for value in 1..=4 {
+            history.apply(SheetEdit::SetAttribute { attribute: "Agility".to_string(), value }).unwrap();
+        }
+
+        assert_eq!(history.undo_stack().count(), 2);
+
+        while history.undo().is_some() {}
+        assert_eq!(agility(&history), 2);
+
+    }
+
+    #[test]
+    fn edits_from_json() {
+
+        let mut history = history(10);
+
+        history.apply_json(r#"{"SetSkill":{"attribute":"Agility","skill":"Stealth","value":2}}"#.to_string()).unwrap();
+        assert!(history.apply_json(r#"{"SetAttribute":{"attribute":"Luck","value":2}}"#.to_string()).is_err());
+        assert!(history.apply_json("{}".to_string()).is_err());
+
+        let json = serde_json::to_string(history.undo_stack().next().unwrap()).unwrap();
+        let command: EditCommand = serde_json::from_str(&json).unwrap();
+        assert_eq!(command.undo, SheetEdit::SetSkill { attribute: "Agility".to_string(), skill: "Stealth".to_string(), value: 0 });
+
+    }
+
+}
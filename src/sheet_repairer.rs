@@ -0,0 +1,674 @@
+use serde::Serialize;
+
+use crate::character_sheet::{CharacterSheet, SheetAttribute};
+use crate::character_sheet_validator::{CharacterSheetError, CharacterSheetValidator};
+use crate::character_template::CharacterTemplate;
+use crate::effective_stats::resource_maximum;
+
+#[derive(Serialize, Debug, Clone, PartialEq)]
+pub struct RepairChange {
+    /// What was changed, e.g. "skill Stealth"
+    pub location: String,
+    pub message: String
+}
+
+pub struct RepairResult {
+    pub sheet: CharacterSheet,
+    pub changes: Vec<RepairChange>,
+    /// What the validator still rejects after repairing, such as spending too many attribute
+    /// points, which has no single safe fix
+    pub remaining_error: Option<CharacterSheetError>
+}
+
+impl RepairResult {
+
+    pub fn is_valid(&self) -> bool {
+        self.remaining_error.is_none()
+    }
+
+}
+
+/**
+ *
+ * Fixes what it safely can in a sheet that doesn't match its template: entries the template
+ * doesn't define are dropped, missing attributes and skills are added at 0, values are clamped
+ * to the allotments' minimums and maximums, and perks and weapon proficiencies the sheet can't afford are
+ * removed, last selected first. Resources the sheet doesn't qualify for are dropped too.
+ * Every fix is recorded so it can be shown to the player.
+ *
+*/
+pub struct SheetRepairer<'a> {
+    template: &'a CharacterTemplate
+}
+
+impl<'a> SheetRepairer<'a> {
+
+    pub fn new(template: &'a CharacterTemplate) -> Self {
+
+        Self {
+            template
+        }
+
+    }
+
+    pub fn repair(&self, sheet: &CharacterSheet) -> RepairResult {
+
+        let mut repair = Repair {
+            sheet: sheet.resolve_ids(self.template),
+            changes: vec![]
+        };
+
+        repair.modules(self.template);
+
+        // Modules can add perks, weapons and equipment, so everything else is repaired
+        // against the template the sheet will be validated with
+        let template = self.template.for_sheet(&repair.sheet).unwrap_or_else(|_| self.template.clone());
+
+        repair.attributes(&template);
+        repair.clamp_points(&template);
+        repair.perks(&template);
+        repair.weapon_proficiencies(&template);
+        repair.equipment(&template);
+        repair.resources(&template);
+
+        let remaining_error = CharacterSheetValidator::new(self.template, &repair.sheet).check().err();
+
+        RepairResult {
+            sheet: repair.sheet,
+            changes: repair.changes,
+            remaining_error
+        }
+
+    }
+
+}
+
+struct Repair {
+    sheet: CharacterSheet,
+    changes: Vec<RepairChange>
+}
+
+impl Repair {
+
+    fn change(&mut self, location: String, message: impl Into<String>) {
+
+        self.changes.push(RepairChange {
+            location,
+            message: message.into()
+        });
+
+    }
+
+    /// Removes the entries `keep` rejects, logging each one under `kind`
+    fn retain<F>(&mut self, kind: &str, entries: &mut Vec<String>, keep: F, reason: &str)
+    where
+        F: Fn(&str) -> bool
+    {
+
+        let mut removed = vec![];
+        entries.retain(|e| keep(e) || {
+            removed.push(e.clone());
+            false
+        });
+
+        for entry in removed {
+            self.change(format!("{} {}", kind, entry), format!("removed, {}", reason));
+        }
+
+    }
+
+    fn modules(&mut self, template: &CharacterTemplate) {
+
+        let Some(mut modules) = self.sheet.template.modules.take() else {
+            return;
+        };
+
+        let known = |m: &str| template.modules.iter().flatten().any(|tm| tm.name == m);
+        self.retain("module", &mut modules, known, "the template has no such module");
+
+        self.sheet.template.modules = Some(modules);
+
+    }
+
+    /// Rebuilds the attributes from the template's base sheet, keeping the sheet's values
+    fn attributes(&mut self, template: &CharacterTemplate) {
+
+        let mut sheet_attributes = std::mem::take(&mut self.sheet.attributes);
+        let mut attributes = vec![];
+
+        for mut base in template.get_base_character_sheet().attributes {
+
+            let Some(idx) = sheet_attributes.iter().position(|a| a.name == base.name) else {
+                self.change(format!("attribute {}", base.name), "added with 0 points");
+                attributes.push(base);
+                continue;
+            };
+
+            let sheet_attribute = sheet_attributes.remove(idx);
+            base.value = sheet_attribute.value;
+            self.skills(&mut base, sheet_attribute);
+
+            attributes.push(base);
+
+        }
+
+        for attribute in sheet_attributes {
+
+            let reason = match attributes.iter().any(|a| a.name == attribute.name) {
+                true  => "removed, listed more than once",
+                false => "removed, the template has no such attribute"
+            };
+
+            self.change(format!("attribute {}", attribute.name), reason);
+
+        }
+
+        self.sheet.attributes = attributes;
+
+    }
+
+    fn skills(&mut self, base: &mut SheetAttribute, sheet_attribute: SheetAttribute) {
+
+        let mut sheet_skills = sheet_attribute.skills.unwrap_or_default();
+
+        for skill in base.skills.iter_mut().flatten() {
+
+            match sheet_skills.iter().position(|s| s.name == skill.name) {
+                Some(idx) => skill.value = sheet_skills.remove(idx).value,
+                None      => self.change(format!("skill {}", skill.name), "added with 0 points")
+            }
+
+        }
+
+        for skill in sheet_skills {
+
+            let reason = match base.skills.iter().flatten().any(|s| s.name == skill.name) {
+                true  => "removed, listed more than once".to_string(),
+                false => format!("removed, {} has no such skill", base.name)
+            };
+
+            self.change(format!("skill {}", skill.name), reason);
+
+        }
+
+    }
+
+    fn clamp_points(&mut self, template: &CharacterTemplate) {
+
+        let attributes = &template.allotments.attributes;
+        let attribute_min = attributes.min_points_per_allotment.unwrap_or(0);
+        let attribute_max = attributes.max_points_per_allotment.unwrap_or(i64::MAX);
+
+        let skills = template.allotments.skills;
+        let skill_min = skills.and_then(|s| s.min_points_per_allotment).unwrap_or(0);
+        let skill_max = skills.and_then(|s| s.max_points_per_allotment).unwrap_or(i64::MAX);
+
+        let mut changes = vec![];
+
+        // Lowering values first frees up the points raising others to their minimums needs
+        for attribute in self.sheet.attributes.iter_mut() {
+
+            if let Some(change) = clamp(&mut attribute.value, 0, attribute_max) {
+                changes.push((format!("attribute {}", attribute.name), change));
+            }
+
+            for skill in attribute.skills.iter_mut().flatten() {
+
+                if let Some(change) = clamp(&mut skill.value, 0, skill_max) {
+                    changes.push((format!("skill {}", skill.name), change));
+                }
+
+            }
+
+        }
+
+        // Values the allotment can't afford to raise are left for the player
+        let mut attribute_points = attributes.given_points - self.sheet.attributes.iter().map(|a| a.value).sum::<i64>();
+        let mut skill_points = skills.map_or(i64::MAX, |s| s.given_points) - self.sheet.attributes
+            .iter()
+            .flat_map(|a| a.skills.iter().flatten())
+            .map(|s| s.value)
+            .sum::<i64>();
+
+        for attribute in self.sheet.attributes.iter_mut() {
+
+            let min = template
+                .find_attribute(&attribute.name)
+                .and_then(|a| a.min_points)
+                .map_or(attribute_min, |min| min.max(attribute_min));
+
+            if let Some(change) = raise(&mut attribute.value, min, &mut attribute_points) {
+                changes.push((format!("attribute {}", attribute.name), change));
+            }
+
+            for skill in attribute.skills.iter_mut().flatten() {
+
+                if let Some(change) = raise(&mut skill.value, skill_min, &mut skill_points) {
+                    changes.push((format!("skill {}", skill.name), change));
+                }
+
+            }
+
+        }
+
+        for (location, message) in changes {
+            self.change(location, message);
+        }
+
+    }
+
+    fn perks(&mut self, template: &CharacterTemplate) {
+
+        let Some(mut perks) = self.sheet.perks.take() else {
+            return;
+        };
+
+        let Some(template_perks) = &template.perks else {
+
+            self.retain("perk", &mut perks, |_| false, "the template has no perks");
+            self.sheet.perks = Some(perks);
+            return;
+
+        };
+
+        let cost = |name: &str| template_perks.iter().find(|p| p.name == name).map_or(0, |p| p.point_cost);
+
+        self.retain("perk", &mut perks, |p| template_perks.iter().any(|tp| tp.name == p), "the template has no such perk");
+
+        if let Some(points) = template.allotments.perks {
+
+            let max_perks = points.max_perks.unwrap_or(i64::MAX).max(0) as usize;
+            while perks.len() > max_perks {
+
+                let perk = perks.pop().unwrap();
+                self.change(format!("perk {}", perk), format!("removed, only {} perks can be selected", max_perks));
+
+            }
+
+            // Removing a perk that gives points back would only make things worse
+            while perks.iter().map(|p| cost(p)).sum::<i64>() > points.given_points {
+
+                let Some(idx) = perks.iter().rposition(|p| cost(p) > 0) else {
+                    break;
+                };
+
+                let perk = perks.remove(idx);
+                self.change(format!("perk {}", perk), "removed, not enough perk points");
+
+            }
+
+        }
+
+        self.sheet.perks = Some(perks);
+
+    }
+
+    fn weapon_proficiencies(&mut self, template: &CharacterTemplate) {
+
+        // Sheets that never had a category list keep not having one
+        let had_categories = self.sheet.weapon_categories.is_some();
+        let sheet_categories = |categories: Vec<String>| (had_categories || !categories.is_empty()).then_some(categories);
+
+        let mut weapons = std::mem::take(&mut self.sheet.weapon_proficiencies);
+        let mut categories = self.sheet.weapon_categories.take().unwrap_or_default();
+
+        let Some(proficiencies) = &template.weapon_proficiencies else {
+
+            self.retain("weapon", &mut weapons, |_| false, "the template has no weapon proficiencies");
+            self.retain("weapon category", &mut categories, |_| false, "the template has no weapon proficiencies");
+
+            self.sheet.weapon_proficiencies = weapons;
+            self.sheet.weapon_categories = sheet_categories(categories);
+            return;
+
+        };
+
+        self.retain("weapon", &mut weapons, |w| proficiencies.find_weapon(w).is_some(), "the template has no such weapon");
+        self.retain("weapon category", &mut categories, |c| proficiencies.find_category(c).is_some(), "the template has no such category");
+
+        self.sheet.weapon_proficiencies = weapons;
+        self.sheet.weapon_categories = Some(categories);
+
+        if let Some(points) = template.allotments.weapon_proficiencies {
+
+            // Counted and charged the way the validator does, a weapon one of the categories covers is free
+            let charged = |sheet: &CharacterSheet, entry: &str| {
+                proficiencies.find_weapon(entry).is_some_and(|w| proficiencies.charged_weapons(sheet).any(|c| c.weapon == w.weapon))
+            };
+            let weapon_cost   = |sheet: &CharacterSheet, entry: &str| {
+                if charged(sheet, entry) { proficiencies.find_weapon(entry).and_then(|w| w.point_cost).unwrap_or(0) } else { 0 }
+            };
+            let category_cost = |c: &str| proficiencies.find_category(c).and_then(|c| c.point_cost).unwrap_or(0);
+
+            let total = |sheet: &CharacterSheet| -> i64 {
+                let categories = sheet.weapon_categories.iter().flatten();
+                sheet.weapon_proficiencies.iter().map(|w| weapon_cost(sheet, w)).sum::<i64>() + categories.map(|c| category_cost(c)).sum::<i64>()
+            };
+
+            let max_proficiencies = points.max_proficiencies.unwrap_or(i64::MAX).max(0) as usize;
+
+            // Individual weapons go before whole categories
+            while proficiencies.selected_count(&self.sheet) > max_proficiencies {
+
+                let removed = match self.sheet.weapon_proficiencies.iter().rposition(|w| charged(&self.sheet, w)) {
+                    Some(idx) => ("weapon", self.sheet.weapon_proficiencies.remove(idx)),
+                    None      => ("weapon category", self.sheet.weapon_categories.as_mut().unwrap().pop().unwrap())
+                };
+
+                self.change(format!("{} {}", removed.0, removed.1), format!("removed, only {} weapon proficiencies can be selected", max_proficiencies));
+
+            }
+
+            // Removing a free weapon or category doesn't give any points back
+            while total(&self.sheet) > points.given_points {
+
+                let weapons = &self.sheet.weapon_proficiencies;
+                let categories = self.sheet.weapon_categories.as_deref().unwrap_or_default();
+
+                let removed = if let Some(idx) = weapons.iter().rposition(|w| weapon_cost(&self.sheet, w) > 0) {
+                    ("weapon", self.sheet.weapon_proficiencies.remove(idx))
+                } else if let Some(idx) = categories.iter().rposition(|c| category_cost(c) > 0) {
+                    ("weapon category", self.sheet.weapon_categories.as_mut().unwrap().remove(idx))
+                } else {
+                    break;
+                };
+
+                self.change(format!("{} {}", removed.0, removed.1), "removed, not enough weapon proficiency points");
+
+            }
+
+        }
+
+        let categories = self.sheet.weapon_categories.take().unwrap_or_default();
+        self.sheet.weapon_categories = sheet_categories(categories);
+
+    }
+
+    fn equipment(&mut self, template: &CharacterTemplate) {
+
+        let known = |item: &str| template.find_equipment(item).is_some();
+
+        if let Some(mut inventory) = self.sheet.inventory.take() {
+
+            self.retain("item", &mut inventory, known, "the template has no such equipment");
+            self.sheet.inventory = Some(inventory);
+
+        }
+
+        if let Some(mut equipped) = self.sheet.equipped.take() {
+
+            let inventory = self.sheet.inventory.clone().unwrap_or_default();
+
+            self.retain("equipped item", &mut equipped, known, "the template has no such equipment");
+            self.retain("equipped item", &mut equipped, |item| inventory.iter().any(|i| i == item), "it is not in the inventory");
+
+            self.sheet.equipped = Some(equipped);
+
+        }
+
+    }
+
+    fn resources(&mut self, template: &CharacterTemplate) {
+
+        let Some(mut resources) = self.sheet.resources.take() else {
+            return;
+        };
+
+        let mut changes = vec![];
+
+        resources.retain_mut(|sheet_resource| {
+
+            let location = format!("resource {}", sheet_resource.name);
+
+            let Some(resource) = template.find_resource(&sheet_resource.name) else {
+                changes.push((location, "removed, the template has no such resource".to_string()));
+                return false;
+            };
+
+            if resource.required.as_ref().is_some_and(|r| !r.is_met_by(&self.sheet)) {
+                changes.push((location, "removed, the sheet doesn't meet its requirements".to_string()));
+                return false;
+            }
+
+            if let Some(change) = clamp(&mut sheet_resource.value, 0, resource_maximum(template, &self.sheet, resource)) {
+                changes.push((location, change));
+            }
+
+            true
+
+        });
+
+        for (location, message) in changes {
+            self.change(location, message);
+        }
+
+        self.sheet.resources = Some(resources);
+
+    }
+
+}
+
+/// Clamps a value to min..=max, never below 0, describing the change if there was one
+fn clamp(value: &mut i64, min: i64, max: i64) -> Option<String> {
+
+    let min = min.max(0);
+    let clamped = (*value).clamp(min, max.max(min));
+    if clamped == *value {
+        return None;
+    }
+
+    let message = format!("changed from {} to {} points", value, clamped);
+    *value = clamped;
+
+    Some(message)
+
+}
+
+/// Raises a value to min when the remaining points cover it, describing the change if there was one
+fn raise(value: &mut i64, min: i64, remaining: &mut i64) -> Option<String> {
+
+    let needed = min - *value;
+    if needed <= 0 || needed > *remaining {
+        return None;
+    }
+
+    *remaining -= needed;
+    clamp(value, min, i64::MAX)
+
+}
+
+#[cfg(test)]
+mod sheet_repairer_tests {
+
+    use std::sync::LazyLock;
+    use crate::character_sheet::{SheetResource, SheetSkill};
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+    static STANDARD_TEMPLATE: LazyLock<CharacterTemplate> = LazyLock::new(|| {
+        json5::from_str(STANDARD_TEMPLATE_STR).unwrap()
+    });
+
+    const CHARACTER_SHEET_SAMPLE_STR: &str = include_str!("../character_sheet_sample.json");
+    static CHARACTER_SHEET_SAMPLE: LazyLock<CharacterSheet> = LazyLock::new(|| {
+        serde_json::from_str(CHARACTER_SHEET_SAMPLE_STR).unwrap()
+    });
+
+    fn locations(result: &RepairResult) -> Vec<&str> {
+        result.changes.iter().map(|c| c.location.as_str()).collect()
+    }
+
+    #[test]
+    fn valid_sheet_is_unchanged() {
+
+        let result = SheetRepairer::new(&STANDARD_TEMPLATE).repair(&CHARACTER_SHEET_SAMPLE);
+
+        assert!(result.changes.is_empty());
+        assert!(result.is_valid());
+        assert_eq!(result.sheet.as_json_str(), CHARACTER_SHEET_SAMPLE.as_json_str());
+
+    }
+
+    #[test]
+    fn unknown_entries_are_dropped_and_missing_ones_added() {
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        sheet.attributes.push(SheetAttribute { name: "Luck".to_string(), value: 1, skills: None });
+        sheet.attributes.retain(|a| a.name != "Agility");
+        sheet.attributes.iter_mut().find(|a| a.name == "Charisma").unwrap().skills.as_mut().unwrap().push(SheetSkill { name: "Juggling".to_string(), value: 1 });
+        sheet.perks.as_mut().unwrap().push("Telepathy".to_string());
+        sheet.weapon_proficiencies.push("Banana".to_string());
+        sheet.inventory = Some(vec!["Medkit".to_string(), "Jetpack".to_string()]);
+        sheet.equipped = Some(vec!["Light Armor".to_string()]);
+
+        let result = SheetRepairer::new(&STANDARD_TEMPLATE).repair(&sheet);
+        let locations = locations(&result);
+
+        for location in ["attribute Luck", "attribute Agility", "skill Juggling", "perk Telepathy", "weapon Banana", "item Jetpack", "equipped item Light Armor"] {
+            assert!(locations.contains(&location), "{} was not repaired", location);
+        }
+
+        let agility = result.sheet.attributes.iter().find(|a| a.name == "Agility").unwrap();
+        assert_eq!(agility.value, 0);
+        assert!(agility.skills.iter().flatten().all(|s| s.value == 0));
+
+        assert_eq!(result.sheet.inventory, Some(vec!["Medkit".to_string()]));
+        assert!(result.is_valid());
+
+    }
+
+    #[test]
+    fn values_are_clamped() {
+
+        let max = STANDARD_TEMPLATE.allotments.attributes.max_points_per_allotment.unwrap();
+
+        let mut sheet = STANDARD_TEMPLATE.get_base_character_sheet();
+        sheet.name = CHARACTER_SHEET_SAMPLE.name.clone();
+        sheet.attributes[0].value = max + 5;
+        sheet.attributes[1].value = -3;
+        sheet.resources = Some(vec![
+            SheetResource { name: "Force Points".to_string(), value: 2 },
+            SheetResource { name: "Luck".to_string(), value: 1 }
+        ]);
+
+        let result = SheetRepairer::new(&STANDARD_TEMPLATE).repair(&sheet);
+
+        assert_eq!(result.sheet.attributes[0].value, max);
+        assert_eq!(result.sheet.attributes[1].value, 0);
+        assert!(result.sheet.resources.as_ref().unwrap().is_empty());
+        assert_eq!(result.changes.len(), 4);
+        assert!(result.is_valid());
+
+    }
+
+    #[test]
+    fn values_are_raised_to_minimums() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.allotments.attributes.min_points_per_allotment = Some(1);
+
+        // The sample spends every attribute point, so raising its empty attributes would overspend
+        let result = SheetRepairer::new(&template).repair(&CHARACTER_SHEET_SAMPLE);
+
+        assert!(result.changes.is_empty());
+        assert!(matches!(result.remaining_error, Some(CharacterSheetError::TooFewAttributePoints { .. })));
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        let zeros = sheet.attributes.iter().filter(|a| a.value == 0).count() as i64;
+        sheet.attributes.iter_mut().find(|a| a.name == "Agility").unwrap().value -= zeros;
+
+        let result = SheetRepairer::new(&template).repair(&sheet);
+
+        assert!(result.sheet.attributes.iter().all(|a| a.value >= 1));
+        assert!(result.changes.iter().all(|c| c.message == "changed from 0 to 1 points"));
+        assert_eq!(result.changes.len() as i64, zeros);
+        assert!(result.is_valid());
+
+    }
+
+    #[test]
+    fn unaffordable_perks_are_removed() {
+
+        let given = STANDARD_TEMPLATE.allotments.perks.unwrap().given_points;
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        let affordable: Vec<String> = STANDARD_TEMPLATE.perks
+            .iter()
+            .flatten()
+            .filter(|p| p.point_cost > 0)
+            .map(|p| p.name.clone())
+            .collect();
+
+        sheet.perks = Some(affordable);
+
+        let result = SheetRepairer::new(&STANDARD_TEMPLATE).repair(&sheet);
+        let spent: i64 = STANDARD_TEMPLATE.sheet_perks(&result.sheet).map(|p| p.point_cost).sum();
+
+        assert!(spent <= given);
+        assert!(result.changes.iter().all(|c| c.location.starts_with("perk ")));
+        assert!(!matches!(result.remaining_error, Some(CharacterSheetError::NotEnoughPerkPoints(_))));
+
+    }
+
+    #[test]
+    fn only_weapons_that_cost_points_are_removed() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        template.allotments.weapon_proficiencies.as_mut().unwrap().given_points = -1;
+
+        let proficiencies = template.weapon_proficiencies.as_mut().unwrap();
+        let unarmed = proficiencies.categories.iter_mut().flat_map(|c| c.weapons.iter_mut()).find(|w| w.weapon == "Unarmed").unwrap();
+        unarmed.point_cost = None;
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        sheet.weapon_proficiencies = vec!["Unarmed".to_string(), "Pistol".to_string()];
+
+        let result = SheetRepairer::new(&template).repair(&sheet);
+
+        assert_eq!(result.sheet.weapon_proficiencies, vec!["Unarmed".to_string()]);
+        assert_eq!(locations(&result), vec!["weapon Pistol"]);
+
+        // Nothing left to remove
+        sheet.weapon_proficiencies = vec![];
+        let result = SheetRepairer::new(&template).repair(&sheet);
+
+        assert!(result.sheet.weapon_proficiencies.is_empty());
+        assert!(result.changes.is_empty());
+
+    }
+
+    #[test]
+    fn weapons_covered_by_a_category_are_not_charged() {
+
+        let mut template = STANDARD_TEMPLATE.clone();
+        let points = template.allotments.weapon_proficiencies.as_mut().unwrap();
+        points.given_points = 2;
+        points.max_proficiencies = Some(1);
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        sheet.weapon_proficiencies = vec!["Sword".to_string()];
+        sheet.weapon_categories = Some(vec!["Melee".to_string()]);
+
+        let result = SheetRepairer::new(&template).repair(&sheet);
+
+        assert!(result.changes.is_empty());
+        assert!(result.is_valid());
+
+    }
+
+    #[test]
+    fn overspent_attributes_are_left_for_the_player() {
+
+        let mut sheet = CHARACTER_SHEET_SAMPLE.clone();
+        let max = STANDARD_TEMPLATE.allotments.attributes.max_points_per_allotment.unwrap();
+        for attribute in sheet.attributes.iter_mut() {
+            attribute.value = max;
+        }
+
+        let result = SheetRepairer::new(&STANDARD_TEMPLATE).repair(&sheet);
+
+        assert!(matches!(result.remaining_error, Some(CharacterSheetError::AttributePointsExceeded(_))));
+
+    }
+
+}
@@ -0,0 +1,545 @@
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use thiserror::Error;
+
+use crate::character_roll::{CharacterRoll, CharacterRollResult, RollError, RollTarget};
+use crate::character_sheet::{CharacterSheet, SheetTemplate};
+use crate::character_sheet_validator::{CharacterSheetError, CharacterSheetValidator, ValidationMode};
+use crate::character_template::composition::CompositionError;
+use crate::character_template::CharacterTemplate;
+use crate::schema::prevalidate_template;
+use crate::InternalVersion;
+
+#[derive(Error, Debug, PartialEq)]
+pub enum RegistryError {
+    #[error("Failed to read {path}: {message}")]
+    Io {
+        path: String,
+        message: String
+    },
+    #[error("Failed to parse template {source_name}: {message}")]
+    Parse {
+        source_name: String,
+        message: String
+    },
+    #[error("Template {source_name} does not match the schema: {}", violations.join("; "))]
+    Schema {
+        source_name: String,
+        violations: Vec<String>
+    },
+    #[error("Template {name} is invalid: {message}")]
+    InvalidTemplate {
+        name: String,
+        message: String
+    },
+    #[error("Template {name} version {version} is already registered")]
+    DuplicateTemplate {
+        name: String,
+        version: String
+    },
+    #[error("Unknown template {0}")]
+    UnknownTemplate(String),
+    #[error("Template {name} has no version {version}")]
+    UnknownVersion {
+        name: String,
+        version: String
+    },
+    #[error("Sheet uses template {name} version {version}, but the latest is {latest}")]
+    OutdatedTemplate {
+        name: String,
+        version: String,
+        latest: String
+    },
+    #[error(transparent)]
+    Composition(#[from] CompositionError),
+    #[error(transparent)]
+    Sheet(#[from] CharacterSheetError),
+    #[error(transparent)]
+    Roll(#[from] RollError)
+}
+
+impl RegistryError {
+
+    /// A stable code for the error, sheet errors use the sheet error's code
+    pub fn code(&self) -> &'static str {
+
+        match self {
+            RegistryError::Io { .. }                => "io",
+            RegistryError::Parse { .. }             => "parse",
+            RegistryError::Schema { .. }            => "schema",
+            RegistryError::InvalidTemplate { .. }   => "invalid_template",
+            RegistryError::DuplicateTemplate { .. } => "duplicate_template",
+            RegistryError::UnknownTemplate(_)       => "unknown_template",
+            RegistryError::UnknownVersion { .. }    => "unknown_version",
+            RegistryError::OutdatedTemplate { .. }  => "outdated_template",
+            RegistryError::Composition(_)           => "composition",
+            RegistryError::Sheet(e)                 => e.code(),
+            RegistryError::Roll(_)                  => "roll"
+        }
+
+    }
+
+}
+
+/**
+ * 
+ * Holds every known template, indexed by name and version, so sheets can be validated
+ * and rolled without the caller finding the right template first. Templates that extend
+ * another are resolved against the parent version they pin in `extends_version`, or else the
+ * latest version of the parent when the child was added, so a newer parent doesn't change
+ * how existing sheets validate.
+ * 
+*/
+#[derive(Default)]
+pub struct TemplateRegistry {
+    templates: BTreeMap<(String, InternalVersion), CharacterTemplate>,
+    prevalidate: bool,
+    mode: ValidationMode
+}
+
+impl TemplateRegistry {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Checks templates added from strings or files against the JSON Schema first, reporting every mismatch
+    pub fn with_prevalidation(mut self) -> Self {
+
+        self.prevalidate = true;
+        self
+
+    }
+
+    /// How strictly sheets are checked for missing attributes and skills
+    pub fn with_validation_mode(mut self, mode: ValidationMode) -> Self {
+
+        self.mode = mode;
+        self
+
+    }
+
+    /**
+     *
+     * Adds a template, checking its formulas, ids and archetypes. Archetypes can use what a parent
+     * defines, so a child's are checked once its parent is in the registry, whichever is added
+     * first. The template isn't added when any of them fail.
+     *
+    */
+    pub fn add(&mut self, mut template: CharacterTemplate) -> Result<(), RegistryError> {
+
+        let invalid = |message: String| RegistryError::InvalidTemplate {
+            name: template.name.clone(),
+            message
+        };
+
+        template.check_formulas().map_err(|e| invalid(e.to_string()))?;
+        template.check_ids().map_err(|e| invalid(e.to_string()))?;
+
+        if template.extends.is_none() {
+            template.check_archetypes().map_err(|e| invalid(e.to_string()))?;
+        }
+
+        let key = (template.name.clone(), template.version.to_owned());
+        if self.templates.contains_key(&key) {
+            return Err(RegistryError::DuplicateTemplate {
+                name: template.name,
+                version: format_version(&key.1)
+            });
+        }
+
+        if let (Some(parent), None) = (&template.extends, &template.extends_version) {
+            template.extends_version = self.latest(parent).map(|p| p.version.to_owned());
+        }
+
+        // Children added before their parent build on the first version of it that's added
+        let mut pinned = vec![];
+        for (child_key, child) in self.templates.iter_mut() {
+
+            if child.extends.as_ref() == Some(&template.name) && child.extends_version.is_none() {
+                child.extends_version = Some(template.version.to_owned());
+                pinned.push(child_key.clone());
+            }
+
+        }
+
+        self.templates.insert(key.clone(), template);
+
+        if let Err(e) = self.check_extended_archetypes() {
+
+            self.templates.remove(&key);
+            for child_key in pinned {
+                self.templates.get_mut(&child_key).unwrap().extends_version = None;
+            }
+
+            return Err(e);
+
+        }
+
+        Ok(())
+
+    }
+
+    /// Checks the archetypes of every template that extends another and can now be resolved
+    fn check_extended_archetypes(&self) -> Result<(), RegistryError> {
+
+        for template in self.templates.values().filter(|t| t.extends.is_some()) {
+
+            // Templates whose parents haven't been added yet are checked when they are
+            let Ok(resolved) = self.resolve(template) else {
+                continue;
+            };
+
+            resolved.check_archetypes().map_err(|e| RegistryError::InvalidTemplate {
+                name: template.name.clone(),
+                message: e.to_string()
+            })?;
+
+        }
+
+        Ok(())
+
+    }
+
+    /// Adds a template from a JSON or JSON5 string, `source_name` is used in errors
+    pub fn add_str(&mut self, source_name: &str, template: &str) -> Result<(), RegistryError> {
+
+        let parse_error = |e: json5::Error| RegistryError::Parse {
+            source_name: source_name.to_string(),
+            message: e.to_string()
+        };
+
+        if self.prevalidate {
+
+            let value: serde_json::Value = json5::from_str(template).map_err(parse_error)?;
+            prevalidate_template(&value).map_err(|violations| RegistryError::Schema {
+                source_name: source_name.to_string(),
+                violations: violations.iter().map(|v| v.to_string()).collect()
+            })?;
+
+        }
+
+        let template: CharacterTemplate = json5::from_str(template).map_err(parse_error)?;
+        self.add(template)
+
+    }
+
+    /// Adds every .json and .json5 file in a directory, returning how many were added
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize, RegistryError> {
+
+        let dir = dir.as_ref();
+        let io_error = |path: &Path, e: std::io::Error| RegistryError::Io {
+            path: path.display().to_string(),
+            message: e.to_string()
+        };
+
+        let mut paths: Vec<_> = fs::read_dir(dir)
+            .map_err(|e| io_error(dir, e))?
+            .filter_map(|entry| entry.ok().map(|e| e.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "json" || ext == "json5"))
+            .collect();
+
+        // Load in a stable order so duplicate errors always name the same file
+        paths.sort();
+
+        for path in &paths {
+
+            let contents = fs::read_to_string(path).map_err(|e| io_error(path, e))?;
+            self.add_str(&path.display().to_string(), &contents)?;
+
+        }
+
+        Ok(paths.len())
+
+    }
+
+    pub fn get(&self, name: &str, version: &InternalVersion) -> Option<&CharacterTemplate> {
+        self.templates.get(&(name.to_string(), version.to_owned()))
+    }
+
+    /// The highest version of a template
+    pub fn latest(&self, name: &str) -> Option<&CharacterTemplate> {
+        self.versions(name).last().copied()
+    }
+
+    /// Every version of a template, oldest first
+    pub fn versions(&self, name: &str) -> Vec<&CharacterTemplate> {
+
+        self.templates
+            .iter()
+            .filter(|((n, _), _)| n == name)
+            .map(|(_, t)| t)
+            .collect()
+
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+
+        let mut names: Vec<&str> = self.templates.keys().map(|(n, _)| n.as_str()).collect();
+        names.dedup();
+        names
+
+    }
+
+    /// The template, with anything it extends resolved, that a sheet was made with
+    pub fn template_for(&self, sheet_template: &SheetTemplate) -> Result<CharacterTemplate, RegistryError> {
+
+        let name = &sheet_template.name;
+        let latest = self.latest(name).ok_or_else(|| RegistryError::UnknownTemplate(name.clone()))?;
+
+        let Some(template) = self.get(name, &sheet_template.version) else {
+
+            let version = format_version(&sheet_template.version);
+
+            if sheet_template.version < latest.version {
+                return Err(RegistryError::OutdatedTemplate {
+                    name: name.clone(),
+                    version,
+                    latest: format_version(&latest.version)
+                });
+            }
+
+            return Err(RegistryError::UnknownVersion { name: name.clone(), version });
+
+        };
+
+        Ok(self.resolve(template)?)
+
+    }
+
+    /// Flattens a template against the parent versions it was pinned to
+    fn resolve(&self, template: &CharacterTemplate) -> Result<CharacterTemplate, CompositionError> {
+
+        template.resolve(|parent, version| match version {
+            Some(version) => self.get(parent, version),
+            None          => self.latest(parent)
+        })
+
+    }
+
+    pub fn validate(&self, sheet: &CharacterSheet) -> Result<(), RegistryError> {
+
+        let template = self.template_for(&sheet.template)?;
+        CharacterSheetValidator::new(&template, sheet).with_mode(self.mode).check()?;
+
+        Ok(())
+
+    }
+
+    pub fn roll(&self, sheet: &CharacterSheet, target: RollTarget) -> Result<CharacterRollResult, RegistryError> {
+
+        let template = self.template_for(&sheet.template)?.for_sheet(sheet)?;
+        Ok(CharacterRoll::new(&template, sheet, target).roll()?)
+
+    }
+
+}
+
+/// Formats a version as "1.0.0"
+pub fn format_version(version: &InternalVersion) -> String {
+    version.iter().map(|v| v.to_string()).collect::<Vec<_>>().join(".")
+}
+
+#[cfg(test)]
+mod template_registry_tests {
+
+    use super::*;
+
+    const STANDARD_TEMPLATE_STR: &str = include_str!("../standard.json5");
+
+    const VARIANT_TEMPLATE_STR: &str = r#"{
+        name: "Standard Lite",
+        extends: "Standard",
+        version: [1, 0, 0],
+        description: "Standard without the Veteran perk",
+        base_health: 10,
+        base_armor_class: 7,
+        allotments: {
+            attributes: { given_points: 15, max_points_per_allotment: 5 },
+            skills: { given_points: 20, max_points_per_allotment: 10 },
+            perks: { given_points: 3, max_perks: 4 }
+        },
+        remove: {
+            perks: ["Veteran"]
+        }
+    }"#;
+
+    fn registry() -> TemplateRegistry {
+
+        let mut registry = TemplateRegistry::new();
+        registry.add_str("standard.json5", STANDARD_TEMPLATE_STR).unwrap();
+        registry.add_str("variant.json5", VARIANT_TEMPLATE_STR).unwrap();
+
+        let mut older: CharacterTemplate = json5::from_str(STANDARD_TEMPLATE_STR).unwrap();
+        older.version = [0, 9, 0];
+        registry.add(older).unwrap();
+
+        registry
+
+    }
+
+    fn sheet(registry: &TemplateRegistry, name: &str) -> CharacterSheet {
+
+        let template = registry.resolve(registry.latest(name).unwrap()).unwrap();
+        let mut sheet = template.get_base_character_sheet();
+        sheet.name = "Registry Test".to_string();
+        sheet
+
+    }
+
+    #[test]
+    fn index_by_name_and_version() {
+
+        let registry = registry();
+
+        assert_eq!(registry.names(), vec!["Standard", "Standard Lite"]);
+        assert_eq!(registry.versions("Standard").len(), 2);
+        assert_eq!(registry.latest("Standard").unwrap().version, [1, 0, 0]);
+        assert!(registry.get("Standard", &[0, 9, 0]).is_some());
+
+        let mut registry = registry;
+        assert_eq!(
+            registry.add_str("standard.json5", STANDARD_TEMPLATE_STR),
+            Err(RegistryError::DuplicateTemplate { name: "Standard".to_string(), version: "1.0.0".to_string() })
+        );
+        assert!(matches!(registry.add_str("broken.json5", "{ name: "), Err(RegistryError::Parse { .. })));
+
+    }
+
+    #[test]
+    fn template_for_sheet() {
+
+        let registry = registry();
+
+        let lite = registry.template_for(&sheet(&registry, "Standard Lite").template).unwrap();
+        assert!(lite.find_perk("Veteran").is_none());
+        assert!(lite.find_perk("Small Frame").is_some());
+
+        let mut sheet = sheet(&registry, "Standard");
+        sheet.template.version = [0, 9, 0];
+        assert!(registry.template_for(&sheet.template).is_ok());
+
+        sheet.template.version = [0, 1, 0];
+        assert_eq!(registry.template_for(&sheet.template).err(), Some(RegistryError::OutdatedTemplate {
+            name: "Standard".to_string(),
+            version: "0.1.0".to_string(),
+            latest: "1.0.0".to_string()
+        }));
+
+        sheet.template.version = [2, 0, 0];
+        assert!(matches!(registry.template_for(&sheet.template), Err(RegistryError::UnknownVersion { .. })));
+
+        sheet.template.name = "Homebrew".to_string();
+        assert_eq!(registry.template_for(&sheet.template).err(), Some(RegistryError::UnknownTemplate("Homebrew".to_string())));
+
+    }
+
+    #[test]
+    fn parents_stay_pinned() {
+
+        let mut registry = registry();
+        assert_eq!(registry.get("Standard Lite", &[1, 0, 0]).unwrap().extends_version, Some([1, 0, 0]));
+
+        // A newer Standard without Charismatic doesn't change how Standard Lite sheets validate
+        let mut newer: CharacterTemplate = json5::from_str(STANDARD_TEMPLATE_STR).unwrap();
+        newer.version = [2, 0, 0];
+        newer.perks.as_mut().unwrap().retain(|p| p.name != "Charismatic");
+        newer.archetypes = None;
+        registry.add(newer).unwrap();
+
+        let mut sheet = sheet(&registry, "Standard Lite");
+        sheet.perks = Some(vec!["Charismatic".to_string()]);
+        assert_eq!(registry.validate(&sheet), Ok(()));
+
+        // A child added before its parent is pinned once the parent arrives
+        let mut registry = TemplateRegistry::new();
+        registry.add_str("variant.json5", VARIANT_TEMPLATE_STR).unwrap();
+        registry.add_str("standard.json5", STANDARD_TEMPLATE_STR).unwrap();
+        assert_eq!(registry.get("Standard Lite", &[1, 0, 0]).unwrap().extends_version, Some([1, 0, 0]));
+
+    }
+
+    #[test]
+    fn validate_and_roll() {
+
+        let registry = registry();
+
+        let mut sheet = sheet(&registry, "Standard Lite");
+        assert_eq!(registry.validate(&sheet), Ok(()));
+        assert!(registry.roll(&sheet, RollTarget::Skill("Stealth".to_string())).is_ok());
+
+        sheet.perks = Some(vec!["Veteran".to_string()]);
+        assert_eq!(registry.validate(&sheet), Err(RegistryError::Sheet(CharacterSheetError::PerkNotAllowed("Veteran".to_string()))));
+
+    }
+
+    #[test]
+    fn archetypes_checked_once_resolved() {
+
+        let mut registry = registry();
+        let sheet = sheet(&registry, "Standard Lite");
+        let template = registry.template_for(&sheet.template).unwrap();
+        assert!(template.sheet_from_archetype("Smuggler").is_ok());
+
+        // The inherited Smuggler takes a perk this template removes
+        let variant = VARIANT_TEMPLATE_STR
+            .replace("Standard Lite", "Standard Plain")
+            .replace(r#"perks: ["Veteran"]"#, r#"perks: ["Charismatic"]"#);
+
+        let invalid = Err(RegistryError::InvalidTemplate {
+            name: "Standard Plain".to_string(),
+            message: "Archetype Smuggler refers to unknown perk Charismatic".to_string()
+        });
+
+        assert_eq!(registry.add_str("plain.json5", &variant), invalid);
+        assert!(registry.latest("Standard Plain").is_none());
+
+        // Added before its parent, the child is checked when the parent is added
+        let mut registry = TemplateRegistry::new();
+        registry.add_str("plain.json5", &variant).unwrap();
+
+        assert_eq!(registry.add_str("standard.json5", STANDARD_TEMPLATE_STR), invalid);
+        assert!(registry.latest("Standard").is_none());
+        assert!(registry.latest("Standard Plain").unwrap().extends_version.is_none());
+
+    }
+
+    #[test]
+    fn prevalidation() {
+
+        let broken = STANDARD_TEMPLATE_STR.replacen("base_health: 10", "base_health: \"ten\"", 1);
+
+        let mut registry = TemplateRegistry::new();
+        assert!(matches!(registry.add_str("broken.json5", &broken), Err(RegistryError::Parse { .. })));
+
+        let mut registry = TemplateRegistry::new().with_prevalidation();
+        let Err(RegistryError::Schema { violations, .. }) = registry.add_str("broken.json5", &broken) else {
+            panic!("expected a schema error");
+        };
+        assert!(violations[0].starts_with("/base_health"));
+
+        assert_eq!(registry.add_str("standard.json5", STANDARD_TEMPLATE_STR), Ok(()));
+
+    }
+
+    #[test]
+    fn load_directory() {
+
+        let dir = std::env::temp_dir().join(format!("character-sheet-registry-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("standard.json5"), STANDARD_TEMPLATE_STR).unwrap();
+        fs::write(dir.join("variant.json5"), VARIANT_TEMPLATE_STR).unwrap();
+        fs::write(dir.join("notes.txt"), "not a template").unwrap();
+
+        let mut registry = TemplateRegistry::new();
+        let loaded = registry.load_dir(&dir);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(loaded, Ok(2));
+        assert!(registry.latest("Standard Lite").is_some());
+
+    }
+
+}
@@ -0,0 +1,212 @@
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Output};
+
+use serde_json::Value;
+
+const TEMPLATE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/standard.json5");
+const SAMPLE: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/character_sheet_sample.json");
+
+fn run(args: &[&str]) -> Output {
+
+    Command::new(env!("CARGO_BIN_EXE_character-sheet"))
+        .args(args)
+        .output()
+        .expect("failed to run character-sheet")
+
+}
+
+fn stdout_json(output: &Output) -> Value {
+    serde_json::from_slice(&output.stdout).expect("stdout isn't JSON")
+}
+
+/// A file in a directory of its own under the system temp directory
+fn temp_file(test: &str, name: &str, contents: &str) -> PathBuf {
+
+    let dir = std::env::temp_dir().join(format!("character-sheet-cli-{}-{}", test, std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+
+    let path = dir.join(name);
+    fs::write(&path, contents).unwrap();
+
+    path
+
+}
+
+fn invalid_sheet(test: &str) -> PathBuf {
+
+    let sample = fs::read_to_string(SAMPLE).unwrap();
+    let invalid = sample.replacen(r#""name": "Strength", "value": 0"#, r#""name": "Strength", "value": 9"#, 1);
+    assert_ne!(sample, invalid);
+
+    temp_file(test, "invalid.json", &invalid)
+
+}
+
+#[test]
+fn validate_exit_codes_and_json() {
+
+    let valid = run(&["validate", TEMPLATE, SAMPLE]);
+    assert_eq!(valid.status.code(), Some(0));
+    assert!(String::from_utf8_lossy(&valid.stdout).contains(": ok"));
+
+    let invalid = invalid_sheet("validate");
+    let output = run(&["validate", TEMPLATE, SAMPLE, invalid.to_str().unwrap(), "--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+
+    let reports = stdout_json(&output);
+    let reports = reports.as_array().unwrap();
+    assert_eq!(reports.len(), 2);
+    assert_eq!(reports[0]["valid"], true);
+    assert_eq!(reports[0]["code"], Value::Null);
+    assert_eq!(reports[1]["valid"], false);
+    assert!(reports[1]["code"].is_string());
+
+}
+
+#[test]
+fn lenient_accepts_missing_attributes() {
+
+    let mut sheet: Value = serde_json::from_str(&fs::read_to_string(SAMPLE).unwrap()).unwrap();
+    sheet["attributes"].as_array_mut().unwrap().retain(|a| a["name"] != "Strength");
+    let path = temp_file("lenient", "missing.json", &sheet.to_string());
+
+    assert_eq!(run(&["validate", TEMPLATE, path.to_str().unwrap()]).status.code(), Some(1));
+    assert_eq!(run(&["validate", TEMPLATE, path.to_str().unwrap(), "--lenient"]).status.code(), Some(0));
+
+}
+
+#[test]
+fn unreadable_template_exits_with_2() {
+
+    let output = run(&["validate", "missing.json5", SAMPLE]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8_lossy(&output.stderr).starts_with("error:"));
+
+}
+
+#[test]
+fn batch_summarizes_a_directory() {
+
+    let invalid = invalid_sheet("batch");
+    fs::copy(SAMPLE, invalid.with_file_name("sample.json")).unwrap();
+
+    let output = run(&["batch", "--template", TEMPLATE, invalid.parent().unwrap().to_str().unwrap(), "--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    stdout_json(&output);
+
+}
+
+#[test]
+fn lint_template_reports_json() {
+
+    let output = run(&["lint-template", TEMPLATE, "--format", "json"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert!(stdout_json(&output).is_array());
+
+    let broken = temp_file("lint", "broken.json5", "{ name: ");
+    let output = run(&["lint-template", broken.to_str().unwrap(), "--format", "json"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stdout_json(&output)[0]["level"], "Error");
+
+}
+
+#[test]
+fn seeded_rolls_repeat() {
+
+    let roll = || run(&["roll", "--template", TEMPLATE, SAMPLE, "Persuasion", "--seed", "7", "--format", "json"]);
+
+    let first = roll();
+    assert_eq!(first.status.code(), Some(0));
+    assert_eq!(stdout_json(&first), stdout_json(&roll()));
+
+    let attack = run(&["roll", "--template", TEMPLATE, SAMPLE, "Pistol", "--attack", "--seed", "7"]);
+    assert_eq!(attack.status.code(), Some(0));
+
+    let unknown = run(&["roll", "--template", TEMPLATE, SAMPLE, "Basket Weaving"]);
+    assert_eq!(unknown.status.code(), Some(2));
+
+}
+
+#[test]
+fn budget_exit_codes_and_json() {
+
+    let output = run(&["budget", "--template", TEMPLATE, SAMPLE, "--format", "json"]);
+    assert_eq!(output.status.code(), Some(0));
+    stdout_json(&output);
+
+    let sample = fs::read_to_string(SAMPLE).unwrap();
+    let overspent = temp_file("budget", "overspent.json", &sample.replacen(r#""name": "Medicine", "value": 10"#, r#""name": "Medicine", "value": 30"#, 1));
+    let output = run(&["budget", "--template", TEMPLATE, overspent.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(1));
+
+    // Perks from the sheet's modules are charged too
+    let mut sheet: Value = serde_json::from_str(&sample).unwrap();
+    sheet["template"]["modules"] = serde_json::json!(["Jedi Academy"]);
+    sheet["perks"] = serde_json::json!(["Small Frame", "Lightsaber Training"]);
+    let with_module = temp_file("budget", "module.json", &sheet.to_string());
+
+    let output = run(&["budget", "--template", TEMPLATE, with_module.to_str().unwrap(), "--format", "json"]);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(stdout_json(&output)["perks"]["spent"], 2);
+
+}
+
+#[test]
+fn generated_and_archetype_sheets_validate() {
+
+    let generated = run(&["generate", TEMPLATE, "--seed", "7", "--focus", "Agility"]);
+    assert_eq!(generated.status.code(), Some(0));
+    assert_eq!(stdout_json(&generated), stdout_json(&run(&["generate", TEMPLATE, "--seed", "7", "--focus", "Agility"])));
+
+    let path = temp_file("generate", "generated.json", &String::from_utf8(generated.stdout).unwrap());
+    assert_eq!(run(&["validate", TEMPLATE, path.to_str().unwrap()]).status.code(), Some(0));
+
+    let new = run(&["new", TEMPLATE, "--archetype", "Smuggler", "--name", "Kira Vance"]);
+    assert_eq!(new.status.code(), Some(0));
+    assert_eq!(stdout_json(&new)["name"], "Kira Vance");
+
+    let unknown = run(&["new", TEMPLATE, "--archetype", "Bounty Hunter"]);
+    assert_eq!(unknown.status.code(), Some(2));
+
+}
+
+#[test]
+fn optimize_prints_sheets() {
+
+    let sheet = run(&["new", TEMPLATE, "--archetype", "Smuggler", "--name", "Kira Vance"]);
+    let path = temp_file("optimize", "kira.json", &String::from_utf8(sheet.stdout).unwrap());
+
+    let best = run(&["optimize", "--template", TEMPLATE, path.to_str().unwrap(), "Persuasion"]);
+    assert_eq!(best.status.code(), Some(0));
+    assert_eq!(stdout_json(&best)["name"], "Kira Vance");
+
+    let front = run(&["optimize", "--template", TEMPLATE, path.to_str().unwrap(), "Agility", "Strength", "--pareto"]);
+    assert_eq!(front.status.code(), Some(0));
+    assert!(!stdout_json(&front).as_array().unwrap().is_empty());
+
+}
+
+#[test]
+fn convert_keeps_key_order() {
+
+    let input = temp_file("convert", "ordered.json5", "{ zebra: 1, apple: { mango: [true, null], banana: 'b' }, // comment\n middle: 2.5 }");
+
+    let output = run(&["convert", input.to_str().unwrap()]);
+    assert_eq!(output.status.code(), Some(0));
+
+    let converted = fs::read_to_string(input.with_extension("json")).unwrap();
+    let keys: Vec<usize> = ["zebra", "apple", "mango", "banana", "middle"]
+        .iter()
+        .map(|key| converted.find(&format!("\"{}\"", key)).unwrap())
+        .collect();
+    assert!(keys.windows(2).all(|pair| pair[0] < pair[1]), "{}", converted);
+
+    let value: Value = serde_json::from_str(&converted).unwrap();
+    assert_eq!(value["apple"]["mango"][1], Value::Null);
+    assert_eq!(value["middle"], 2.5);
+
+    let overwrite = run(&["convert", input.to_str().unwrap(), "--output", input.to_str().unwrap()]);
+    assert_eq!(overwrite.status.code(), Some(2));
+
+}